@@ -0,0 +1,151 @@
+//! Compares filling templates by walking the `Partial`s of a
+//! `DocumentTemplate` with filling their `CompiledTemplate`, and parsing
+//! templates with loading them from a `TemplateCache`. Run with
+//! `cargo bench`.
+
+use docugen::core::cache::TemplateCache;
+use docugen::core::document::{DocumentTemplate, RenderOptions};
+use docugen::core::loader::{resolve_includes_listed, DirectorySource};
+use docugen::core::metadata::TemplateFile;
+use docugen::core::parser::parse_template;
+use docugen::core::value::Value;
+use docugen::data::filters;
+use serde_json::json;
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Number of patients in a batch.
+const PATIENTS: usize = 20_000;
+/// Each measurement is repeated, keeping the fastest run.
+const ROUNDS: usize = 5;
+
+fn main() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let source = DirectorySource::new(root.join("templates"));
+    let patients = (0..PATIENTS).map(patient).collect::<Vec<_>>();
+    let options = RenderOptions {
+        filters: filters::registry(),
+        ..RenderOptions::default()
+    };
+
+    let letter = read(&root.join("template.template"), &source);
+    let compiled = letter.compile();
+    let letters = patients
+        .iter()
+        .map(|patient| vec![("patient", patient.clone())].into_iter().collect())
+        .collect::<Vec<Value>>();
+    compare(
+        &format!("letter per patient, {} patients", PATIENTS),
+        ("Vec<Partial> walk", &mut || {
+            for data in &letters {
+                black_box(letter.saturate_with(data, &options).unwrap());
+            }
+        }),
+        ("compiled", &mut || {
+            for data in &letters {
+                black_box(compiled.saturate_with(data, &options).unwrap());
+            }
+        }),
+    );
+
+    let list = read(
+        &root.join("templates/patient_birthdates_list.template"),
+        &source,
+    );
+    let compiled = list.compile();
+    let data = vec![("patients", patients.clone())].into_iter().collect();
+    compare(
+        &format!("list of {} patients", PATIENTS),
+        ("Vec<Partial> walk", &mut || {
+            black_box(list.saturate_with(&data, &options).unwrap());
+        }),
+        ("compiled", &mut || {
+            black_box(compiled.saturate_with(&data, &options).unwrap());
+        }),
+    );
+
+    let file = fs::read_to_string(root.join("template.template")).unwrap();
+    let raw = TemplateFile::parse(&file).unwrap().template;
+    let cache_directory = std::env::temp_dir()
+        .join(format!("docugen-bench-cache-{}", std::process::id()));
+    let cache = TemplateCache::new(cache_directory.clone());
+    let (template, included) =
+        resolve_includes_listed(&parse_template(raw).unwrap(), &source)
+            .unwrap();
+    cache
+        .store(raw, &source, &included, &template.compile())
+        .unwrap();
+    compare(
+        "loading the letter 1000 times",
+        ("parse and compile", &mut || {
+            for _ in 0..1000 {
+                let template = parse_template(raw).unwrap();
+                let (template, _) =
+                    resolve_includes_listed(&template, &source).unwrap();
+                black_box(template.compile());
+            }
+        }),
+        ("cache", &mut || {
+            for _ in 0..1000 {
+                black_box(cache.load(raw, &source).unwrap());
+            }
+        }),
+    );
+    fs::remove_dir_all(&cache_directory).unwrap();
+}
+
+fn read(path: &Path, source: &DirectorySource) -> DocumentTemplate {
+    let raw = fs::read_to_string(path).unwrap();
+    let raw = TemplateFile::parse(&raw).unwrap().template;
+    let (template, _) =
+        resolve_includes_listed(&parse_template(raw).unwrap(), source).unwrap();
+    template
+}
+
+fn patient(index: usize) -> Value {
+    json!({
+        "resourceType": "Patient",
+        "id": format!("patient-{}", index),
+        "name": [{
+            "use": "official",
+            "family": format!("Smith{}", index),
+            "given": ["Jane", "Anne"],
+        }],
+        "gender": "female",
+        "birthDate": format!("{}-03-04", 1950 + index % 70),
+    })
+    .into()
+}
+
+/// Time `before` and `after`, each given with a label, and report how much
+/// faster `after` is.
+fn compare(
+    name: &str,
+    before: (&str, &mut dyn FnMut()),
+    after: (&str, &mut dyn FnMut()),
+) {
+    let (before_label, before) = (before.0, fastest(before.1));
+    let (after_label, after) = (after.0, fastest(after.1));
+
+    println!("{}", name);
+    println!("  {:<20}{:>10.2?}", before_label, before);
+    println!(
+        "  {:<20}{:>10.2?} ({:.2}x faster)",
+        after_label,
+        after,
+        before.as_secs_f64() / after.as_secs_f64()
+    );
+}
+
+fn fastest(f: &mut dyn FnMut()) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
@@ -1,4 +1,4 @@
-use clap::{App, AppSettings, Arg};
+use clap::{App, AppSettings, Arg, SubCommand};
 
 /// We devise a CLI interface for docugen.
 ///
@@ -7,7 +7,13 @@ use clap::{App, AppSettings, Arg};
 /// We read a template file from the filesystem, e.g. from
 /// `./templates/patient_birthdates_list.template`.
 ///
-/// We save the output to either `stdout` or a user-specified output file.
+/// We save the output to either `stdout` or files in a user-specified output
+/// directory, named by the front matter of the template.
+///
+/// The endpoint may be left out when the front matter of the template names
+/// the type of resources filling it.
+///
+/// The `lint` subcommand checks a template without fetching any data.
 pub fn cli<'a, 'b>() -> App<'a, 'b> {
     let config_arg = Arg::with_name("config")
         .short("c")
@@ -17,8 +23,7 @@ pub fn cli<'a, 'b>() -> App<'a, 'b> {
         .takes_value(true);
 
     let endpoint_arg = Arg::with_name("ENDPOINT")
-        .help("Select the endpoint to use, e.g. `/api/Patient`. Configure the IP address and port in the configuration file.")
-        .required(true)
+        .help("Select the endpoint to use, e.g. `/api/Patient`. Configure the IP address and port in the configuration file. Defaults to the endpoint of the `resource_type` set in the front matter of the template.")
         .index(1);
 
     let template_arg = Arg::with_name("TEMPLATE")
@@ -26,18 +31,158 @@ pub fn cli<'a, 'b>() -> App<'a, 'b> {
         .required(true)
         .index(2);
 
+    let combined_arg = Arg::with_name("combined")
+        .long("combined")
+        .help("Fill the template once with all patients, listed under the `patients` tag, instead of once per patient under the `patient` tag.");
+
+    let format_arg = Arg::with_name("format")
+        .long("format")
+        .value_name("FORMAT")
+        .possible_values(&["text", "html", "latex", "csv"])
+        .help("Sets the format of the filled documents, which sets how data is escaped. Defaults to the format set in the configuration file, or the format named by the extension of the template, e.g. `letter.html.template`.")
+        .takes_value(true);
+
+    let locale_arg = Arg::with_name("locale")
+        .long("locale")
+        .value_name("LOCALE")
+        .help("Sets the locale of the documents, e.g. `fr` or `en-US`, which picks the localized variant of the template, e.g. `letter.fr.template`, its messages and how dates are written. Defaults to the locale set in the configuration file, or `en-GB`.")
+        .takes_value(true);
+
+    let output_arg = Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .value_name("DIRECTORY")
+        .help("Write each document to a file in DIRECTORY, named by the `filename` set in the front matter of the template, instead of to stdout.")
+        .takes_value(true);
+
     let verbosity_arg = Arg::with_name("v")
         .short("v")
         .multiple(true)
         .help("Sets the level of logging verbosity.");
 
+    let lint_subcommand = SubCommand::with_name("lint")
+        .about("Check a template without fetching any data: list the tags it needs and report problems. Exits with a non-zero status if the template cannot be used.")
+        .arg(
+            Arg::with_name("TEMPLATE")
+                .help("The template to check.")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("templates")
+                .long("templates")
+                .value_name("DIRECTORY")
+                .help("Sets the directory included templates are read from. Defaults to the directory set in the configuration file if there is one, or the directory containing the template.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("deny-warnings")
+                .long("deny-warnings")
+                .help("Also exit with a non-zero status if there are warnings."),
+        )
+        .arg(
+            Arg::with_name("combined")
+                .long("combined")
+                .help("Check the template against the data of all patients, as filled with --combined."),
+        );
+
     App::new("FHIRworks2020 docugen")
             .version("0.1.0")
             .author("Jieyou Xu (Joe) <jieyou.xu.18@ucl.ac.uk>")
             .about("Small CLI tool to fetch data from a FHIR API endpoint and fill out a document template.")
             .setting(AppSettings::ColoredHelp)
+            .setting(AppSettings::SubcommandsNegateReqs)
+            .setting(AppSettings::AllowMissingPositional)
             .arg(&config_arg)
             .arg(&endpoint_arg)
             .arg(&template_arg)
+            .arg(&combined_arg)
+            .arg(&format_arg)
+            .arg(&locale_arg)
+            .arg(&output_arg)
             .arg(&verbosity_arg)
+            .subcommand(lint_subcommand)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_arguments() {
+        let matches = cli()
+            .get_matches_from_safe(vec![
+                "docugen",
+                "/api/Patient",
+                "letter.template",
+                "--combined",
+                "--format",
+                "html",
+                "--locale",
+                "fr",
+            ])
+            .unwrap();
+
+        assert_eq!(Some("/api/Patient"), matches.value_of("ENDPOINT"));
+        assert_eq!(Some("letter.template"), matches.value_of("TEMPLATE"));
+        assert!(matches.is_present("combined"));
+        assert_eq!(Some("html"), matches.value_of("format"));
+        assert_eq!(Some("fr"), matches.value_of("locale"));
+        assert!(matches.subcommand_matches("lint").is_none());
+    }
+
+    #[test]
+    fn test_endpoint_from_front_matter() {
+        let matches = cli()
+            .get_matches_from_safe(vec![
+                "docugen",
+                "letter.template",
+                "--output",
+                "letters",
+            ])
+            .unwrap();
+
+        assert_eq!(None, matches.value_of("ENDPOINT"));
+        assert_eq!(Some("letter.template"), matches.value_of("TEMPLATE"));
+        assert_eq!(Some("letters"), matches.value_of("output"));
+    }
+
+    #[test]
+    fn test_lint_subcommand() {
+        let matches = cli()
+            .get_matches_from_safe(vec![
+                "docugen",
+                "lint",
+                "letter.template",
+                "--templates",
+                "templates",
+            ])
+            .unwrap();
+
+        let lint = matches.subcommand_matches("lint").unwrap();
+        assert_eq!(Some("letter.template"), lint.value_of("TEMPLATE"));
+        assert_eq!(Some("templates"), lint.value_of("templates"));
+        assert!(!lint.is_present("deny-warnings"));
+        assert!(!lint.is_present("combined"));
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        assert!(cli()
+            .get_matches_from_safe(vec![
+                "docugen",
+                "/api/Patient",
+                "letter.template",
+                "--format",
+                "pdf",
+            ])
+            .is_err());
+    }
+
+    #[test]
+    fn test_lint_requires_template() {
+        assert!(cli()
+            .get_matches_from_safe(vec!["docugen", "lint"])
+            .is_err());
+    }
 }
@@ -1,21 +1,19 @@
+use crate::core::document::MissingTagPolicy;
+use crate::core::escape::Escape;
+use crate::core::parser::Delimiters;
+use crate::data::locale::Locale;
 use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
 
 /// Configuration for the `Docugen` tool.
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct DocugenConfig {
     pub web_api: WebApiConfig,
     pub logging: LoggingConfig,
-}
-
-impl Default for DocugenConfig {
-    fn default() -> Self {
-        DocugenConfig {
-            web_api: WebApiConfig::default(),
-            logging: LoggingConfig::default(),
-        }
-    }
+    #[serde(default)]
+    pub templates: TemplateConfig,
 }
 
 /// Configuration for the intermediate Web API.
@@ -37,6 +35,43 @@ impl Default for WebApiConfig {
     }
 }
 
+/// Configuration for reading templates.
+#[derive(Debug, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateConfig {
+    /// Directory that included templates are read from. Defaults to the
+    /// directory containing the template being filled.
+    #[serde(default)]
+    pub directory: Option<PathBuf>,
+    /// How tags without data are filled: `"strict"` (the default),
+    /// `"lenient-empty"` or `"lenient-placeholder"`.
+    #[serde(default)]
+    pub missing_tags: MissingTagPolicy,
+    /// Directory that compiled templates are cached in, so that unchanged
+    /// templates are not parsed again on every run. Templates are not cached
+    /// by default.
+    #[serde(default)]
+    pub cache_directory: Option<PathBuf>,
+    /// Format of the filled documents, which sets how data is escaped:
+    /// `"text"`, `"html"`, `"latex"` or `"csv"`. Defaults to the format named
+    /// by the extension of the template, e.g. `letter.html.template`.
+    #[serde(default)]
+    pub format: Option<Escape>,
+    /// Delimiters of the tags of templates, e.g. `["<%", "%>"]` for templates
+    /// full of braces. Defaults to `["{{", "}}"]`.
+    #[serde(default)]
+    pub delimiters: Delimiters,
+    /// Locale of the filled documents, e.g. `"fr"`, which picks the localized
+    /// variants of templates, e.g. `letter.fr.template`, the message
+    /// catalogue and how dates are written. Defaults to `"en-GB"`.
+    #[serde(default)]
+    pub locale: Option<Locale>,
+    /// Directory that message catalogues are read from, e.g. `fr.toml`.
+    /// Defaults to the `messages` directory within the templates directory.
+    #[serde(default)]
+    pub messages_directory: Option<PathBuf>,
+}
+
 /// Logging configuration.
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -198,6 +233,7 @@ mod tests {
             logging: LoggingConfig {
                 log_level: LogLevel::Debug,
             },
+            templates: TemplateConfig::default(),
         };
 
         assert_eq!(
@@ -208,4 +244,77 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_template_config_serialization() -> Result<(), String> {
+        let raw_template_config = r#"
+            directory = "templates"
+            cache_directory = ".docugen-cache"
+            format = "latex"
+            delimiters = ["<%", "%>"]
+            locale = "fr-CA"
+            messages_directory = "messages"
+        "#;
+
+        let expected_template_config = TemplateConfig {
+            directory: Some(PathBuf::from("templates")),
+            missing_tags: MissingTagPolicy::Strict,
+            cache_directory: Some(PathBuf::from(".docugen-cache")),
+            format: Some(Escape::Latex),
+            delimiters: Delimiters::new("<%", "%>").unwrap(),
+            locale: Some("fr-CA".parse().unwrap()),
+            messages_directory: Some(PathBuf::from("messages")),
+        };
+
+        assert_eq!(
+            expected_template_config,
+            toml::from_str::<TemplateConfig>(raw_template_config)
+                .map_err(|e| e.to_string())?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_delimiters() {
+        let raw_template_config = r#"
+            delimiters = ["<%", ""]
+        "#;
+
+        assert!(toml::from_str::<TemplateConfig>(raw_template_config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_locale() {
+        let raw_template_config = r#"
+            locale = "french"
+        "#;
+
+        assert!(toml::from_str::<TemplateConfig>(raw_template_config).is_err());
+    }
+
+    #[test]
+    fn test_missing_tags_policy_serialization() -> Result<(), String> {
+        let raw_template_config = r#"
+            missing_tags = "lenient-placeholder"
+        "#;
+
+        let expected_template_config = TemplateConfig {
+            directory: None,
+            missing_tags: MissingTagPolicy::LenientPlaceholder,
+            cache_directory: None,
+            format: None,
+            delimiters: Delimiters::default(),
+            locale: None,
+            messages_directory: None,
+        };
+
+        assert_eq!(
+            expected_template_config,
+            toml::from_str::<TemplateConfig>(raw_template_config)
+                .map_err(|e| e.to_string())?
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,243 @@
+use super::compiled::CompiledTemplate;
+use super::document::Identifier;
+use super::loader::TemplateSource;
+use super::parser::Delimiters;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Version of the format of cache entries. It must be bumped whenever the
+/// serialized form of `CompiledTemplate` changes, so that stale entries are
+/// compiled again instead of being misread.
+const FORMAT_VERSION: u32 = 4;
+
+/// File extension of cache entries.
+pub const CACHE_EXTENSION: &str = "bin";
+
+/// A `TemplateCache` keeps `CompiledTemplate`s in a directory, so that a
+/// template is only parsed and compiled again when it, or a template it
+/// includes, has changed. Each entry is a binary file named after the
+/// `content_hash` of the source of the template, and of the `Delimiters` it
+/// starts with if they are not the default ones.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TemplateCache {
+    directory: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    version: u32,
+    /// Every template included, directly or not, with the `content_hash` of
+    /// its source when the entry was written.
+    includes: Vec<(Identifier, u64)>,
+    template: CompiledTemplate,
+}
+
+impl TemplateCache {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// The cached compiled form of the template `source`, unless none of its
+    /// includes, read from `includes`, has changed since it was cached. A
+    /// missing, stale or unreadable entry is a cache miss.
+    pub fn load(
+        &self,
+        source: &str,
+        includes: &dyn TemplateSource,
+    ) -> Option<CompiledTemplate> {
+        let raw = fs::read(self.entry_path(source, includes)).ok()?;
+        let entry = bincode::deserialize::<CacheEntry>(&raw).ok()?;
+        if entry.version != FORMAT_VERSION {
+            return None;
+        }
+
+        let unchanged = entry.includes.iter().all(|(name, hash)| {
+            includes
+                .read_template(name)
+                .is_ok_and(|raw| content_hash(&raw) == *hash)
+        });
+        if unchanged {
+            Some(entry.template)
+        } else {
+            None
+        }
+    }
+
+    /// Cache `template`, compiled from the template `source` which includes
+    /// the templates named `included`, read from `includes`.
+    pub fn store(
+        &self,
+        source: &str,
+        includes: &dyn TemplateSource,
+        included: &[Identifier],
+        template: &CompiledTemplate,
+    ) -> Result<(), String> {
+        let path = self.entry_path(source, includes);
+        let includes = included
+            .iter()
+            .map(|name| {
+                let raw = includes.read_template(name)?;
+                Ok((name.clone(), content_hash(&raw)))
+            })
+            .collect::<Result<_, String>>()?;
+        let entry = CacheEntry {
+            version: FORMAT_VERSION,
+            includes,
+            template: template.clone(),
+        };
+        let raw = bincode::serialize(&entry).map_err(|e| e.to_string())?;
+
+        // Write to a temporary file first, so that a concurrent `load` never
+        // reads a partially written entry.
+        let temporary = path.with_extension("tmp");
+        fs::create_dir_all(&self.directory)
+            .and_then(|_| fs::write(&temporary, raw))
+            .and_then(|_| fs::rename(&temporary, &path))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn entry_path(
+        &self,
+        source: &str,
+        includes: &dyn TemplateSource,
+    ) -> PathBuf {
+        let delimiters = includes.delimiters();
+        let hash = if delimiters == Delimiters::default() {
+            content_hash(source)
+        } else {
+            content_hash(&format!(
+                "{} {}\n{}",
+                delimiters.open(),
+                delimiters.close(),
+                source
+            ))
+        };
+        self.directory
+            .join(format!("{:016x}.{}", hash, CACHE_EXTENSION))
+    }
+}
+
+/// The 64-bit [FNV-1a](http://www.isthe.com/chongo/tech/comp/fnv/) hash of
+/// `source`. Unlike `std`'s `DefaultHasher`, it is the same across Rust
+/// versions, so it can name files which outlive the process.
+pub fn content_hash(source: &str) -> u64 {
+    source.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::loader::{resolve_includes_listed, DirectorySource};
+    use crate::core::parser::parse_template;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+    use std::env;
+    use std::ops::Deref;
+
+    /// A `TemplateCache` in a directory of its own, which is removed once
+    /// the test is over, whether it passed or not.
+    struct TestCache(TemplateCache);
+
+    impl Deref for TestCache {
+        type Target = TemplateCache;
+
+        fn deref(&self) -> &TemplateCache {
+            &self.0
+        }
+    }
+
+    impl Drop for TestCache {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0.directory);
+        }
+    }
+
+    fn cache(name: &str) -> TestCache {
+        let directory = env::temp_dir().join(format!(
+            "docugen-cache-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        TestCache(TemplateCache::new(directory))
+    }
+
+    fn compile_and_store(
+        cache: &TemplateCache,
+        source: &str,
+        includes: &HashMap<String, String>,
+    ) -> CompiledTemplate {
+        let template = parse_template(source).unwrap();
+        let (template, included) =
+            resolve_includes_listed(&template, includes).unwrap();
+        let template = template.compile();
+        cache.store(source, includes, &included, &template).unwrap();
+        template
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(0xcbf2_9ce4_8422_2325, content_hash(""));
+        assert_eq!(0xaf63_dc4c_8601_ec8c, content_hash("a"));
+        assert_ne!(content_hash("{{ a }}"), content_hash("{{ b }}"));
+    }
+
+    #[test]
+    fn test_load_stored_template() {
+        let cache = cache("load");
+        let includes = HashMap::new();
+        let source = "Dear {{ name | upper }},";
+
+        assert_eq!(None, cache.load(source, &includes));
+        let template = compile_and_store(&cache, source, &includes);
+        assert_eq!(Some(template), cache.load(source, &includes));
+        assert_eq!(None, cache.load("Dear {{ name }},", &includes));
+    }
+
+    #[test]
+    fn test_changed_include_is_a_miss() {
+        let cache = cache("include");
+        let mut includes = HashMap::new();
+        includes.insert("signature".to_string(), "Sincerely,".to_string());
+        let source = "Dear {{ name }},{{> signature }}";
+
+        compile_and_store(&cache, source, &includes);
+        assert!(cache.load(source, &includes).is_some());
+
+        includes.insert("signature".to_string(), "Regards,".to_string());
+        assert_eq!(None, cache.load(source, &includes));
+
+        includes.remove("signature");
+        assert_eq!(None, cache.load(source, &includes));
+    }
+
+    #[test]
+    fn test_corrupt_entry_is_a_miss() {
+        let cache = cache("corrupt");
+        let includes = HashMap::new();
+        let source = "{{ name }}";
+
+        compile_and_store(&cache, source, &includes);
+        fs::write(cache.entry_path(source, &includes), b"not an entry")
+            .unwrap();
+        assert_eq!(None, cache.load(source, &includes));
+    }
+
+    #[test]
+    fn test_delimiters_are_part_of_the_key() {
+        let cache = cache("delimiters");
+        let includes = HashMap::new();
+        let source = "<% name %>";
+        let delimited = DirectorySource::new(".")
+            .with_delimiters(Delimiters::new("<%", "%>").unwrap());
+
+        compile_and_store(&cache, source, &includes);
+        assert!(cache.load(source, &includes).is_some());
+        assert_eq!(None, cache.load(source, &delimited));
+    }
+}
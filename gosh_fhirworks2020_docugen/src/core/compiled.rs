@@ -0,0 +1,514 @@
+use super::document::{
+    apply_filters, evaluate, message, write_value, Context, DocumentTemplate,
+    FilledDocument, Identifier, IoOutput, Output, Partial, RenderOptions, Tag,
+    TemplateError,
+};
+use super::expr::Expr;
+use super::value::Value;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+
+/// A `CompiledTemplate` is a `DocumentTemplate` prepared to be filled many
+/// times, e.g. once per patient of a large batch. It fills documents exactly
+/// as the `DocumentTemplate` it was compiled from, but:
+///
+/// - every distinct tag identifier is split into its path segments once and
+///   tags refer to it by its slot index;
+/// - the data a slot resolves to outside of any `Section` is only looked up
+///   once per document;
+/// - tags without a default or filters are written without copying their
+///   data, unless they are escaped.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CompiledTemplate {
+    slots: Vec<Slot>,
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+struct Slot {
+    id: Identifier,
+    /// The segments of `id`, empty for the implicit iterator `.`.
+    segments: Vec<String>,
+}
+
+/// The compiled form of a `Partial`, whose tags refer to slots by index.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+enum Node {
+    Text(String),
+    /// A tag without a default or filters which is not raw.
+    Slot(usize),
+    Tag(usize, Tag),
+    Section(usize, Vec<Node>),
+    InvertedSection(usize, Vec<Node>),
+    Include(Identifier),
+    Message(Identifier),
+    Layout(Identifier),
+    Conditional(Vec<(Expr, Vec<Node>)>, Vec<Node>),
+}
+
+impl DocumentTemplate {
+    /// Compile the template into a `CompiledTemplate`. `Include`s should be
+    /// resolved first, see `core::loader`.
+    pub fn compile(&self) -> CompiledTemplate {
+        let mut compiler = Compiler::default();
+        let nodes = compiler.compile(&self.partials);
+
+        CompiledTemplate {
+            slots: compiler.slots,
+            nodes,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Compiler {
+    slots: Vec<Slot>,
+    indices: HashMap<Identifier, usize>,
+}
+
+impl Compiler {
+    fn compile(&mut self, partials: &[Partial]) -> Vec<Node> {
+        let mut nodes = Vec::with_capacity(partials.len());
+        for partial in partials {
+            let node = match partial {
+                Partial::StringLiteral(s) => Node::Text(s.clone()),
+                Partial::Tag(tag)
+                    if tag.default.is_none()
+                        && tag.filters.is_empty()
+                        && !tag.raw =>
+                {
+                    Node::Slot(self.slot(&tag.id))
+                }
+                Partial::Tag(tag) => Node::Tag(self.slot(&tag.id), tag.clone()),
+                Partial::Section(id, nested) => {
+                    Node::Section(self.slot(id), self.compile(nested))
+                }
+                Partial::InvertedSection(id, nested) => {
+                    Node::InvertedSection(self.slot(id), self.compile(nested))
+                }
+                Partial::Include(name) => Node::Include(name.clone()),
+                Partial::Message(key) => Node::Message(key.clone()),
+                // A block is filled with its content, so it is compiled away.
+                Partial::Block(_, nested) => {
+                    nodes.extend(self.compile(nested));
+                    continue;
+                }
+                Partial::Layout(name, _) => Node::Layout(name.clone()),
+                Partial::Conditional(branches, otherwise) => Node::Conditional(
+                    branches
+                        .iter()
+                        .map(|(condition, nested)| {
+                            (condition.clone(), self.compile(nested))
+                        })
+                        .collect(),
+                    self.compile(otherwise),
+                ),
+            };
+            nodes.push(node);
+        }
+        nodes
+    }
+
+    fn slot(&mut self, id: &str) -> usize {
+        if let Some(&index) = self.indices.get(id) {
+            return index;
+        }
+
+        let segments = match id {
+            "." => Vec::new(),
+            _ => id.split('.').map(str::to_string).collect(),
+        };
+        self.slots.push(Slot {
+            id: id.to_string(),
+            segments,
+        });
+        self.indices.insert(id.to_string(), self.slots.len() - 1);
+        self.slots.len() - 1
+    }
+}
+
+impl CompiledTemplate {
+    /// Fill the template with `data`, see `DocumentTemplate::saturate`.
+    pub fn saturate(
+        &self,
+        data: &Value,
+    ) -> Result<FilledDocument, TemplateError> {
+        self.saturate_with(data, &RenderOptions::default())
+    }
+
+    /// Fill the template with `data` using `options`, see
+    /// `DocumentTemplate::saturate_with`.
+    pub fn saturate_with(
+        &self,
+        data: &Value,
+        options: &RenderOptions,
+    ) -> Result<FilledDocument, TemplateError> {
+        let mut content = String::new();
+        self.render(data, options, &mut content)?;
+        Ok(FilledDocument(content))
+    }
+
+    /// Fill the template with `data` using `options`, writing the document
+    /// straight to `writer`, see `DocumentTemplate::render_to`.
+    pub fn render_to<W: io::Write>(
+        &self,
+        data: &Value,
+        options: &RenderOptions,
+        writer: &mut W,
+    ) -> Result<(), TemplateError> {
+        self.render(data, options, &mut IoOutput(writer))
+    }
+
+    fn render(
+        &self,
+        data: &Value,
+        options: &RenderOptions,
+        content: &mut dyn Output,
+    ) -> Result<(), TemplateError> {
+        let mut renderer = Renderer {
+            slots: &self.slots,
+            context: Context::new(data, options.exhaustive),
+            options,
+            root_lookups: vec![None; self.slots.len()],
+        };
+        renderer.render(&self.nodes, content)?;
+        renderer.context.check_usage(data)
+    }
+}
+
+struct Renderer<'a, 't> {
+    slots: &'t [Slot],
+    context: Context<'a>,
+    options: &'t RenderOptions,
+    /// What each slot resolved to outside of any `Section`, once looked up.
+    root_lookups: Vec<Option<Option<(usize, &'a Value)>>>,
+}
+
+impl<'a, 't> Renderer<'a, 't> {
+    fn render(
+        &mut self,
+        nodes: &[Node],
+        content: &mut dyn Output,
+    ) -> Result<(), TemplateError> {
+        for node in nodes {
+            match node {
+                Node::Text(s) => content.write_str(s)?,
+                Node::Slot(slot) => {
+                    let value =
+                        self.lookup(*slot, true).unwrap_or(&Value::Null);
+                    let id = &self.slots[*slot].id;
+                    write_value(
+                        &mut self.context,
+                        self.options,
+                        id,
+                        self.options.escape,
+                        value,
+                        content,
+                    )?;
+                }
+                Node::Tag(slot, tag) => {
+                    let value = self
+                        .lookup(*slot, true)
+                        .or(tag.default.as_ref())
+                        .cloned()
+                        .unwrap_or(Value::Null);
+                    let value = apply_filters(tag, value, self.options)?;
+                    write_value(
+                        &mut self.context,
+                        self.options,
+                        &tag.id,
+                        tag.escape(self.options),
+                        &value,
+                        content,
+                    )?;
+                }
+                Node::Section(slot, nested) => {
+                    let id = &self.slots[*slot].id;
+                    match self.lookup(*slot, false) {
+                        Some(Value::List(items)) => {
+                            for (index, item) in items.iter().enumerate() {
+                                self.context.push(id, Some(index), item);
+                                let result = self.render(nested, content);
+                                self.context.pop();
+                                result?;
+                            }
+                        }
+                        Some(value) if value.is_truthy() => {
+                            self.context.push(id, None, value);
+                            let result = self.render(nested, content);
+                            self.context.pop();
+                            result?;
+                        }
+                        _ => {}
+                    }
+                }
+                Node::InvertedSection(slot, nested) => {
+                    if !self.lookup(*slot, false).is_some_and(Value::is_truthy)
+                    {
+                        self.render(nested, content)?;
+                    }
+                }
+                Node::Include(name) => {
+                    return Err(TemplateError::UnresolvedInclude(name.clone()));
+                }
+                Node::Message(key) => {
+                    content.write_str(message(self.options, key)?)?
+                }
+                Node::Layout(name) => {
+                    return Err(TemplateError::UnresolvedLayout(name.clone()));
+                }
+                Node::Conditional(branches, otherwise) => {
+                    let mut chosen = otherwise;
+                    for (condition, nested) in branches {
+                        let value = evaluate(
+                            condition,
+                            &mut self.context,
+                            self.options,
+                        )?;
+                        if value.is_truthy() {
+                            chosen = nested;
+                            break;
+                        }
+                    }
+                    self.render(chosen, content)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolve `slot` and record its use, see `Context::record`. Outside of
+    /// any `Section`, a slot always resolves to the same data, so it is only
+    /// looked up once.
+    fn lookup(&mut self, slot: usize, whole: bool) -> Option<&'a Value> {
+        let Slot { id, segments } = &self.slots[slot];
+        let path = segments.iter().map(String::as_str);
+        let resolved = if self.context.depth() == 1 {
+            let context = &self.context;
+            *self.root_lookups[slot]
+                .get_or_insert_with(|| context.resolve_path(path))
+        } else {
+            self.context.resolve_path(path)
+        };
+
+        self.context.record(id, resolved, whole)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::document::MissingTagPolicy;
+    use crate::core::escape::Escape;
+    use crate::core::parser::parse_template;
+    use pretty_assertions::assert_eq;
+
+    fn patients() -> Value {
+        let patient = |family: &str, given: Vec<&str>, birth_date| {
+            vec![
+                ("family", Value::from(family)),
+                ("given", Value::from(given)),
+                ("birthDate", Value::from(birth_date)),
+            ]
+            .into_iter()
+            .collect::<Value>()
+        };
+
+        vec![(
+            "patients",
+            Value::from(vec![
+                patient("Smith", vec!["Jane", "Anne"], Some("2012-03-04")),
+                patient("Jones", vec![], None),
+            ]),
+        )]
+        .into_iter()
+        .collect()
+    }
+
+    /// Check that the compiled template fills `data` exactly as the template
+    /// it was compiled from.
+    fn assert_same_output(
+        raw: &str,
+        data: &Value,
+        options: &RenderOptions,
+    ) -> Result<FilledDocument, TemplateError> {
+        let template = parse_template(raw).unwrap();
+        let expected = template.saturate_with(data, options);
+        assert_eq!(expected, template.compile().saturate_with(data, options));
+        expected
+    }
+
+    #[test]
+    fn test_slots_are_shared() {
+        let template =
+            parse_template("{{ a.b }}{{#a}}{{ a.b | upper }}{{ . }}{{/a}}")
+                .unwrap()
+                .compile();
+
+        assert_eq!(
+            vec![
+                Slot {
+                    id: "a.b".to_string(),
+                    segments: vec!["a".to_string(), "b".to_string()],
+                },
+                Slot {
+                    id: "a".to_string(),
+                    segments: vec!["a".to_string()],
+                },
+                Slot {
+                    id: ".".to_string(),
+                    segments: vec![],
+                },
+            ],
+            template.slots
+        );
+    }
+
+    #[test]
+    fn test_same_output_as_template() {
+        let options = RenderOptions::default();
+        let data = patients();
+
+        assert_same_output(
+            "{{#patients}}{{ family | upper }}, {{ given | join(\" \") }} \
+             {{#given}}[{{ . }}]{{/given}}{{^given}}no given name{{/given}}\n\
+             {{/patients}}",
+            &data,
+            &options,
+        )
+        .unwrap();
+        assert_same_output(
+            "{{#patients}}{{#if birthDate | age(\"2020-03-04\") < 16}}child\
+             {{else if birthDate}}adult{{else}}unknown{{/if}};{{/patients}}",
+            &data,
+            &options,
+        )
+        .unwrap();
+        assert_same_output(
+            "{{ patients.0.family }} {{ patients.1.birthDate ? \"-\" }}",
+            &data,
+            &options,
+        )
+        .unwrap();
+        assert_same_output(
+            "{{$list}}{{#patients}}{{ family }};{{/patients}}{{/list}}",
+            &data,
+            &options,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_same_errors_as_template() {
+        let data = patients();
+        let options = RenderOptions::default();
+
+        assert_same_output(
+            "{{#patients}}{{ birthDate }}{{/patients}}",
+            &data,
+            &options,
+        )
+        .unwrap_err();
+        assert_same_output("{{ patients }}", &data, &options).unwrap_err();
+        assert_same_output("{{ patients | reverse }}", &data, &options)
+            .unwrap_err();
+        assert_same_output("{{ t \"greeting\" }}", &data, &options)
+            .unwrap_err();
+        assert_same_output(
+            "{{ patients.0.family }}",
+            &data,
+            &RenderOptions {
+                exhaustive: true,
+                ..RenderOptions::default()
+            },
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_localized_output() {
+        let data = vec![("birthDate", "2012-03-04")].into_iter().collect();
+        let mut options = RenderOptions {
+            locale: "de".parse().unwrap(),
+            ..RenderOptions::default()
+        };
+        options.messages.insert("born", "Geboren am");
+
+        let filled_document = assert_same_output(
+            "{{ t \"born\" }} {{ birthDate | format }}",
+            &data,
+            &options,
+        )
+        .unwrap();
+        assert_eq!("Geboren am 4. März 2012", filled_document.document());
+    }
+
+    #[test]
+    fn test_escaped_output() {
+        let data = patients();
+
+        let filled_document = assert_same_output(
+            "{{#patients}}{{ family }},{{ given | join(\", \") }},\
+             {{{ given | join(\", \") }}};{{/patients}}",
+            &data,
+            &RenderOptions {
+                escape: Escape::Csv,
+                ..RenderOptions::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            "Smith,\"Jane, Anne\",Jane, Anne;Jones,,;",
+            filled_document.document()
+        );
+    }
+
+    #[test]
+    fn test_missing_tag_policies() {
+        let data = patients();
+
+        for missing_tags in &[
+            MissingTagPolicy::LenientEmpty,
+            MissingTagPolicy::LenientPlaceholder,
+        ] {
+            assert_same_output(
+                "{{#patients}}{{ birthDate }};{{/patients}}{{ birthDate }}",
+                &data,
+                &RenderOptions {
+                    missing_tags: *missing_tags,
+                    ..RenderOptions::default()
+                },
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_root_lookups_are_reused() {
+        let data = vec![("name", "Jane")].into_iter().collect();
+        let template = parse_template("{{ name }} {{ name | upper }}")
+            .unwrap()
+            .compile();
+
+        assert_eq!("Jane JANE", template.saturate(&data).unwrap().document());
+    }
+
+    #[test]
+    fn test_render_to_writer() {
+        let data = patients();
+        let template =
+            parse_template("{{#patients}}{{ family }};{{/patients}}").unwrap();
+        let options = RenderOptions::default();
+
+        let mut written = Vec::new();
+        template
+            .compile()
+            .render_to(&data, &options, &mut written)
+            .unwrap();
+
+        assert_eq!(b"Smith;Jones;", &written[..]);
+    }
+}
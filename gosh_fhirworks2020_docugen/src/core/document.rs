@@ -1,6 +1,17 @@
+use super::escape::Escape;
+use super::expr::Expr;
+use super::filter::{Filter, FilterRegistry};
+use super::messages::MessageCatalogue;
+use super::value::Value;
+use crate::data::locale::Locale;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io;
+
 /// A `DocumentTemplate` mimics a [mustache](https://mustache.github.io/)
 /// template. A template consists of a list of `Partial`s.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Default)]
 pub struct DocumentTemplate {
     pub partials: Vec<Partial>,
 }
@@ -21,27 +32,99 @@ impl DocumentTemplate {
     }
 }
 
-impl Default for DocumentTemplate {
-    fn default() -> Self {
+/// An `Identifier` names the data a tag is filled with. It is either a single
+/// key, the implicit iterator `.` or a dotted path of keys and list indices
+/// such as `patient.name.0.family`.
+pub type Identifier = String;
+
+/// A `Tag` is filled with the data named by its `Identifier`, or its `default`
+/// if that data is missing, transformed by each of its `Filter`s in turn.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: Identifier,
+    pub default: Option<Value>,
+    pub filters: Vec<Filter>,
+    /// Whether the data is written without the `Escape` of the document, as
+    /// for `{{{ id }}}` or `{{& id }}`.
+    pub raw: bool,
+}
+
+impl Tag {
+    pub fn new(id: &str) -> Self {
+        Self::with_filters(id, &[])
+    }
+
+    pub fn with_filters(id: &str, filters: &[Filter]) -> Self {
         Self {
-            partials: Vec::new(),
+            id: id.to_string(),
+            default: None,
+            filters: filters.to_vec(),
+            raw: false,
         }
     }
-}
 
-pub type Identifier = String;
+    pub fn with_default(id: &str, default: Value) -> Self {
+        Self {
+            default: Some(default),
+            ..Self::new(id)
+        }
+    }
 
-/// Each `Partial` is either a UTF-8 `StringLiteral`, or a `Tag`.
+    pub fn raw(id: &str) -> Self {
+        Self {
+            raw: true,
+            ..Self::new(id)
+        }
+    }
+
+    /// How the data of the tag is escaped when filled using `options`.
+    pub(super) fn escape(&self, options: &RenderOptions) -> Escape {
+        if self.raw {
+            Escape::None
+        } else {
+            options.escape
+        }
+    }
+}
+
+/// Each `Partial` is either a UTF-8 `StringLiteral`, a `Tag`, or a block of
+/// nested `Partial`s guarded by a tag.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Partial {
     StringLiteral(String),
-    Tag(Identifier),
+    Tag(Tag),
+    /// `{{#key}}...{{/key}}`: the nested `Partial`s are only rendered if `key`
+    /// has a non-empty value.
+    Section(Identifier, Vec<Partial>),
+    /// `{{^key}}...{{/key}}`: the nested `Partial`s are only rendered if `key`
+    /// is missing or has an empty value.
+    InvertedSection(Identifier, Vec<Partial>),
+    /// `{{> name}}`: replaced by the `Partial`s of the template called `name`
+    /// when the template is loaded, see `core::loader`.
+    Include(Identifier),
+    /// `{{ t "key" }}`: replaced by the message `key` of the
+    /// `MessageCatalogue` of the document, written as it is, as text of the
+    /// template is.
+    Message(Identifier),
+    /// `{{$name}}...{{/name}}`: a named part of a layout, which templates
+    /// using the layout may override. The nested `Partial`s are its default
+    /// content.
+    Block(Identifier, Vec<Partial>),
+    /// `{{<name}}{{$block}}...{{/block}}{{/name}}`: replaced by the template
+    /// called `name`, its layout, with the content of its `Block`s overridden
+    /// by the `Block`s listed, when the template is loaded. Anything else
+    /// between the tags is ignored.
+    Layout(Identifier, Vec<(Identifier, Vec<Partial>)>),
+    /// `{{#if condition}}...{{else if condition}}...{{else}}...{{/if}}`: the
+    /// nested `Partial`s of the first branch whose condition is truthy are
+    /// rendered, or the `Partial`s after `{{else}}` if there is none.
+    Conditional(Vec<(Expr, Vec<Partial>)>, Vec<Partial>),
 }
 
 /// A `FilledDocument` is generated from a `DocumentTemplate` with the required
 /// `Tag`s filled in.
 #[derive(Debug, PartialEq)]
-pub struct FilledDocument(String);
+pub struct FilledDocument(pub(super) String);
 
 impl FilledDocument {
     pub fn document(&self) -> &str {
@@ -49,54 +132,640 @@ impl FilledDocument {
     }
 }
 
-/// A `TagPair` is an association between the tag name `key` and the `value`
-/// that should be used to fill its place.
-#[derive(Debug, PartialEq)]
-pub struct TagPair {
-    pub key: String,
-    pub value: String,
-}
-
 /// Cause of error when trying to fill a `DocumentTemplate`.
 #[derive(Debug, PartialEq)]
 pub enum TemplateError {
     MissingRequiredTagValue(Identifier),
-    NonExhaustiveTags(Vec<Identifier>),
+    /// In exhaustive mode, the data supplied did not exactly match the data
+    /// used by the template. `unused` lists the dotted paths of supplied data
+    /// the template never used and `missing` lists the tags that had no data.
+    NonExhaustiveTags {
+        unused: Vec<Identifier>,
+        missing: Vec<Identifier>,
+    },
+    /// A `Tag` resolved to a `List` or a `Map`, which has no textual form.
+    NonScalarTagValue(Identifier),
+    /// The template still contains an `Include` which was never expanded.
+    UnresolvedInclude(Identifier),
+    /// The template still contains a `Layout` which was never expanded.
+    UnresolvedLayout(Identifier),
+    /// A `Message` names a message which is not in the `MessageCatalogue`.
+    MissingMessage(Identifier),
+    /// A `Tag` uses a filter which is not in the `FilterRegistry`.
+    UnknownFilter(Identifier),
+    /// A filter could not be applied to the value of a `Tag`.
+    FilterFailed {
+        tag: Identifier,
+        filter: Identifier,
+        cause: String,
+    },
+    /// The condition of a `Conditional` could not be evaluated, e.g. because
+    /// it compares a number with text.
+    InvalidCondition {
+        condition: String,
+        cause: String,
+    },
+    /// The document could not be written out, see
+    /// `DocumentTemplate::render_to`.
+    Io(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::MissingRequiredTagValue(id) => {
+                write!(f, "tag `{}` has no data and no default value", id)
+            }
+            TemplateError::NonExhaustiveTags { unused, missing } => {
+                write!(f, "data does not exactly match the template")?;
+                if !unused.is_empty() {
+                    write!(f, "; unused data: {}", unused.join(", "))?;
+                }
+                if !missing.is_empty() {
+                    write!(f, "; tags without data: {}", missing.join(", "))?;
+                }
+                Ok(())
+            }
+            TemplateError::NonScalarTagValue(id) => write!(
+                f,
+                "tag `{}` is a list or a record, which cannot be written",
+                id
+            ),
+            TemplateError::UnresolvedInclude(name) => {
+                write!(f, "included template `{}` was never loaded", name)
+            }
+            TemplateError::UnresolvedLayout(name) => {
+                write!(f, "layout `{}` was never loaded", name)
+            }
+            TemplateError::MissingMessage(key) => {
+                write!(f, "no translation of message `{}`", key)
+            }
+            TemplateError::UnknownFilter(filter) => {
+                write!(f, "unknown filter `{}`", filter)
+            }
+            TemplateError::FilterFailed { tag, filter, cause } => write!(
+                f,
+                "filter `{}` failed on tag `{}`: {}",
+                filter, tag, cause
+            ),
+            TemplateError::InvalidCondition { condition, cause } => {
+                write!(f, "condition `{}` is invalid: {}", condition, cause)
+            }
+            TemplateError::Io(cause) => {
+                write!(f, "failed to write the document: {}", cause)
+            }
+        }
+    }
+}
+
+/// What to do when a `Tag` has neither data nor a default value.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum MissingTagPolicy {
+    /// Fail with `TemplateError::MissingRequiredTagValue`.
+    #[default]
+    #[serde(rename = "strict")]
+    Strict,
+    /// Fill the tag with the empty string.
+    #[serde(rename = "lenient-empty")]
+    LenientEmpty,
+    /// Fill the tag with a visible placeholder naming it, e.g.
+    /// `[missing: family]`.
+    #[serde(rename = "lenient-placeholder")]
+    LenientPlaceholder,
+}
+
+/// Options controlling how a `DocumentTemplate` is filled.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Filters available to `Tag`s. Defaults to the built-in filters.
+    pub filters: FilterRegistry,
+    /// How `Tag`s without data are filled. Defaults to
+    /// `MissingTagPolicy::Strict`.
+    pub missing_tags: MissingTagPolicy,
+    /// Whether to check that every piece of supplied data is used by the
+    /// template. If so, filling fails with `TemplateError::NonExhaustiveTags`
+    /// reporting all unused data and all missing tags at once, instead of
+    /// stopping at the first missing tag.
+    pub exhaustive: bool,
+    /// How the data filling `Tag`s is escaped. Defaults to `Escape::None`.
+    pub escape: Escape,
+    /// The locale of the document, in which filters such as `format` write
+    /// data. Defaults to `en-GB`.
+    pub locale: Locale,
+    /// Translations of `Message`s in `locale`. Empty by default.
+    pub messages: MessageCatalogue,
 }
 
 impl DocumentTemplate {
+    /// Fill the template with `data`, which is usually a `Value::Map` keyed by
+    /// the tag identifiers used in the template.
     pub fn saturate(
         &self,
-        tag_pairs: &[TagPair],
+        data: &Value,
+    ) -> Result<FilledDocument, TemplateError> {
+        self.saturate_with(data, &RenderOptions::default())
+    }
+
+    /// Fill the template with `data` as `saturate` does, using `options`.
+    pub fn saturate_with(
+        &self,
+        data: &Value,
+        options: &RenderOptions,
     ) -> Result<FilledDocument, TemplateError> {
         let mut content = String::new();
+        let mut context = Context::new(data, options.exhaustive);
+        saturate_partials(&self.partials, &mut context, options, &mut content)?;
+        context.check_usage(data)?;
+
+        Ok(FilledDocument(content))
+    }
+
+    /// Fill the template with `data` as `saturate_with` does, writing the
+    /// document straight to `writer` as it is filled instead of building it in
+    /// memory. `writer` should be buffered, e.g. by an `io::BufWriter`.
+    ///
+    /// If filling fails, the part of the document filled so far has already
+    /// been written. In exhaustive mode, `TemplateError::NonExhaustiveTags` is
+    /// only reported once the whole document has been written.
+    pub fn render_to<W: io::Write>(
+        &self,
+        data: &Value,
+        options: &RenderOptions,
+        writer: &mut W,
+    ) -> Result<(), TemplateError> {
+        let mut context = Context::new(data, options.exhaustive);
+        let mut output = IoOutput(writer);
+        saturate_partials(&self.partials, &mut context, options, &mut output)?;
+        context.check_usage(data)
+    }
+}
 
-        // TODO: replace this `O(n^2)` loop with a `O(1)` `HashMap`. Currently
-        // this requires iterating over `self.partials` in the outer loop and
-        // iterating over `tag_pairs` in the inner loop in the worst case
-        // scenario.
-        for partial in &self.partials[..] {
-            match partial {
-                Partial::StringLiteral(s) => content.push_str(s),
-                Partial::Tag(id) => {
-                    let tag_value = saturate_or_error(tag_pairs, id)?;
-                    content.push_str(tag_value);
+/// Where a document is written to as it is filled.
+pub(super) trait Output {
+    fn write_str(&mut self, s: &str) -> Result<(), TemplateError>;
+
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<(), TemplateError>;
+}
+
+impl Output for String {
+    fn write_str(&mut self, s: &str) -> Result<(), TemplateError> {
+        self.push_str(s);
+        Ok(())
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<(), TemplateError> {
+        // Formatting into a `String` cannot fail.
+        let _ = fmt::Write::write_fmt(self, args);
+        Ok(())
+    }
+}
+
+pub(super) struct IoOutput<'w, W>(pub(super) &'w mut W);
+
+impl<W: io::Write> Output for IoOutput<'_, W> {
+    fn write_str(&mut self, s: &str) -> Result<(), TemplateError> {
+        self.0
+            .write_all(s.as_bytes())
+            .map_err(|e| TemplateError::Io(e.to_string()))
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<(), TemplateError> {
+        self.0
+            .write_fmt(args)
+            .map_err(|e| TemplateError::Io(e.to_string()))
+    }
+}
+
+/// The stack of `Value`s that tags are resolved against. Entering a `Section`
+/// pushes its `Value` so that tags within it resolve against the innermost
+/// `Value` first, falling back to the enclosing ones.
+pub(super) struct Context<'a> {
+    stack: Vec<Frame<'a>>,
+    /// How the data is used by the template, only tracked in exhaustive mode.
+    usage: Option<Usage>,
+}
+
+struct Frame<'a> {
+    /// The dotted path of `value` from the root `Value`. Only tracked in
+    /// exhaustive mode.
+    path: String,
+    value: &'a Value,
+}
+
+impl<'a> Context<'a> {
+    pub(super) fn new(root: &'a Value, exhaustive: bool) -> Self {
+        Self {
+            stack: vec![Frame {
+                path: String::new(),
+                value: root,
+            }],
+            usage: if exhaustive {
+                Some(Usage::default())
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Resolve `id` against the stack. The implicit iterator `.` refers to the
+    /// innermost `Value` itself.
+    ///
+    /// A dotted path such as `patient.name.0.family` is resolved by looking up
+    /// its first segment against the stack and then descending into the
+    /// `Value` found with each following segment; numeric segments index into
+    /// lists. `Null` is treated the same as a missing value.
+    pub(super) fn lookup(&mut self, id: &str) -> Option<&'a Value> {
+        let resolved = self.resolve(id);
+        self.record(id, resolved, true)
+    }
+
+    /// Resolve `id` as `lookup` does, for a `Section`. A non-empty list or map
+    /// guarding a `Section` only counts as used through its contents.
+    fn lookup_section(&mut self, id: &str) -> Option<&'a Value> {
+        let resolved = self.resolve(id);
+        self.record(id, resolved, false)
+    }
+
+    /// Record the use of the data `id` was resolved to, in the frame it was
+    /// found in. `whole` is `false` when only the contents of a non-empty
+    /// list or map are used, i.e. when it guards a `Section`.
+    pub(super) fn record(
+        &mut self,
+        id: &str,
+        resolved: Option<(usize, &'a Value)>,
+        whole: bool,
+    ) -> Option<&'a Value> {
+        let (frame, value) = resolved?;
+        if *value == Value::Null {
+            return None;
+        }
+
+        let has_contents = match value {
+            Value::List(items) => !items.is_empty(),
+            Value::Map(map) => !map.is_empty(),
+            _ => false,
+        };
+        if whole || !has_contents {
+            let path = self.path_of(frame, id);
+            if let Some(usage) = &mut self.usage {
+                usage.used.insert(path);
+            }
+        }
+
+        Some(value)
+    }
+
+    /// Find the `Value` named by `id` and the index of the frame it was found
+    /// in.
+    fn resolve(&self, id: &str) -> Option<(usize, &'a Value)> {
+        if id == "." {
+            self.resolve_path(std::iter::empty())
+        } else {
+            self.resolve_path(id.split('.'))
+        }
+    }
+
+    /// Find the `Value` named by the path `segments` and the index of the
+    /// frame it was found in. An empty path names the innermost `Value`.
+    ///
+    /// Every lookup searches the stack for its first segment, so templates
+    /// filled many times should be compiled, see `core::compiled`, to split
+    /// identifiers once and reuse lookups made at the root.
+    pub(super) fn resolve_path<'s, I>(
+        &self,
+        mut segments: I,
+    ) -> Option<(usize, &'a Value)>
+    where
+        I: Iterator<Item = &'s str>,
+    {
+        let head = match segments.next() {
+            Some(head) => head,
+            None => {
+                let frame = self.stack.len() - 1;
+                return Some((frame, self.stack[frame].value));
+            }
+        };
+        let (frame, value) = self
+            .stack
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(i, frame)| frame.value.get(head).map(|v| (i, v)))?;
+        let value =
+            segments.try_fold(value, |value, segment| value.get(segment))?;
+
+        Some((frame, value))
+    }
+
+    /// The dotted path from the root `Value` of the data `id` names when
+    /// resolved in `frame`. Empty unless usage is tracked.
+    fn path_of(&self, frame: usize, id: &str) -> String {
+        if self.usage.is_none() {
+            return String::new();
+        }
+
+        let base = &self.stack[frame].path;
+        match id {
+            "." => base.clone(),
+            _ => join_path(base, id),
+        }
+    }
+
+    /// Push the `Value` named by `id`, or its item at `index` if it is a list.
+    pub(super) fn push(
+        &mut self,
+        id: &str,
+        index: Option<usize>,
+        value: &'a Value,
+    ) {
+        let resolved = self.usage.as_ref().and_then(|_| self.resolve(id));
+        let path = match resolved {
+            Some((frame, _)) => {
+                let path = self.path_of(frame, id);
+                match index {
+                    Some(index) => join_path(&path, &index.to_string()),
+                    None => path,
                 }
             }
+            _ => String::new(),
+        };
+
+        self.stack.push(Frame { path, value });
+    }
+
+    pub(super) fn pop(&mut self) {
+        self.stack.pop();
+    }
+
+    /// The number of `Value`s on the stack, `1` outside of any `Section`.
+    pub(super) fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// In exhaustive mode, check that all of `data` was used and no tag was
+    /// missing.
+    pub(super) fn check_usage(self, data: &Value) -> Result<(), TemplateError> {
+        if let Some(usage) = self.usage {
+            let unused = usage.unused(data);
+            if !unused.is_empty() || !usage.missing.is_empty() {
+                return Err(TemplateError::NonExhaustiveTags {
+                    unused,
+                    missing: usage.missing,
+                });
+            }
         }
 
-        Ok(FilledDocument(content))
+        Ok(())
+    }
+
+    /// Record that `tag` had no data.
+    fn record_missing(&mut self, tag: &str) {
+        if let Some(usage) = &mut self.usage {
+            if !usage.missing.iter().any(|t| t == tag) {
+                usage.missing.push(tag.to_string());
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Usage {
+    /// Dotted paths of the data used, each also covering the data within it.
+    used: BTreeSet<String>,
+    /// Tags without data, in order of appearance.
+    missing: Vec<Identifier>,
+}
+
+impl Usage {
+    /// Dotted paths of every non-null scalar, empty list and empty map within
+    /// `data` which is not covered by a used path.
+    fn unused(&self, data: &Value) -> Vec<Identifier> {
+        let mut leaves = Vec::new();
+        collect_leaves(data, String::new(), &mut leaves);
+
+        leaves
+            .into_iter()
+            .filter(|leaf| !self.covers(leaf))
+            .collect()
+    }
+
+    fn covers(&self, leaf: &str) -> bool {
+        self.used.contains("")
+            || self.used.contains(leaf)
+            || leaf
+                .match_indices('.')
+                .any(|(i, _)| self.used.contains(&leaf[..i]))
     }
 }
 
-fn saturate_or_error<'a>(
-    tag_pairs: &'a [TagPair],
-    tag_key: &'a str,
-) -> Result<&'a str, TemplateError> {
-    match tag_pairs.iter().find(|t| t.key == tag_key) {
-        Some(TagPair { value, .. }) => Ok(value),
-        None => {
-            Err(TemplateError::MissingRequiredTagValue(tag_key.to_string()))
+fn collect_leaves(value: &Value, path: String, leaves: &mut Vec<String>) {
+    match value {
+        Value::Null => {}
+        Value::List(items) if !items.is_empty() => {
+            for (i, item) in items.iter().enumerate() {
+                collect_leaves(item, join_path(&path, &i.to_string()), leaves);
+            }
+        }
+        Value::Map(map) if !map.is_empty() => {
+            for (key, item) in map {
+                collect_leaves(item, join_path(&path, key), leaves);
+            }
+        }
+        _ if path.is_empty() => {}
+        _ => leaves.push(path),
+    }
+}
+
+fn join_path(base: &str, id: &str) -> String {
+    if base.is_empty() {
+        id.to_string()
+    } else {
+        format!("{}.{}", base, id)
+    }
+}
+
+fn saturate_partials<'a>(
+    partials: &[Partial],
+    context: &mut Context<'a>,
+    options: &RenderOptions,
+    content: &mut dyn Output,
+) -> Result<(), TemplateError> {
+    for partial in partials {
+        match partial {
+            Partial::StringLiteral(s) => content.write_str(s)?,
+            Partial::Tag(tag) => {
+                let value = context
+                    .lookup(&tag.id)
+                    .or(tag.default.as_ref())
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                let value = apply_filters(tag, value, options)?;
+                let escape = tag.escape(options);
+                write_value(
+                    context, options, &tag.id, escape, &value, content,
+                )?;
+            }
+            Partial::Section(id, nested) => match context.lookup_section(id) {
+                Some(Value::List(items)) => {
+                    for (index, item) in items.iter().enumerate() {
+                        context.push(id, Some(index), item);
+                        let result = saturate_partials(
+                            nested, context, options, content,
+                        );
+                        context.pop();
+                        result?;
+                    }
+                }
+                Some(value) if value.is_truthy() => {
+                    context.push(id, None, value);
+                    let result =
+                        saturate_partials(nested, context, options, content);
+                    context.pop();
+                    result?;
+                }
+                _ => {}
+            },
+            Partial::InvertedSection(id, nested) => {
+                if !context.lookup_section(id).is_some_and(Value::is_truthy) {
+                    saturate_partials(nested, context, options, content)?;
+                }
+            }
+            Partial::Include(name) => {
+                return Err(TemplateError::UnresolvedInclude(name.clone()));
+            }
+            Partial::Message(key) => {
+                content.write_str(message(options, key)?)?
+            }
+            Partial::Block(_, nested) => {
+                saturate_partials(nested, context, options, content)?;
+            }
+            Partial::Layout(name, _) => {
+                return Err(TemplateError::UnresolvedLayout(name.clone()));
+            }
+            Partial::Conditional(branches, otherwise) => {
+                let mut chosen = otherwise;
+                for (condition, nested) in branches {
+                    if evaluate(condition, context, options)?.is_truthy() {
+                        chosen = nested;
+                        break;
+                    }
+                }
+                saturate_partials(chosen, context, options, content)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write `value`, the filtered data of the tag `id`, into `content` escaped
+/// with `escape`. A tag is filled with its data, or its default if the data is
+/// missing, passed through its filters. Filters receive missing data as
+/// `Value::Null`, so that e.g. `default` can fill it in. Data still missing
+/// after filtering is handled according to the `MissingTagPolicy`.
+pub(super) fn write_value(
+    context: &mut Context<'_>,
+    options: &RenderOptions,
+    id: &str,
+    escape: Escape,
+    value: &Value,
+    content: &mut dyn Output,
+) -> Result<(), TemplateError> {
+    match value {
+        Value::Null => match options.missing_tags {
+            MissingTagPolicy::Strict if options.exhaustive => {
+                context.record_missing(id);
+            }
+            MissingTagPolicy::Strict => {
+                return Err(TemplateError::MissingRequiredTagValue(
+                    id.to_string(),
+                ));
+            }
+            MissingTagPolicy::LenientEmpty => {}
+            MissingTagPolicy::LenientPlaceholder => {
+                let placeholder = format!("[missing: {}]", id);
+                content.write_str(&escape.apply(&placeholder))?;
+            }
+        },
+        value if value.is_scalar() && escape == Escape::None => {
+            write!(content, "{}", value)?
+        }
+        value if value.is_scalar() => {
+            content.write_str(&escape.apply(&value.to_string()))?
+        }
+        _ => return Err(TemplateError::NonScalarTagValue(id.to_string())),
+    }
+
+    Ok(())
+}
+
+/// The translation of the message `key` in the locale of the document.
+pub(super) fn message<'o>(
+    options: &'o RenderOptions,
+    key: &str,
+) -> Result<&'o str, TemplateError> {
+    options
+        .messages
+        .get(key)
+        .ok_or_else(|| TemplateError::MissingMessage(key.to_string()))
+}
+
+pub(super) fn apply_filters(
+    tag: &Tag,
+    mut value: Value,
+    options: &RenderOptions,
+) -> Result<Value, TemplateError> {
+    for filter in &tag.filters {
+        let apply = options
+            .filters
+            .get(&filter.name)
+            .ok_or_else(|| TemplateError::UnknownFilter(filter.name.clone()))?;
+        value =
+            apply(&value, &filter.args, &options.locale).map_err(|cause| {
+                TemplateError::FilterFailed {
+                    tag: tag.id.clone(),
+                    filter: filter.name.clone(),
+                    cause,
+                }
+            })?;
+    }
+
+    Ok(value)
+}
+
+/// Evaluate the condition of a `Conditional`. Unlike a `Tag`, a condition may
+/// refer to missing data, which evaluates to `Value::Null`.
+pub(super) fn evaluate(
+    expr: &Expr,
+    context: &mut Context<'_>,
+    options: &RenderOptions,
+) -> Result<Value, TemplateError> {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Tag(tag) => {
+            let value = context.lookup(&tag.id).cloned().unwrap_or(Value::Null);
+            apply_filters(tag, value, options)
+        }
+        Expr::Not(e) => {
+            Ok(Value::Bool(!evaluate(e, context, options)?.is_truthy()))
+        }
+        Expr::And(left, right) => Ok(Value::Bool(
+            evaluate(left, context, options)?.is_truthy()
+                && evaluate(right, context, options)?.is_truthy(),
+        )),
+        Expr::Or(left, right) => Ok(Value::Bool(
+            evaluate(left, context, options)?.is_truthy()
+                || evaluate(right, context, options)?.is_truthy(),
+        )),
+        Expr::Compare(left, comparator, right) => {
+            let left = evaluate(left, context, options)?;
+            let right = evaluate(right, context, options)?;
+            comparator.compare(&left, &right).map(Value::Bool).map_err(
+                |cause| TemplateError::InvalidCondition {
+                    condition: expr.to_string(),
+                    cause,
+                },
+            )
         }
     }
 }
@@ -104,11 +773,12 @@ fn saturate_or_error<'a>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::expr::Comparator;
 
     #[test]
     fn test_no_tags() {
         let template = DocumentTemplate::new();
-        let saturated = template.saturate(&[]);
+        let saturated = template.saturate(&Value::default());
         assert!(saturated.is_ok());
     }
 
@@ -116,15 +786,12 @@ mod tests {
     fn test_one_tag() {
         let template = DocumentTemplate::with_partials(&[
             Partial::StringLiteral("Hello ".to_string()),
-            Partial::Tag("name".to_string()),
+            Partial::Tag(Tag::new("name")),
             Partial::StringLiteral(", welcome!".to_string()),
         ]);
 
         let filled_document = template
-            .saturate(&[TagPair {
-                key: "name".to_string(),
-                value: "Joe".to_string(),
-            }])
+            .saturate(&vec![("name", "Joe")].into_iter().collect())
             .unwrap();
 
         let expected_string = "Hello Joe, welcome!".to_string();
@@ -135,15 +802,11 @@ mod tests {
     #[test]
     #[should_panic]
     fn test_non_existent_tag() {
-        let template = DocumentTemplate::with_partials(&[Partial::Tag(
-            "name".to_string(),
-        )]);
+        let template =
+            DocumentTemplate::with_partials(&[Partial::Tag(Tag::new("name"))]);
 
         template
-            .saturate(&[TagPair {
-                key: "Hello".to_string(),
-                value: "___".to_string(),
-            }])
+            .saturate(&vec![("Hello", "___")].into_iter().collect())
             .unwrap();
     }
 
@@ -151,32 +814,783 @@ mod tests {
     fn test_multiple_tags() {
         let template = DocumentTemplate::with_partials(&[
             Partial::StringLiteral("<S1>".to_string()),
-            Partial::Tag("T1".to_string()),
+            Partial::Tag(Tag::new("T1")),
             Partial::StringLiteral("<S2>".to_string()),
-            Partial::Tag("T2".to_string()),
-            Partial::Tag("T1".to_string()),
-            Partial::Tag("T3".to_string()),
+            Partial::Tag(Tag::new("T2")),
+            Partial::Tag(Tag::new("T1")),
+            Partial::Tag(Tag::new("T3")),
         ]);
 
         let filled_document = template
-            .saturate(&[
-                TagPair {
-                    key: "T1".to_string(),
-                    value: "T1V".to_string(),
-                },
-                TagPair {
-                    key: "T2".to_string(),
-                    value: "T2V".to_string(),
-                },
-                TagPair {
-                    key: "T3".to_string(),
-                    value: "T3V".to_string(),
-                },
-            ])
+            .saturate(
+                &vec![("T1", "T1V"), ("T2", "T2V"), ("T3", "T3V")]
+                    .into_iter()
+                    .collect(),
+            )
             .unwrap();
 
         let expected_string = "<S1>T1V<S2>T2VT1VT3V".to_string();
 
         assert_eq!(expected_string, filled_document.document());
     }
+
+    fn section_template() -> DocumentTemplate {
+        DocumentTemplate::with_partials(&[
+            Partial::StringLiteral("Dear patient".to_string()),
+            Partial::Section(
+                "family".to_string(),
+                vec![
+                    Partial::StringLiteral(" ".to_string()),
+                    Partial::Tag(Tag::new("family")),
+                ],
+            ),
+            Partial::InvertedSection(
+                "family".to_string(),
+                vec![Partial::StringLiteral(" (no family name)".to_string())],
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_section_with_value() {
+        let filled_document = section_template()
+            .saturate(&vec![("family", "Smith")].into_iter().collect())
+            .unwrap();
+
+        assert_eq!("Dear patient Smith", filled_document.document());
+    }
+
+    #[test]
+    fn test_section_with_missing_value() {
+        let filled_document =
+            section_template().saturate(&Value::default()).unwrap();
+
+        assert_eq!("Dear patient (no family name)", filled_document.document());
+    }
+
+    #[test]
+    fn test_section_with_empty_value() {
+        let filled_document = section_template()
+            .saturate(&vec![("family", "")].into_iter().collect())
+            .unwrap();
+
+        assert_eq!("Dear patient (no family name)", filled_document.document());
+    }
+
+    #[test]
+    fn test_section_over_list() {
+        let template = DocumentTemplate::with_partials(&[Partial::Section(
+            "patients".to_string(),
+            vec![
+                Partial::Tag(Tag::new("name")),
+                Partial::StringLiteral(": ".to_string()),
+                Partial::Tag(Tag::new("birth_date")),
+                Partial::StringLiteral("\n".to_string()),
+            ],
+        )]);
+
+        let patients = vec![
+            vec![("name", "A B"), ("birth_date", "2001-01-01")]
+                .into_iter()
+                .collect::<Value>(),
+            vec![("name", "C D"), ("birth_date", "2002-02-02")]
+                .into_iter()
+                .collect::<Value>(),
+        ];
+        let data = vec![("patients", patients)].into_iter().collect();
+
+        let filled_document = template.saturate(&data).unwrap();
+
+        assert_eq!(
+            "A B: 2001-01-01\nC D: 2002-02-02\n",
+            filled_document.document()
+        );
+    }
+
+    #[test]
+    fn test_section_implicit_iterator() {
+        let template = DocumentTemplate::with_partials(&[Partial::Section(
+            "given".to_string(),
+            vec![
+                Partial::Tag(Tag::new(".")),
+                Partial::StringLiteral(" ".to_string()),
+            ],
+        )]);
+
+        let data = vec![("given", vec!["Jane", "Anne"])].into_iter().collect();
+        let filled_document = template.saturate(&data).unwrap();
+
+        assert_eq!("Jane Anne ", filled_document.document());
+    }
+
+    #[test]
+    fn test_section_falls_back_to_enclosing_context() {
+        let template = DocumentTemplate::with_partials(&[Partial::Section(
+            "names".to_string(),
+            vec![
+                Partial::Tag(Tag::new("family")),
+                Partial::Tag(Tag::new("hospital")),
+            ],
+        )]);
+
+        let names =
+            vec![vec![("family", "Smith")].into_iter().collect::<Value>()];
+        let data = vec![
+            ("names", Value::from(names)),
+            ("hospital", Value::from(" @ GOSH")),
+        ]
+        .into_iter()
+        .collect();
+        let filled_document = template.saturate(&data).unwrap();
+
+        assert_eq!("Smith @ GOSH", filled_document.document());
+    }
+
+    #[test]
+    fn test_inverted_section_over_empty_list() {
+        let template =
+            DocumentTemplate::with_partials(&[Partial::InvertedSection(
+                "patients".to_string(),
+                vec![Partial::StringLiteral("No patients.".to_string())],
+            )]);
+
+        let data = vec![("patients", Value::List(vec![]))]
+            .into_iter()
+            .collect();
+        let filled_document = template.saturate(&data).unwrap();
+
+        assert_eq!("No patients.", filled_document.document());
+    }
+
+    #[test]
+    fn test_dotted_path() {
+        let template = DocumentTemplate::with_partials(&[
+            Partial::Tag(Tag::new("patient.name.0.given.1")),
+            Partial::StringLiteral(" ".to_string()),
+            Partial::Tag(Tag::new("patient.name.0.family")),
+        ]);
+
+        let name = vec![
+            ("family", Value::from("Smith")),
+            ("given", Value::from(vec!["Jane", "Anne"])),
+        ]
+        .into_iter()
+        .collect::<Value>();
+        let patient = vec![("name", vec![name])].into_iter().collect::<Value>();
+        let data = vec![("patient", patient)].into_iter().collect();
+
+        let filled_document = template.saturate(&data).unwrap();
+
+        assert_eq!("Anne Smith", filled_document.document());
+    }
+
+    #[test]
+    fn test_dotted_path_inside_section() {
+        let template = DocumentTemplate::with_partials(&[Partial::Section(
+            "names".to_string(),
+            vec![Partial::Tag(Tag::new("given.0"))],
+        )]);
+
+        let name = vec![("given", vec!["Jane"])].into_iter().collect::<Value>();
+        let data = vec![("names", vec![name])].into_iter().collect();
+
+        let filled_document = template.saturate(&data).unwrap();
+
+        assert_eq!("Jane", filled_document.document());
+    }
+
+    #[test]
+    fn test_dotted_path_missing_segment() {
+        let template = DocumentTemplate::with_partials(&[Partial::Tag(
+            Tag::new("patient.name.1.family"),
+        )]);
+
+        let name = vec![("family", "Smith")].into_iter().collect::<Value>();
+        let patient = vec![("name", vec![name])].into_iter().collect::<Value>();
+        let data = vec![("patient", patient)].into_iter().collect();
+
+        assert_eq!(
+            Err(TemplateError::MissingRequiredTagValue(
+                "patient.name.1.family".to_string()
+            )),
+            template.saturate(&data)
+        );
+    }
+
+    #[test]
+    fn test_null_is_missing() {
+        let template = DocumentTemplate::with_partials(&[Partial::Tag(
+            Tag::new("family"),
+        )]);
+
+        let data = vec![("family", Value::Null)].into_iter().collect();
+
+        assert_eq!(
+            Err(TemplateError::MissingRequiredTagValue("family".to_string())),
+            template.saturate(&data)
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let template = DocumentTemplate::with_partials(&[
+            Partial::Tag(Tag::with_filters(
+                "family",
+                &[
+                    Filter::new("default", &["Unknown".into()]),
+                    Filter::new("upper", &[]),
+                ],
+            )),
+            Partial::StringLiteral(", born ".to_string()),
+            Partial::Tag(Tag::with_filters(
+                "birth_date",
+                &[Filter::new("format", &["%d %B %Y".into()])],
+            )),
+        ]);
+
+        let data = vec![("birth_date", "2012-03-04")].into_iter().collect();
+        let filled_document = template.saturate(&data).unwrap();
+
+        assert_eq!("UNKNOWN, born 04 March 2012", filled_document.document());
+    }
+
+    #[test]
+    fn test_unknown_filter() {
+        let template = DocumentTemplate::with_partials(&[Partial::Tag(
+            Tag::with_filters("name", &[Filter::new("shout", &[])]),
+        )]);
+
+        let data = vec![("name", "Joe")].into_iter().collect();
+
+        assert_eq!(
+            Err(TemplateError::UnknownFilter("shout".to_string())),
+            template.saturate(&data)
+        );
+    }
+
+    #[test]
+    fn test_display_template_error() {
+        assert_eq!(
+            "tag `patient.birthDate` has no data and no default value",
+            TemplateError::MissingRequiredTagValue(
+                "patient.birthDate".to_string()
+            )
+            .to_string()
+        );
+        assert_eq!(
+            "data does not exactly match the template; unused data: id",
+            TemplateError::NonExhaustiveTags {
+                unused: vec!["id".to_string()],
+                missing: vec![],
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_failing_filter() {
+        let template = DocumentTemplate::with_partials(&[Partial::Tag(
+            Tag::with_filters(
+                "birth_date",
+                &[Filter::new("format", &["%d".into()])],
+            ),
+        )]);
+
+        let data = vec![("birth_date", "not a date")].into_iter().collect();
+
+        assert!(matches!(
+            template.saturate(&data),
+            Err(TemplateError::FilterFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_localized_document() {
+        let template = DocumentTemplate::with_partials(&[
+            Partial::Message("greeting".to_string()),
+            Partial::StringLiteral(" ".to_string()),
+            Partial::Tag(Tag::with_filters(
+                "birth_date",
+                &[Filter::new("format", &[])],
+            )),
+        ]);
+        let data = vec![("birth_date", "2012-03-04")].into_iter().collect();
+
+        let mut options = RenderOptions {
+            locale: "fr".parse().unwrap(),
+            ..RenderOptions::default()
+        };
+        options.messages.insert("greeting", "Né le");
+        assert_eq!(
+            "Né le 4 mars 2012",
+            template.saturate_with(&data, &options).unwrap().document()
+        );
+
+        assert_eq!(
+            Err(TemplateError::MissingMessage("greeting".to_string())),
+            template.saturate(&data)
+        );
+    }
+
+    #[test]
+    fn test_custom_filter() {
+        let template = DocumentTemplate::with_partials(&[Partial::Tag(
+            Tag::with_filters("name", &[Filter::new("shout", &[])]),
+        )]);
+
+        let mut options = RenderOptions::default();
+        options
+            .filters
+            .register("shout", |v, _| Ok(Value::from(format!("{}!", v))));
+        let data = vec![("name", "Joe")].into_iter().collect();
+        let filled_document = template.saturate_with(&data, &options).unwrap();
+
+        assert_eq!("Joe!", filled_document.document());
+    }
+
+    #[test]
+    fn test_tag_default() {
+        let template = DocumentTemplate::with_partials(&[
+            Partial::Tag(Tag::with_default("family", "Unknown".into())),
+            Partial::StringLiteral(" / ".to_string()),
+            Partial::Tag(Tag::with_default("given", "Unknown".into())),
+        ]);
+
+        let data = vec![("given", "Jane")].into_iter().collect();
+        let filled_document = template.saturate(&data).unwrap();
+
+        assert_eq!("Unknown / Jane", filled_document.document());
+    }
+
+    #[test]
+    fn test_tag_default_is_filtered() {
+        let template = DocumentTemplate::with_partials(&[Partial::Tag(Tag {
+            default: Some("Unknown".into()),
+            ..Tag::with_filters("family", &[Filter::new("upper", &[])])
+        })]);
+
+        let filled_document = template.saturate(&Value::default()).unwrap();
+
+        assert_eq!("UNKNOWN", filled_document.document());
+    }
+
+    fn missing_tag_template() -> DocumentTemplate {
+        DocumentTemplate::with_partials(&[
+            Partial::StringLiteral("Dear ".to_string()),
+            Partial::Tag(Tag::new("family")),
+        ])
+    }
+
+    #[test]
+    fn test_missing_tag_lenient_empty() {
+        let options = RenderOptions {
+            missing_tags: MissingTagPolicy::LenientEmpty,
+            ..RenderOptions::default()
+        };
+
+        let filled_document = missing_tag_template()
+            .saturate_with(&Value::default(), &options)
+            .unwrap();
+
+        assert_eq!("Dear ", filled_document.document());
+    }
+
+    #[test]
+    fn test_missing_tag_lenient_placeholder() {
+        let options = RenderOptions {
+            missing_tags: MissingTagPolicy::LenientPlaceholder,
+            ..RenderOptions::default()
+        };
+
+        let filled_document = missing_tag_template()
+            .saturate_with(&Value::default(), &options)
+            .unwrap();
+
+        assert_eq!("Dear [missing: family]", filled_document.document());
+    }
+
+    #[test]
+    fn test_lenient_policy_keeps_other_errors() {
+        let template =
+            DocumentTemplate::with_partials(&[Partial::Tag(Tag::new("given"))]);
+        let options = RenderOptions {
+            missing_tags: MissingTagPolicy::LenientEmpty,
+            ..RenderOptions::default()
+        };
+
+        let data = vec![("given", vec!["Jane"])].into_iter().collect();
+
+        assert_eq!(
+            Err(TemplateError::NonScalarTagValue("given".to_string())),
+            template.saturate_with(&data, &options)
+        );
+    }
+
+    fn exhaustive() -> RenderOptions {
+        RenderOptions {
+            exhaustive: true,
+            ..RenderOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_exhaustive_all_used() {
+        let data = vec![("name", "Joe"), ("family", "Smith")]
+            .into_iter()
+            .collect();
+        let template = DocumentTemplate::with_partials(&[
+            Partial::Tag(Tag::new("name")),
+            Partial::Tag(Tag::new("family")),
+        ]);
+
+        assert!(template.saturate_with(&data, &exhaustive()).is_ok());
+    }
+
+    #[test]
+    fn test_exhaustive_reports_unused_and_missing() {
+        let data = vec![
+            ("name", "Joe"),
+            ("famly", "Smith"),
+            ("birth_date", "2012-03-04"),
+        ]
+        .into_iter()
+        .collect();
+        let template = DocumentTemplate::with_partials(&[
+            Partial::Tag(Tag::new("name")),
+            Partial::Tag(Tag::new("family")),
+            Partial::Tag(Tag::new("title")),
+            Partial::Tag(Tag::new("family")),
+        ]);
+
+        assert_eq!(
+            Err(TemplateError::NonExhaustiveTags {
+                unused: vec!["birth_date".to_string(), "famly".to_string()],
+                missing: vec!["family".to_string(), "title".to_string()],
+            }),
+            template.saturate_with(&data, &exhaustive())
+        );
+    }
+
+    #[test]
+    fn test_exhaustive_nested_paths() {
+        let name = vec![
+            ("family", Value::from("Smith")),
+            ("given", Value::from(vec!["Jane", "Anne"])),
+            ("text", Value::Null),
+        ]
+        .into_iter()
+        .collect::<Value>();
+        let patient = vec![
+            ("name", Value::from(vec![name])),
+            ("birthDate", Value::from("2012-03-04")),
+        ]
+        .into_iter()
+        .collect::<Value>();
+        let data = vec![("patient", patient)].into_iter().collect();
+
+        let template = DocumentTemplate::with_partials(&[Partial::Section(
+            "patient.name".to_string(),
+            vec![
+                Partial::Tag(Tag::new("given.0")),
+                Partial::Tag(Tag::new("family")),
+            ],
+        )]);
+
+        assert_eq!(
+            Err(TemplateError::NonExhaustiveTags {
+                unused: vec![
+                    "patient.birthDate".to_string(),
+                    "patient.name.0.given.1".to_string(),
+                ],
+                missing: vec![],
+            }),
+            template.saturate_with(&data, &exhaustive())
+        );
+    }
+
+    #[test]
+    fn test_exhaustive_whole_list_used() {
+        let data = vec![("given", vec!["Jane", "Anne"])].into_iter().collect();
+        let template = DocumentTemplate::with_partials(&[Partial::Tag(
+            Tag::with_filters("given", &[Filter::new("join", &[" ".into()])]),
+        )]);
+
+        assert!(template.saturate_with(&data, &exhaustive()).is_ok());
+    }
+
+    #[test]
+    fn test_exhaustive_lenient_missing_tags() {
+        let data = Value::default();
+        let template = missing_tag_template();
+        let options = RenderOptions {
+            missing_tags: MissingTagPolicy::LenientEmpty,
+            ..exhaustive()
+        };
+
+        assert!(template.saturate_with(&data, &options).is_ok());
+    }
+
+    #[test]
+    fn test_non_scalar_tag_value() {
+        let template =
+            DocumentTemplate::with_partials(&[Partial::Tag(Tag::new("given"))]);
+
+        let data = vec![("given", vec!["Jane"])].into_iter().collect();
+
+        assert_eq!(
+            Err(TemplateError::NonScalarTagValue("given".to_string())),
+            template.saturate(&data)
+        );
+    }
+
+    fn age_template() -> DocumentTemplate {
+        let age_at_least = |years: u32| {
+            Expr::Compare(
+                Box::new(Expr::Tag(Tag::new("age"))),
+                Comparator::Ge,
+                Box::new(Expr::Literal(years.into())),
+            )
+        };
+
+        DocumentTemplate::with_partials(&[Partial::Conditional(
+            vec![
+                (
+                    age_at_least(18),
+                    vec![Partial::StringLiteral("adult".to_string())],
+                ),
+                (
+                    age_at_least(16),
+                    vec![Partial::StringLiteral("young person".to_string())],
+                ),
+            ],
+            vec![Partial::StringLiteral("child".to_string())],
+        )])
+    }
+
+    #[test]
+    fn test_conditional_branches() {
+        let template = age_template();
+        let render = |age: u32| {
+            let data = vec![("age", age)].into_iter().collect();
+            template.saturate(&data).unwrap().document().to_string()
+        };
+
+        assert_eq!("adult", render(18));
+        assert_eq!("young person", render(17));
+        assert_eq!("child", render(3));
+    }
+
+    #[test]
+    fn test_conditional_on_missing_data() {
+        let filled_document = age_template().saturate(&Value::default());
+
+        assert_eq!("child", filled_document.unwrap().document());
+    }
+
+    #[test]
+    fn test_conditional_boolean_operators() {
+        let condition = Expr::And(
+            Box::new(Expr::Tag(Tag::new("guardian"))),
+            Box::new(Expr::Not(Box::new(Expr::Tag(Tag::new("deceased"))))),
+        );
+        let template =
+            DocumentTemplate::with_partials(&[Partial::Conditional(
+                vec![(
+                    condition,
+                    vec![Partial::StringLiteral("Dear guardian".to_string())],
+                )],
+                vec![],
+            )]);
+
+        let data = vec![("guardian", Value::from("Joe"))]
+            .into_iter()
+            .collect::<Value>();
+        assert_eq!(
+            "Dear guardian",
+            template.saturate(&data).unwrap().document()
+        );
+
+        let data =
+            vec![("guardian", Value::from("Joe")), ("deceased", true.into())]
+                .into_iter()
+                .collect::<Value>();
+        assert_eq!("", template.saturate(&data).unwrap().document());
+    }
+
+    #[test]
+    fn test_conditional_with_filters() {
+        let age = Tag::with_filters(
+            "birth_date",
+            &[Filter::new("age", &["2020-03-04".into()])],
+        );
+        let template =
+            DocumentTemplate::with_partials(&[Partial::Conditional(
+                vec![(
+                    Expr::Compare(
+                        Box::new(Expr::Tag(age)),
+                        Comparator::Lt,
+                        Box::new(Expr::Literal(16u32.into())),
+                    ),
+                    vec![Partial::StringLiteral("paediatric".to_string())],
+                )],
+                vec![],
+            )]);
+
+        let data = vec![("birth_date", "2012-03-04")].into_iter().collect();
+
+        assert_eq!("paediatric", template.saturate(&data).unwrap().document());
+    }
+
+    #[test]
+    fn test_invalid_condition() {
+        let data = vec![("age", "unknown")].into_iter().collect();
+
+        assert_eq!(
+            Err(TemplateError::InvalidCondition {
+                condition: "age >= 18".to_string(),
+                cause: "cannot order text and a number".to_string(),
+            }),
+            age_template().saturate(&data)
+        );
+    }
+
+    #[test]
+    fn test_block_is_filled_with_its_content() {
+        let template = DocumentTemplate::with_partials(&[Partial::Block(
+            "body".to_string(),
+            vec![Partial::Tag(Tag::new("name"))],
+        )]);
+        let data = vec![("name", "Jane")].into_iter().collect();
+
+        assert_eq!("Jane", template.saturate(&data).unwrap().document());
+    }
+
+    #[test]
+    fn test_unresolved_layout() {
+        let template = DocumentTemplate::with_partials(&[Partial::Layout(
+            "letter".to_string(),
+            Vec::new(),
+        )]);
+
+        assert_eq!(
+            Err(TemplateError::UnresolvedLayout("letter".to_string())),
+            template.saturate(&Value::default())
+        );
+    }
+
+    #[test]
+    fn test_render_to_writer() {
+        let template = DocumentTemplate::with_partials(&[
+            Partial::StringLiteral("Dear ".to_string()),
+            Partial::Tag(Tag::new("name")),
+            Partial::Section(
+                "given".to_string(),
+                vec![
+                    Partial::StringLiteral(" ".to_string()),
+                    Partial::Tag(Tag::new(".")),
+                ],
+            ),
+        ]);
+        let data = vec![
+            ("name", Value::from("Smith")),
+            ("given", vec!["Jane", "Anne"].into()),
+        ]
+        .into_iter()
+        .collect();
+        let options = RenderOptions::default();
+
+        let mut written = Vec::new();
+        template.render_to(&data, &options, &mut written).unwrap();
+
+        assert_eq!(
+            template.saturate(&data).unwrap().document().as_bytes(),
+            &written[..]
+        );
+    }
+
+    #[test]
+    fn test_render_to_failing_writer() {
+        let template = DocumentTemplate::with_partials(&[
+            Partial::StringLiteral("Dear ".to_string()),
+            Partial::Tag(Tag::new("name")),
+        ]);
+        let data = vec![("name", "Smith")].into_iter().collect();
+
+        let mut full = [0u8; 6];
+        let result = template.render_to(
+            &data,
+            &RenderOptions::default(),
+            &mut &mut full[..],
+        );
+
+        assert!(matches!(result, Err(TemplateError::Io(_))));
+        assert_eq!(b"Dear S", &full);
+    }
+
+    fn escaped(escape: Escape) -> RenderOptions {
+        RenderOptions {
+            escape,
+            ..RenderOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_escaped_tags() {
+        let template = DocumentTemplate::with_partials(&[
+            Partial::StringLiteral("<p>".to_string()),
+            Partial::Tag(Tag::new("family")),
+            Partial::Tag(Tag::with_filters(
+                "given",
+                &[Filter::new("upper", &[])],
+            )),
+            Partial::StringLiteral("</p>".to_string()),
+        ]);
+        let data = vec![("family", "Smith & Sons "), ("given", "<Jo>")]
+            .into_iter()
+            .collect();
+
+        let filled_document = template
+            .saturate_with(&data, &escaped(Escape::Html))
+            .unwrap();
+
+        assert_eq!(
+            "<p>Smith &amp; Sons &lt;JO&gt;</p>",
+            filled_document.document()
+        );
+    }
+
+    #[test]
+    fn test_raw_tags_are_not_escaped() {
+        let template = DocumentTemplate::with_partials(&[
+            Partial::Tag(Tag::raw("markup")),
+            Partial::StringLiteral(" ".to_string()),
+            Partial::Tag(Tag::new("markup")),
+        ]);
+        let data = vec![("markup", "\\textbf{50%}")].into_iter().collect();
+
+        let filled_document = template
+            .saturate_with(&data, &escaped(Escape::Latex))
+            .unwrap();
+
+        assert_eq!(
+            "\\textbf{50%} \\textbackslash{}textbf\\{50\\%\\}",
+            filled_document.document()
+        );
+    }
+
+    #[test]
+    fn test_escaped_placeholder() {
+        let template = DocumentTemplate::with_partials(&[Partial::Tag(
+            Tag::new("birth_date"),
+        )]);
+        let options = RenderOptions {
+            missing_tags: MissingTagPolicy::LenientPlaceholder,
+            ..escaped(Escape::Latex)
+        };
+
+        let filled_document =
+            template.saturate_with(&Value::default(), &options).unwrap();
+
+        assert_eq!("[missing: birth\\_date]", filled_document.document());
+    }
 }
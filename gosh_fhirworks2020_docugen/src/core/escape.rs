@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::Path;
+
+/// How the data filling `Tag`s is escaped for the format of the document, so
+/// that e.g. a family name containing `&` does not break an HTML or LaTeX
+/// document. Text of the template itself is never escaped, and neither are
+/// raw tags, `{{{ x }}}` or `{{& x }}`.
+#[derive(
+    Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize,
+)]
+pub enum Escape {
+    /// Data is written as it is.
+    #[default]
+    #[serde(rename = "text")]
+    None,
+    /// `&`, `<`, `>`, `"` and `'` are written as character references.
+    #[serde(rename = "html")]
+    Html,
+    /// Characters special to LaTeX are written as commands which print them.
+    #[serde(rename = "latex")]
+    Latex,
+    /// Data containing a comma, a double quote or a line break is quoted as a
+    /// CSV field, with its double quotes doubled.
+    #[serde(rename = "csv")]
+    Csv,
+}
+
+impl Escape {
+    /// The escaping for a document format named `format`, e.g. `html`, or for
+    /// files with the extension `format`. `None` for an unknown format.
+    pub fn from_format(format: &str) -> Option<Self> {
+        match format.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Some(Escape::None),
+            "html" | "htm" | "xml" => Some(Escape::Html),
+            "latex" | "tex" => Some(Escape::Latex),
+            "csv" => Some(Escape::Csv),
+            _ => None,
+        }
+    }
+
+    /// The escaping for the documents filled from the template at `path`,
+    /// from the extension the documents have before the `.template`
+    /// extension, e.g. `letter.html.template` fills HTML documents.
+    pub fn for_template(path: &Path) -> Self {
+        let path = match path.extension() {
+            Some(extension) if extension == "template" => {
+                Path::new(path.file_stem().unwrap_or_default())
+            }
+            _ => path,
+        };
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Escape::from_format)
+            .unwrap_or_default()
+    }
+
+    /// Escape `text` to be written into a document.
+    pub fn apply<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        match self {
+            Escape::None => Cow::Borrowed(text),
+            Escape::Html => replace(text, |c| match c {
+                '&' => Some("&amp;"),
+                '<' => Some("&lt;"),
+                '>' => Some("&gt;"),
+                '"' => Some("&quot;"),
+                '\'' => Some("&#39;"),
+                _ => None,
+            }),
+            Escape::Latex => replace(text, |c| match c {
+                '&' => Some("\\&"),
+                '%' => Some("\\%"),
+                '$' => Some("\\$"),
+                '#' => Some("\\#"),
+                '_' => Some("\\_"),
+                '{' => Some("\\{"),
+                '}' => Some("\\}"),
+                '~' => Some("\\textasciitilde{}"),
+                '^' => Some("\\textasciicircum{}"),
+                '\\' => Some("\\textbackslash{}"),
+                _ => None,
+            }),
+            Escape::Csv if text.contains(&[',', '"', '\n', '\r'][..]) => {
+                Cow::Owned(format!("\"{}\"", text.replace('"', "\"\"")))
+            }
+            Escape::Csv => Cow::Borrowed(text),
+        }
+    }
+}
+
+/// `text` with every character for which `replacement` is `Some` replaced.
+fn replace<'t>(
+    text: &'t str,
+    replacement: impl Fn(char) -> Option<&'static str>,
+) -> Cow<'t, str> {
+    if !text.chars().any(|c| replacement(c).is_some()) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match replacement(c) {
+            Some(replaced) => escaped.push_str(replaced),
+            None => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_html() {
+        assert_eq!(
+            "Smith &amp; Sons &lt;b&gt; &quot;Jo&#39;s&quot;",
+            Escape::Html.apply("Smith & Sons <b> \"Jo's\"")
+        );
+        assert!(matches!(Escape::Html.apply("Smith"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_latex() {
+        assert_eq!(
+            "50\\% \\& \\$5 \\#1 a\\_b \\{x\\} \\textasciitilde{} \
+             \\textasciicircum{} \\textbackslash{}",
+            Escape::Latex.apply("50% & $5 #1 a_b {x} ~ ^ \\")
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!("Smith", Escape::Csv.apply("Smith"));
+        assert_eq!("\"Smith, Jane\"", Escape::Csv.apply("Smith, Jane"));
+        assert_eq!("\"Jo \"\"JJ\"\"\"", Escape::Csv.apply("Jo \"JJ\""));
+        assert_eq!("\"a\nb\"", Escape::Csv.apply("a\nb"));
+    }
+
+    #[test]
+    fn test_none() {
+        assert_eq!("<&>", Escape::None.apply("<&>"));
+    }
+
+    #[test]
+    fn test_for_template() {
+        let escape = |path: &str| Escape::for_template(Path::new(path));
+
+        assert_eq!(Escape::Html, escape("templates/letter.html.template"));
+        assert_eq!(Escape::Latex, escape("letter.tex.template"));
+        assert_eq!(Escape::Csv, escape("list.CSV"));
+        assert_eq!(Escape::None, escape("letter.template"));
+        assert_eq!(Escape::None, escape("letter.md.template"));
+        assert_eq!(None, Escape::from_format("pdf"));
+    }
+}
@@ -0,0 +1,230 @@
+use super::document::Tag;
+use super::value::Value;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// An `Expr` is the condition of a `Conditional`, e.g. `age >= 18` or
+/// `patient.name.0.family and not patient.deceasedBoolean`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Expr {
+    /// A literal string, number or boolean.
+    Literal(Value),
+    /// The data named by a tag identifier, transformed by the tag's filters.
+    /// Missing data evaluates to `Value::Null`.
+    Tag(Tag),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, Comparator, Box<Expr>),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Comparator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparator {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Comparator::Eq => "==",
+            Comparator::Ne => "!=",
+            Comparator::Lt => "<",
+            Comparator::Le => "<=",
+            Comparator::Gt => ">",
+            Comparator::Ge => ">=",
+        }
+    }
+
+    /// Compare `left` with `right`. Numbers are ordered numerically and text
+    /// lexicographically, which also orders FHIR dates. Missing data is never
+    /// ordered, so e.g. `age < 16` is false when `age` is missing.
+    pub fn compare(self, left: &Value, right: &Value) -> Result<bool, String> {
+        let ordering = match (left, right) {
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            _ => None,
+        };
+
+        match (self, ordering) {
+            (Comparator::Eq, _) => Ok(left == right),
+            (Comparator::Ne, _) => Ok(left != right),
+            (_, None) if *left == Value::Null || *right == Value::Null => {
+                Ok(false)
+            }
+            (_, None) => Err(format!(
+                "cannot order {} and {}",
+                describe(left),
+                describe(right)
+            )),
+            (Comparator::Lt, Some(ordering)) => Ok(ordering.is_lt()),
+            (Comparator::Le, Some(ordering)) => Ok(ordering.is_le()),
+            (Comparator::Gt, Some(ordering)) => Ok(ordering.is_gt()),
+            (Comparator::Ge, Some(ordering)) => Ok(ordering.is_ge()),
+        }
+    }
+}
+
+fn describe(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "missing data",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "text",
+        Value::List(_) => "a list",
+        Value::Map(_) => "a map",
+    }
+}
+
+impl Expr {
+    /// How tightly the expression binds, used to parenthesize sub-expressions
+    /// when displaying it.
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Or(..) => 1,
+            Expr::And(..) => 2,
+            Expr::Not(_) => 3,
+            Expr::Compare(..) => 4,
+            Expr::Literal(_) | Expr::Tag(_) => 5,
+        }
+    }
+}
+
+/// Expressions are displayed as they are written in templates.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Literal(value) => write_literal(f, value),
+            Expr::Tag(tag) => {
+                write!(f, "{}", tag.id)?;
+                for filter in &tag.filters {
+                    write!(f, " | {}", filter.name)?;
+                    if !filter.args.is_empty() {
+                        write!(f, "(")?;
+                        for (i, arg) in filter.args.iter().enumerate() {
+                            if i > 0 {
+                                write!(f, ", ")?;
+                            }
+                            write_literal(f, arg)?;
+                        }
+                        write!(f, ")")?;
+                    }
+                }
+                Ok(())
+            }
+            Expr::Not(e) => {
+                write!(f, "not ")?;
+                write_operand(f, e, self.precedence())
+            }
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                let keyword = match self {
+                    Expr::And(..) => "and",
+                    _ => "or",
+                };
+                write_operand(f, left, self.precedence())?;
+                write!(f, " {} ", keyword)?;
+                write_operand(f, right, self.precedence() + 1)
+            }
+            Expr::Compare(left, comparator, right) => {
+                write_operand(f, left, self.precedence() + 1)?;
+                write!(f, " {} ", comparator.symbol())?;
+                write_operand(f, right, self.precedence() + 1)
+            }
+        }
+    }
+}
+
+fn write_operand(
+    f: &mut fmt::Formatter<'_>,
+    expr: &Expr,
+    precedence: u8,
+) -> fmt::Result {
+    if expr.precedence() < precedence {
+        write!(f, "({})", expr)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
+
+fn write_literal(f: &mut fmt::Formatter<'_>, value: &Value) -> fmt::Result {
+    match value {
+        Value::String(s) => {
+            write!(f, "\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+        value => write!(f, "{}", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::filter::Filter;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_compare_numbers_and_text() {
+        let eighteen = Value::from(18u32);
+
+        assert_eq!(Ok(true), Comparator::Ge.compare(&eighteen, &eighteen));
+        assert_eq!(Ok(false), Comparator::Lt.compare(&eighteen, &eighteen));
+        assert_eq!(
+            Ok(true),
+            Comparator::Lt
+                .compare(&Value::from("2012-03-04"), &Value::from("2020-01"))
+        );
+        assert_eq!(
+            Ok(true),
+            Comparator::Ne.compare(&eighteen, &Value::from("18"))
+        );
+    }
+
+    #[test]
+    fn test_compare_missing_data() {
+        let eighteen = Value::from(18u32);
+
+        assert_eq!(Ok(false), Comparator::Lt.compare(&Value::Null, &eighteen));
+        assert_eq!(Ok(false), Comparator::Ge.compare(&Value::Null, &eighteen));
+        assert_eq!(
+            Ok(true),
+            Comparator::Eq.compare(&Value::Null, &Value::Null)
+        );
+    }
+
+    #[test]
+    fn test_compare_incomparable() {
+        assert_eq!(
+            Err("cannot order a number and text".to_string()),
+            Comparator::Gt.compare(&Value::from(18u32), &Value::from("x"))
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let age = Expr::Tag(Tag::with_filters(
+            "birthDate",
+            &[Filter::new("age", &["2020-01-01".into()])],
+        ));
+        let adult = Expr::Compare(
+            Box::new(age),
+            Comparator::Ge,
+            Box::new(Expr::Literal(18u32.into())),
+        );
+        let guardian = Expr::Or(
+            Box::new(Expr::Tag(Tag::new("guardian"))),
+            Box::new(Expr::Literal(Value::Bool(false))),
+        );
+        let expr =
+            Expr::And(Box::new(Expr::Not(Box::new(adult))), Box::new(guardian));
+
+        assert_eq!(
+            "not birthDate | age(\"2020-01-01\") >= 18 and \
+             (guardian or false)",
+            expr.to_string()
+        );
+    }
+}
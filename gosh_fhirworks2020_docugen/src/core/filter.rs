@@ -0,0 +1,471 @@
+use super::document::Identifier;
+use super::value::Value;
+use crate::data::fhir_date::{FHIRDate, FHIRDateTime};
+use crate::data::locale::Locale;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A `Filter` transforms the value of a `Tag` before it is written into the
+/// document, e.g. `{{ name | upper }}` or `{{ birth_date | format("%Y") }}`.
+/// Filters are applied left to right.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Filter {
+    pub name: Identifier,
+    pub args: Vec<Value>,
+}
+
+impl Filter {
+    pub fn new(name: &str, args: &[Value]) -> Self {
+        Self {
+            name: name.to_string(),
+            args: args.to_vec(),
+        }
+    }
+}
+
+/// The implementation of a filter. It receives the value being filtered, which
+/// is `Value::Null` for missing data, the filter's arguments and the `Locale`
+/// of the document.
+pub type FilterFn =
+    dyn Fn(&Value, &[Value], &Locale) -> Result<Value, String> + Send + Sync;
+
+/// A `FilterRegistry` maps filter names used in templates to their
+/// implementations. The `Default` registry contains the built-in filters:
+///
+/// - `upper`, `lower`, `capitalize`: change the case of text;
+/// - `format([pattern])`: format a `FHIRDate` or `FHIRDateTime` in the locale
+///   of the document, see `FHIRDateTime::format_in`. The pattern may also be
+///   `"long"`, the default, or `"short"`, for the usual order of dates of the
+///   locale. Partial dates missing a part the pattern writes, e.g. `2012`,
+///   are written as they are;
+/// - `age([date])`: the age in years on `date`, today by default, of someone
+///   born on a `FHIRDate` or `FHIRDateTime`;
+/// - `default(value)`: replace missing or empty data by `value`;
+/// - `truncate(length[, suffix])`: shorten text to `length` characters,
+///   ending it with `suffix` if it was shortened;
+/// - `pad_left(width[, fill])`, `pad_right(width[, fill])`: pad text to
+///   `width` characters with `fill`, a space by default;
+/// - `join(separator)`: join the items of a list.
+///
+/// Filters of FHIR resources, such as `name`, are registered by
+/// `data::filters`.
+#[derive(Clone)]
+pub struct FilterRegistry {
+    filters: HashMap<Identifier, Arc<FilterFn>>,
+}
+
+impl FilterRegistry {
+    /// A registry without any filters.
+    pub fn empty() -> Self {
+        Self {
+            filters: HashMap::new(),
+        }
+    }
+
+    /// Register `filter` under `name`, replacing any filter of the same name.
+    pub fn register<F>(&mut self, name: &str, filter: F)
+    where
+        F: Fn(&Value, &[Value]) -> Result<Value, String>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.register_localized(name, move |value, args, _| {
+            filter(value, args)
+        });
+    }
+
+    /// Register `filter` under `name` as `register` does, for a filter which
+    /// depends on the `Locale` of the document.
+    pub fn register_localized<F>(&mut self, name: &str, filter: F)
+    where
+        F: Fn(&Value, &[Value], &Locale) -> Result<Value, String>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.filters.insert(name.to_string(), Arc::new(filter));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FilterFn> {
+        self.filters.get(name).map(|f| f.as_ref())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.filters.contains_key(name)
+    }
+}
+
+impl Default for FilterRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register("upper", |v, _| map_text(v, |s| s.to_uppercase()));
+        registry.register("lower", |v, _| map_text(v, |s| s.to_lowercase()));
+        registry.register("capitalize", |v, _| map_text(v, capitalize));
+        registry.register_localized("format", format_date);
+        registry.register("age", age);
+        registry.register("default", default);
+        registry.register("truncate", truncate);
+        registry.register("pad_left", |v, args| pad(v, args, true));
+        registry.register("pad_right", |v, args| pad(v, args, false));
+        registry.register("join", join);
+        registry
+    }
+}
+
+impl fmt::Debug for FilterRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names = self.filters.keys().collect::<Vec<_>>();
+        names.sort();
+        f.debug_struct("FilterRegistry")
+            .field("filters", &names)
+            .finish()
+    }
+}
+
+/// Apply `f` to the textual form of a scalar `value`. Missing data is passed
+/// through untouched so that a later `default` filter can replace it.
+fn map_text<F>(value: &Value, f: F) -> Result<Value, String>
+where
+    F: Fn(&str) -> String,
+{
+    match value {
+        Value::Null => Ok(Value::Null),
+        v if v.is_scalar() => Ok(Value::String(f(&v.to_string()))),
+        _ => Err("expected text, found a list or a map".to_string()),
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn format_date(
+    value: &Value,
+    args: &[Value],
+    locale: &Locale,
+) -> Result<Value, String> {
+    let calendar = locale.calendar();
+    let pattern = match args.first() {
+        Some(_) => text_arg(args, 0)?,
+        None => calendar.long_date.to_string(),
+    };
+    let pattern = match pattern.as_str() {
+        "long" => calendar.long_date,
+        "short" => calendar.short_date,
+        pattern => pattern,
+    };
+    match value {
+        Value::Null => Ok(Value::Null),
+        Value::String(s) => {
+            let date = s.parse::<FHIRDateTime>()?;
+            match date.format_in(pattern, locale) {
+                Ok(formatted) => Ok(Value::String(formatted)),
+                // Only patterns which fail on complete dates are errors.
+                Err(e) => {
+                    let complete = "2000-01-01T00:00:00Z"
+                        .parse::<FHIRDateTime>()
+                        .expect("a complete date is valid");
+                    complete.format_in(pattern, locale).map_err(|_| e)?;
+                    Ok(value.clone())
+                }
+            }
+        }
+        _ => Err("expected a date".to_string()),
+    }
+}
+
+fn age(value: &Value, args: &[Value]) -> Result<Value, String> {
+    let on = match args.first() {
+        Some(_) => text_arg(args, 0)?.parse::<FHIRDateTime>()?.date().clone(),
+        None => FHIRDate::today(),
+    };
+    match value {
+        Value::Null => Ok(Value::Null),
+        Value::String(s) => {
+            let born = s.parse::<FHIRDateTime>()?;
+            born.date().age_on(&on).map(Value::from)
+        }
+        _ => Err("expected a date".to_string()),
+    }
+}
+
+fn default(value: &Value, args: &[Value]) -> Result<Value, String> {
+    let fallback = args
+        .first()
+        .ok_or_else(|| "missing argument 1".to_string())?;
+
+    if value.is_truthy() {
+        Ok(value.clone())
+    } else {
+        Ok(fallback.clone())
+    }
+}
+
+fn truncate(value: &Value, args: &[Value]) -> Result<Value, String> {
+    let length = count_arg(args, 0)?;
+    let suffix = match args.get(1) {
+        Some(_) => text_arg(args, 1)?,
+        None => String::new(),
+    };
+
+    map_text(value, |s| {
+        if s.chars().count() <= length {
+            s.to_string()
+        } else {
+            let mut truncated = s.chars().take(length).collect::<String>();
+            truncated.push_str(&suffix);
+            truncated
+        }
+    })
+}
+
+fn pad(value: &Value, args: &[Value], left: bool) -> Result<Value, String> {
+    let width = count_arg(args, 0)?;
+    let fill = match args.get(1) {
+        Some(_) => {
+            let fill = text_arg(args, 1)?;
+            let mut chars = fill.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => return Err("fill must be a single character".to_string()),
+            }
+        }
+        None => ' ',
+    };
+
+    map_text(value, |s| {
+        let padding = fill
+            .to_string()
+            .repeat(width.saturating_sub(s.chars().count()));
+        if left {
+            padding + s
+        } else {
+            s.to_string() + &padding
+        }
+    })
+}
+
+fn join(value: &Value, args: &[Value]) -> Result<Value, String> {
+    let separator = text_arg(args, 0)?;
+    match value {
+        Value::List(items) => Ok(Value::String(
+            items
+                .iter()
+                .filter(|item| **item != Value::Null)
+                .map(|item| item.to_string())
+                .collect::<Vec<_>>()
+                .join(&separator),
+        )),
+        v => Ok(v.clone()),
+    }
+}
+
+pub(crate) fn text_arg(args: &[Value], index: usize) -> Result<String, String> {
+    match args.get(index) {
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(_) => Err(format!("argument {} must be text", index + 1)),
+        None => Err(format!("missing argument {}", index + 1)),
+    }
+}
+
+fn count_arg(args: &[Value], index: usize) -> Result<usize, String> {
+    match args.get(index) {
+        Some(Value::Number(n)) if *n >= 0.0 && n.fract() == 0.0 => {
+            Ok(*n as usize)
+        }
+        Some(_) => {
+            Err(format!("argument {} must be a whole number", index + 1))
+        }
+        None => Err(format!("missing argument {}", index + 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn apply(
+        name: &str,
+        value: Value,
+        args: &[Value],
+    ) -> Result<Value, String> {
+        apply_in(name, value, args, "en-GB")
+    }
+
+    fn apply_in(
+        name: &str,
+        value: Value,
+        args: &[Value],
+        locale: &str,
+    ) -> Result<Value, String> {
+        let registry = FilterRegistry::default();
+        let filter = registry.get(name).unwrap();
+        filter(&value, args, &locale.parse().unwrap())
+    }
+
+    #[test]
+    fn test_case_filters() {
+        assert_eq!(
+            Ok(Value::from("SMITH")),
+            apply("upper", "Smith".into(), &[])
+        );
+        assert_eq!(
+            Ok(Value::from("smith")),
+            apply("lower", "Smith".into(), &[])
+        );
+        assert_eq!(
+            Ok(Value::from("Éloïse")),
+            apply("capitalize", "éloïse".into(), &[])
+        );
+        assert_eq!(Ok(Value::Null), apply("upper", Value::Null, &[]));
+    }
+
+    #[test]
+    fn test_format_filter() {
+        assert_eq!(
+            Ok(Value::from("04 March 2012")),
+            apply("format", "2012-03-04".into(), &["%d %B %Y".into()])
+        );
+        assert!(apply("format", "not a date".into(), &["%Y".into()]).is_err());
+        assert_eq!(
+            Ok(Value::from("2012")),
+            apply("format", "2012".into(), &["%d %B %Y".into()])
+        );
+        assert_eq!(
+            Ok(Value::from("2012-03-04")),
+            apply("format", "2012-03-04".into(), &["%H:%M".into()])
+        );
+        assert!(apply("format", "2012".into(), &["%d %Q".into()]).is_err());
+        assert_eq!(
+            Ok(Value::from("4 March 2012, 14:30")),
+            apply(
+                "format",
+                "2012-03-04T14:30:00Z".into(),
+                &["%e %B %Y, %H:%M".into()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_filter_in_locale() {
+        let date = Value::from("2012-03-04");
+
+        assert_eq!(
+            Ok(Value::from("4 March 2012")),
+            apply("format", date.clone(), &[])
+        );
+        assert_eq!(
+            Ok(Value::from("March 4, 2012")),
+            apply_in("format", date.clone(), &[], "en-US")
+        );
+        assert_eq!(
+            Ok(Value::from("03/04/2012")),
+            apply_in("format", date.clone(), &["short".into()], "en-US")
+        );
+        assert_eq!(
+            Ok(Value::from("4 de marzo de 2012")),
+            apply_in("format", date.clone(), &["long".into()], "es")
+        );
+        assert_eq!(
+            Ok(Value::from("mars 2012")),
+            apply_in("format", date, &["%B %Y".into()], "fr")
+        );
+    }
+
+    #[test]
+    fn test_age_filter() {
+        assert_eq!(
+            Ok(Value::from(7u32)),
+            apply("age", "2012-03-04".into(), &["2020-03-03".into()])
+        );
+        assert_eq!(
+            Ok(Value::from(8u32)),
+            apply(
+                "age",
+                "2012-03-04T08:00:00Z".into(),
+                &["2020-03-04T07:00:00Z".into()]
+            )
+        );
+        assert_eq!(Ok(Value::Null), apply("age", Value::Null, &[]));
+        assert!(apply("age", "2012-03-04".into(), &[]).is_ok());
+    }
+
+    #[test]
+    fn test_default_filter() {
+        assert_eq!(
+            Ok(Value::from("Unknown")),
+            apply("default", Value::Null, &["Unknown".into()])
+        );
+        assert_eq!(
+            Ok(Value::from("Unknown")),
+            apply("default", "".into(), &["Unknown".into()])
+        );
+        assert_eq!(
+            Ok(Value::from("Smith")),
+            apply("default", "Smith".into(), &["Unknown".into()])
+        );
+    }
+
+    #[test]
+    fn test_truncate_filter() {
+        assert_eq!(
+            Ok(Value::from("Wolfe...")),
+            apply(
+                "truncate",
+                "Wolfeschlegel".into(),
+                &[5u32.into(), "...".into()]
+            )
+        );
+        assert_eq!(
+            Ok(Value::from("Li")),
+            apply("truncate", "Li".into(), &[5u32.into(), "...".into()])
+        );
+        assert!(apply("truncate", "Li".into(), &["5".into()]).is_err());
+    }
+
+    #[test]
+    fn test_pad_filters() {
+        assert_eq!(
+            Ok(Value::from("007")),
+            apply("pad_left", 7u32.into(), &[3u32.into(), "0".into()])
+        );
+        assert_eq!(
+            Ok(Value::from("Li   ")),
+            apply("pad_right", "Li".into(), &[5u32.into()])
+        );
+        assert!(apply("pad_left", "Li".into(), &[5u32.into(), "ab".into()])
+            .is_err());
+    }
+
+    #[test]
+    fn test_join_filter() {
+        assert_eq!(
+            Ok(Value::from("Jane Anne")),
+            apply("join", vec!["Jane", "Anne"].into(), &[" ".into()])
+        );
+    }
+
+    #[test]
+    fn test_register_custom_filter() {
+        let mut registry = FilterRegistry::empty();
+        assert!(!registry.contains("reverse"));
+
+        registry.register("reverse", |v, _| {
+            map_text(v, |s| s.chars().rev().collect())
+        });
+
+        let filter = registry.get("reverse").unwrap();
+        assert_eq!(
+            Ok(Value::from("cba")),
+            filter(&"abc".into(), &[], &Locale::default())
+        );
+    }
+}
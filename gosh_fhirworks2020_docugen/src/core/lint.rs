@@ -0,0 +1,775 @@
+use super::document::{DocumentTemplate, Identifier, Partial, Tag};
+use super::expr::Expr;
+use super::filter::FilterRegistry;
+use super::loader::{resolve_includes, IncludeError, TemplateSource};
+use super::metadata::{MetadataError, TemplateFile};
+use super::parser::{self, Delimiters, TemplateParseError};
+use crate::data::schema::Shape;
+use std::fmt;
+
+/// A problem found in a template by `lint`.
+#[derive(Debug, PartialEq)]
+pub enum Lint {
+    /// The template could not be parsed, e.g. because a section is never
+    /// closed or is closed by the tag of another section.
+    IllFormed(TemplateParseError),
+    /// The front matter of the template could not be read.
+    Metadata(MetadataError),
+    /// An included template could not be read or parsed, or templates include
+    /// each other in a cycle.
+    Include(IncludeError),
+    /// A tag uses a filter which is not in the `FilterRegistry`, so filling
+    /// the template will fail.
+    UnknownFilter { tag: Identifier, filter: Identifier },
+    /// A tag names data which the data filling the template never has.
+    UnknownField(UnknownField),
+    /// An escape sequence which likely does not do what was intended, e.g.
+    /// `\{\{`, which is written as `{{` and looks like a tag in the document.
+    SuspiciousEscape {
+        origin: Option<String>,
+        line: u32,
+        column: u32,
+        source_line: String,
+        message: String,
+        help: &'static str,
+    },
+}
+
+impl Lint {
+    /// Whether the template cannot be used at all, rather than likely being
+    /// wrong.
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            Lint::IllFormed(_)
+                | Lint::Metadata(_)
+                | Lint::Include(_)
+                | Lint::UnknownField(_)
+        )
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lint::IllFormed(error) => write!(f, "{}", error),
+            Lint::Metadata(error) => write!(f, "error: {}", error),
+            Lint::Include(error) => write!(f, "error: {}", error),
+            Lint::UnknownFilter { tag, filter } => write!(
+                f,
+                "warning: tag `{}` uses the unknown filter `{}`",
+                tag, filter
+            ),
+            Lint::UnknownField(unknown) => write!(f, "error: {}", unknown),
+            Lint::SuspiciousEscape {
+                origin,
+                line,
+                column,
+                source_line,
+                message,
+                help,
+            } => {
+                writeln!(f, "warning: {}", message)?;
+                parser::write_snippet(
+                    f,
+                    origin.as_deref(),
+                    *line,
+                    *column,
+                    source_line,
+                )?;
+                let gutter = " ".repeat(line.to_string().len());
+                write!(f, "\n{} = help: {}", gutter, help)
+            }
+        }
+    }
+}
+
+/// A tag identifier naming data which is not in the `Shape` of the data
+/// filling the template, e.g. `patient.brithDate`.
+#[derive(Debug, PartialEq)]
+pub struct UnknownField {
+    pub tag: Identifier,
+    /// The leading segments of `tag` naming data which exists, e.g. `patient`.
+    /// Empty if the first segment names no data.
+    pub found: String,
+    /// The segment of `tag` naming data which does not exist.
+    pub segment: String,
+    /// A hint on how to fix the tag.
+    pub help: Option<String>,
+}
+
+impl fmt::Display for UnknownField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.found.is_empty() {
+            write!(f, "`{}` is not in the data", self.tag)?;
+        } else {
+            write!(
+                f,
+                "`{}` is not in the data: `{}` has no field `{}`",
+                self.tag, self.found, self.segment
+            )?;
+        }
+        if let Some(help) = &self.help {
+            write!(f, "\n  = help: {}", help)?;
+        }
+        Ok(())
+    }
+}
+
+/// A tag identifier used by a template, with the identifiers of the sections
+/// it is nested in, outermost first. Within a section, a tag is looked up in
+/// the data of the section before the enclosing data.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RequiredTag {
+    pub sections: Vec<Identifier>,
+    pub id: Identifier,
+}
+
+/// Displayed as the identifiers of the sections and the tag, separated by
+/// `>`, e.g. `patients > name.0.family`.
+impl fmt::Display for RequiredTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for section in &self.sections {
+            write!(f, "{} > ", section)?;
+        }
+        write!(f, "{}", self.id)
+    }
+}
+
+/// What `lint` found in a template.
+#[derive(Debug, PartialEq, Default)]
+pub struct LintReport {
+    /// Tags, sections and conditions used by the template and the templates
+    /// it includes, in order of first use.
+    pub tags: Vec<RequiredTag>,
+    pub lints: Vec<Lint>,
+}
+
+impl LintReport {
+    pub fn errors(&self) -> usize {
+        self.lints.iter().filter(|lint| lint.is_error()).count()
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.lints.len() - self.errors()
+    }
+}
+
+/// Check the template `source`, called `origin`, without filling it. It may
+/// start with front matter, see `TemplateFile`. Its includes are read from
+/// `includes` and its filters are looked up in `filters`. If the `shape` of
+/// the data it will be filled with is given, its tags are also checked
+/// against it, see `check_schema`.
+pub fn lint(
+    source: &str,
+    origin: &str,
+    includes: &dyn TemplateSource,
+    filters: &FilterRegistry,
+    shape: Option<&Shape>,
+) -> LintReport {
+    let mut report = LintReport::default();
+
+    let file = match TemplateFile::parse(source) {
+        Ok(file) => file,
+        Err(error) => {
+            report.lints.push(Lint::Metadata(error));
+            return report;
+        }
+    };
+    let offset = file.front_matter_lines;
+
+    let delimiters = includes.delimiters();
+    let template = match parser::parse_template_with(file.template, &delimiters)
+    {
+        Ok(template) => template,
+        Err(error) => {
+            let error = error.with_origin(origin).offset_lines(offset);
+            report.lints.push(Lint::IllFormed(error));
+            return report;
+        }
+    };
+    // Backslashes are ordinary text with other delimiters.
+    if delimiters == Delimiters::default() {
+        let mut escapes = suspicious_escapes(file.template, origin);
+        for escape in &mut escapes {
+            if let Lint::SuspiciousEscape { line, .. } = escape {
+                *line += offset;
+            }
+        }
+        report.lints.extend(escapes);
+    }
+
+    match resolve_includes(&template, includes) {
+        Ok(template) => {
+            let mut checker = Checker {
+                filters: Some(filters),
+                sections: Vec::new(),
+                shapes: shape.map(|shape| vec![shape]),
+                report: &mut report,
+            };
+            checker.check(&template);
+        }
+        Err(error) => report.lints.push(Lint::Include(error)),
+    }
+
+    report
+}
+
+/// Check that every tag of `template` names data which can be in data of the
+/// given `shape`. Tags within a `Section` are checked against the shape of
+/// the data of the section first, as they are resolved when filling.
+pub fn check_schema(
+    template: &DocumentTemplate,
+    shape: &Shape,
+) -> Vec<UnknownField> {
+    let mut report = LintReport::default();
+    let mut checker = Checker {
+        filters: None,
+        sections: Vec::new(),
+        shapes: Some(vec![shape]),
+        report: &mut report,
+    };
+    checker.check(template);
+
+    report
+        .lints
+        .into_iter()
+        .filter_map(|lint| match lint {
+            Lint::UnknownField(unknown) => Some(unknown),
+            _ => None,
+        })
+        .collect()
+}
+
+struct Checker<'a> {
+    /// Filters tags may use, if they are checked.
+    filters: Option<&'a FilterRegistry>,
+    /// Identifiers of the sections being checked, outermost first.
+    sections: Vec<Identifier>,
+    /// The shapes of the data tags are resolved against, if they are checked:
+    /// the shape of the root data, then that of the data of each enclosing
+    /// `Section`.
+    shapes: Option<Vec<&'a Shape>>,
+    report: &'a mut LintReport,
+}
+
+impl<'a> Checker<'a> {
+    fn check(&mut self, template: &DocumentTemplate) {
+        self.check_partials(&template.partials);
+    }
+
+    fn check_partials(&mut self, partials: &[Partial]) {
+        for partial in partials {
+            match partial {
+                Partial::StringLiteral(_)
+                | Partial::Include(_)
+                | Partial::Message(_)
+                | Partial::Layout(..) => {}
+                Partial::Block(_, nested) => self.check_partials(nested),
+                Partial::Tag(tag) => self.check_tag(tag),
+                Partial::Section(id, nested) => {
+                    self.require(id);
+                    let shape = self.resolve(id).map(|shape| match shape {
+                        Shape::List(item) => &**item,
+                        shape => shape,
+                    });
+                    self.sections.push(id.clone());
+                    if let Some(shapes) = &mut self.shapes {
+                        // Tags within a section of unknown data are not
+                        // reported again.
+                        shapes.push(shape.unwrap_or(&Shape::Any));
+                    }
+                    self.check_partials(nested);
+                    self.sections.pop();
+                    if let Some(shapes) = &mut self.shapes {
+                        shapes.pop();
+                    }
+                }
+                // An inverted section is only filled without its data, so
+                // tags within it are resolved against the enclosing data.
+                Partial::InvertedSection(id, nested) => {
+                    self.require(id);
+                    self.resolve(id);
+                    self.sections.push(id.clone());
+                    self.check_partials(nested);
+                    self.sections.pop();
+                }
+                Partial::Conditional(branches, otherwise) => {
+                    for (condition, nested) in branches {
+                        self.check_expr(condition);
+                        self.check_partials(nested);
+                    }
+                    self.check_partials(otherwise);
+                }
+            }
+        }
+    }
+
+    fn check_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal(_) => {}
+            Expr::Tag(tag) => self.check_tag(tag),
+            Expr::Not(e) => self.check_expr(e),
+            Expr::And(left, right)
+            | Expr::Or(left, right)
+            | Expr::Compare(left, _, right) => {
+                self.check_expr(left);
+                self.check_expr(right);
+            }
+        }
+    }
+
+    fn check_tag(&mut self, tag: &Tag) {
+        self.require(&tag.id);
+        self.resolve(&tag.id);
+        let filters = match self.filters {
+            Some(filters) => filters,
+            None => return,
+        };
+        for filter in &tag.filters {
+            if !filters.contains(&filter.name) {
+                self.report_once(Lint::UnknownFilter {
+                    tag: tag.id.clone(),
+                    filter: filter.name.clone(),
+                });
+            }
+        }
+    }
+
+    /// The shape of the data `id` names, reporting it if there is no such
+    /// data. `None` if shapes are not checked or there is no such data.
+    fn resolve(&mut self, id: &str) -> Option<&'a Shape> {
+        let shapes = self.shapes.as_ref()?;
+        let innermost = *shapes.last()?;
+        if id == "." {
+            return Some(innermost);
+        }
+
+        let mut segments = id.split('.');
+        let head = segments.next()?;
+        let mut shape = match shapes.iter().rev().find_map(|s| s.get(head)) {
+            Some(shape) => shape,
+            None => {
+                let names = shapes
+                    .iter()
+                    .flat_map(|shape| shape.field_names())
+                    .collect::<Vec<_>>();
+                self.report_once(Lint::UnknownField(UnknownField {
+                    tag: id.to_string(),
+                    found: String::new(),
+                    segment: head.to_string(),
+                    help: suggest(head, &names)
+                        .map(|name| format!("did you mean `{}`?", name)),
+                }));
+                return None;
+            }
+        };
+
+        let mut found = head.to_string();
+        for segment in segments {
+            shape = match shape.get(segment) {
+                Some(field) => field,
+                None => {
+                    let help = match shape {
+                        Shape::List(_) => Some(format!(
+                            "`{}` is a list: use an index such as `{}.0.{}`, \
+                             or a section",
+                            found, found, segment
+                        )),
+                        _ => {
+                            suggest(segment, &shape.field_names()).map(|name| {
+                                format!("did you mean `{}.{}`?", found, name)
+                            })
+                        }
+                    };
+                    self.report_once(Lint::UnknownField(UnknownField {
+                        tag: id.to_string(),
+                        found,
+                        segment: segment.to_string(),
+                        help,
+                    }));
+                    return None;
+                }
+            };
+            found.push('.');
+            found.push_str(segment);
+        }
+
+        Some(shape)
+    }
+
+    fn report_once(&mut self, lint: Lint) {
+        if !self.report.lints.contains(&lint) {
+            self.report.lints.push(lint);
+        }
+    }
+
+    fn require(&mut self, id: &str) {
+        let tag = RequiredTag {
+            sections: self.sections.clone(),
+            id: id.to_string(),
+        };
+        if !self.report.tags.contains(&tag) {
+            self.report.tags.push(tag);
+        }
+    }
+}
+
+/// The name in `names` closest to the misspelt `name`, if any is close
+/// enough.
+fn suggest(name: &str, names: &[&'static str]) -> Option<&'static str> {
+    names
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= 2 && *distance < name.len())
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The number of characters to insert, delete or substitute to turn `a` into
+/// `b`, counting the swap of two adjacent characters once.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = (a[i - 1] != b[j - 1]) as usize;
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+/// Find the escape sequences in the text of the well-formed template `source`
+/// which are likely mistakes.
+fn suspicious_escapes(source: &str, origin: &str) -> Vec<Lint> {
+    let bytes = source.as_bytes();
+    let mut lints = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        // Backslashes are ordinary text after a set-delimiter tag.
+        if rest.starts_with(b"{{=") || rest.starts_with(b"{{-=") {
+            break;
+        }
+        if rest.starts_with(b"{{") {
+            i = tag_end(bytes, i);
+            continue;
+        }
+        if rest[0] != b'\\' {
+            i += 1;
+            continue;
+        }
+
+        let warning =
+            if rest.starts_with(b"\\{\\{") || rest.starts_with(b"\\}\\}") {
+                Some((
+                    format!(
+                        "`{}` is written as `{}{}`, which looks like a tag",
+                        String::from_utf8_lossy(&rest[..4]),
+                        rest[1] as char,
+                        rest[1] as char
+                    ),
+                    "remove the backslashes to write a tag",
+                ))
+            } else if rest.starts_with(b"\\\\{{") {
+                Some((
+                    "`\\\\` writes a backslash before the following tag"
+                        .to_string(),
+                    "write `\\{` to escape a brace instead",
+                ))
+            } else {
+                None
+            };
+
+        if let Some((message, help)) = warning {
+            let (line, column, source_line) = parser::locate(source, i);
+            lints.push(Lint::SuspiciousEscape {
+                origin: Some(origin.to_string()),
+                line,
+                column,
+                source_line,
+                message,
+                help,
+            });
+        }
+        // Every backslash in text starts an escape sequence of two characters.
+        i += 2;
+    }
+
+    lints
+}
+
+/// The position after the tag starting at `start`, skipping over the quoted
+/// strings within it.
+fn tag_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 2;
+    let mut quoted = false;
+    let comment = bytes.get(i) == Some(&b'!');
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if quoted => i += 1,
+            b'"' if !comment => quoted = !quoted,
+            b'}' if !quoted && bytes[i..].starts_with(b"}}") => return i + 2,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::patient::Patient;
+    use crate::data::schema::Schema;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+
+    fn lint_source(raw: &str, includes: &[(&str, &str)]) -> LintReport {
+        let includes = includes
+            .iter()
+            .map(|(name, raw)| (name.to_string(), raw.to_string()))
+            .collect::<HashMap<_, _>>();
+        lint(
+            raw,
+            "letter.template",
+            &includes,
+            &FilterRegistry::default(),
+            None,
+        )
+    }
+
+    fn tags(report: &LintReport) -> Vec<String> {
+        report.tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn test_required_tags() {
+        let report = lint_source(
+            "Dear {{ patient.name.0.family }},{{> signature }}\n\
+             {{#patients}}{{ family }}{{^given}}{{ family }}{{/given}}\
+             {{/patients}}\n\
+             {{#if age | default(0) >= 16 and not patient.deceased}}\
+             {{ patient.name.0.family }}{{/if}}",
+            &[("signature", "{{ sender }}")],
+        );
+
+        assert_eq!(
+            vec![
+                "patient.name.0.family",
+                "sender",
+                "patients",
+                "patients > family",
+                "patients > given",
+                "patients > given > family",
+                "age",
+                "patient.deceased",
+            ],
+            tags(&report)
+        );
+        assert_eq!(Vec::<Lint>::new(), report.lints);
+    }
+
+    #[test]
+    fn test_unbalanced_section() {
+        let report = lint_source("{{#patients}}{{ name }}{{/patient}}", &[]);
+
+        assert_eq!(1, report.errors());
+        assert!(report.tags.is_empty());
+        match &report.lints[0] {
+            Lint::IllFormed(error) => {
+                assert_eq!(Some("letter.template"), error.origin.as_deref());
+                assert_eq!(24, error.column);
+            }
+            lint => panic!("unexpected lint {:?}", lint),
+        }
+    }
+
+    #[test]
+    fn test_front_matter() {
+        let report = lint_source(
+            "+++\ntitle = \"Letter\"\n+++\nDear \\{\\{ name,\n",
+            &[],
+        );
+
+        match &report.lints[..] {
+            [Lint::SuspiciousEscape { line: 4, .. }] => {}
+            lints => panic!("unexpected lints {:?}", lints),
+        }
+
+        let report =
+            lint_source("+++\ntitle = \"Letter\"\n+++\n{{ name }", &[]);
+        match &report.lints[..] {
+            [Lint::IllFormed(error)] => assert_eq!(4, error.line),
+            lints => panic!("unexpected lints {:?}", lints),
+        }
+
+        let report = lint_source("+++\ntitle = \"Letter\"\n", &[]);
+        assert_eq!(
+            vec![Lint::Metadata(MetadataError::Unterminated)],
+            report.lints
+        );
+    }
+
+    #[test]
+    fn test_missing_include() {
+        let report = lint_source("{{> letterhead }}", &[]);
+
+        assert_eq!(1, report.errors());
+        assert!(matches!(
+            report.lints[0],
+            Lint::Include(IncludeError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unknown_filters() {
+        let report = lint_source(
+            "{{ name | shout }}{{ name | shout }}{{ name | upper }}\
+             {{#if age | years > 1}}{{/if}}",
+            &[],
+        );
+
+        assert_eq!(
+            vec![
+                Lint::UnknownFilter {
+                    tag: "name".to_string(),
+                    filter: "shout".to_string(),
+                },
+                Lint::UnknownFilter {
+                    tag: "age".to_string(),
+                    filter: "years".to_string(),
+                },
+            ],
+            report.lints
+        );
+        assert_eq!(0, report.errors());
+        assert_eq!(2, report.warnings());
+    }
+
+    #[test]
+    fn test_suspicious_escapes() {
+        let report = lint_source(
+            "Fine: \\{{{ a }}\\} \\\\ {{ b | default(\"\\\\{\\\\{\") }}\n\
+             Text: \\{\\{ name \\}\\}\n\
+             \t\\\\{{ name }}\n\
+             {{=<% %>=}}\\textbf{\\{\\{ <% name %> \\}\\}}",
+            &[],
+        );
+
+        let positions = report
+            .lints
+            .iter()
+            .map(|lint| match lint {
+                Lint::SuspiciousEscape { line, column, .. } => (*line, *column),
+                lint => panic!("unexpected lint {:?}", lint),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vec![(2, 7), (2, 17), (3, 2)], positions);
+    }
+
+    #[test]
+    fn test_display_suspicious_escape() {
+        let report = lint_source("Dear \\{\\{ name \\}\\},", &[]);
+
+        assert_eq!(
+            "warning: `\\{\\{` is written as `{{`, which looks like a tag\n \
+             --> letter.template:1:6\n  |\n1 | Dear \\{\\{ name \\}\\},\n  \
+             |      ^\n  = help: remove the backslashes to write a tag",
+            report.lints[0].to_string()
+        );
+    }
+
+    fn patient_shape() -> Shape {
+        Shape::Record(vec![
+            ("patient", Patient::shape()),
+            ("patients", Vec::<Patient>::shape()),
+        ])
+    }
+
+    fn check(raw: &str) -> Vec<String> {
+        let template = parser::parse_template(raw).unwrap();
+        check_schema(&template, &patient_shape())
+            .iter()
+            .map(|unknown| unknown.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_check_schema() {
+        assert_eq!(
+            Vec::<String>::new(),
+            check(
+                "{{ patient.name.0.family }} {{ patient.birthDate | age }}\
+                 {{#patients}}{{#name}}{{ family }}{{ given.0 }}{{/name}}\
+                 {{ birthDate }}{{ patient.birthDate }}{{/patients}}\
+                 {{#patient.name.0.given}}{{ . }}{{/patient.name.0.given}}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_check_schema_unknown_fields() {
+        assert_eq!(
+            vec![
+                "`patient.brithDate` is not in the data: `patient` has no \
+                 field `brithDate`\n  = help: did you mean \
+                 `patient.birthDate`?",
+                "`patient.name.family` is not in the data: `patient.name` has \
+                 no field `family`\n  = help: `patient.name` is a list: use an \
+                 index such as `patient.name.0.family`, or a section",
+                "`patient.birthDate.year` is not in the data: \
+                 `patient.birthDate` has no field `year`",
+                "`pateint` is not in the data\n  = help: did you mean \
+                 `patient`?",
+            ],
+            check(
+                "{{ patient.brithDate }}{{ patient.name.family }}\
+                 {{ patient.birthDate.year }}{{ patient.brithDate }}\
+                 {{#if pateint}}{{/if}}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_check_schema_in_sections() {
+        assert_eq!(
+            vec![
+                "`familly` is not in the data\n  = help: did you mean \
+                 `family`?",
+                "`unknown` is not in the data",
+                "`family` is not in the data",
+            ],
+            check(
+                "{{#patients}}{{#name}}{{ familly }}{{/name}}{{/patients}}\
+                 {{#unknown}}{{ anything }}{{/unknown}}\
+                 {{^patient.name}}{{ family }}{{/patient.name}}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(0, edit_distance("birthDate", "birthDate"));
+        assert_eq!(1, edit_distance("brithDate", "birthDate"));
+        assert_eq!(3, edit_distance("", "abc"));
+        assert_eq!(None, suggest("gender", &["name", "birthDate"]));
+    }
+}
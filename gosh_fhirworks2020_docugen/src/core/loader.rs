@@ -0,0 +1,643 @@
+use super::document::{DocumentTemplate, Identifier, Partial};
+use super::metadata::{MetadataError, TemplateFile};
+use super::parser::{self, Delimiters, TemplateParseError};
+use crate::data::locale::Locale;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File extension of template files.
+pub const TEMPLATE_EXTENSION: &str = "template";
+
+/// A `TemplateSource` provides the raw content of the templates that are
+/// referred to by name in `Include`s.
+pub trait TemplateSource {
+    fn read_template(&self, name: &str) -> Result<String, String>;
+
+    /// The `Delimiters` the templates start with.
+    fn delimiters(&self) -> Delimiters {
+        Delimiters::default()
+    }
+}
+
+/// The path of the variant of the template at `path` localized for `locale`,
+/// e.g. `letter.fr.template` for `letter.template`, if there is one. The
+/// variants for the tags of the locale are looked up in turn, e.g.
+/// `letter.fr-CA.template` then `letter.fr.template`.
+pub fn localized_path(path: &Path, locale: &Locale) -> Option<PathBuf> {
+    let extension = format!(".{}", TEMPLATE_EXTENSION);
+    let stem = path.file_name()?.to_str()?.strip_suffix(&extension)?;
+
+    locale
+        .tags()
+        .iter()
+        .map(|tag| {
+            path.with_file_name(format!("{}.{}{}", stem, tag, extension))
+        })
+        .find(|path| path.is_file())
+}
+
+/// A `DirectorySource` reads the template called `name` from
+/// `<directory>/<name>.template`, or from its variant localized for the
+/// locale of the source if there is one, see `localized_path`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DirectorySource {
+    directory: PathBuf,
+    delimiters: Delimiters,
+    locale: Option<Locale>,
+}
+
+impl DirectorySource {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        Self {
+            directory: directory.into(),
+            delimiters: Delimiters::default(),
+            locale: None,
+        }
+    }
+
+    /// Read the variants of the templates localized for `locale`.
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+
+    /// Start the templates read with tags delimited by `delimiters`.
+    pub fn with_delimiters(mut self, delimiters: Delimiters) -> Self {
+        self.delimiters = delimiters;
+        self
+    }
+}
+
+impl TemplateSource for DirectorySource {
+    fn read_template(&self, name: &str) -> Result<String, String> {
+        // Only templates within `directory` are read.
+        if name
+            .split('/')
+            .any(|segment| segment.is_empty() || segment == "..")
+        {
+            return Err(format!(
+                "`{}` is not the name of a template in {}",
+                name,
+                self.directory.display()
+            ));
+        }
+        let path = self
+            .directory
+            .join(format!("{}.{}", name, TEMPLATE_EXTENSION));
+        let path = match &self.locale {
+            Some(locale) => localized_path(&path, locale).unwrap_or(path),
+            None => path,
+        };
+        fs::read_to_string(&path)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn delimiters(&self) -> Delimiters {
+        self.delimiters.clone()
+    }
+}
+
+/// In-memory templates, keyed by name.
+impl TemplateSource for HashMap<String, String> {
+    fn read_template(&self, name: &str) -> Result<String, String> {
+        self.get(name)
+            .cloned()
+            .ok_or_else(|| format!("no template named `{}`", name))
+    }
+}
+
+/// Cause of error when trying to expand the `Include`s of a
+/// `DocumentTemplate`.
+#[derive(Debug, PartialEq)]
+pub enum IncludeError {
+    /// The included template could not be read.
+    NotFound { name: Identifier, cause: String },
+    /// The front matter of the included template could not be read.
+    Metadata {
+        name: Identifier,
+        cause: MetadataError,
+    },
+    /// The included template could not be parsed.
+    IllFormed(TemplateParseError),
+    /// Templates include each other in a cycle. The cycle is listed in include
+    /// order, starting and ending with the same template.
+    Cycle(Vec<Identifier>),
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncludeError::NotFound { name, cause } => {
+                write!(
+                    f,
+                    "failed to read included template `{}`: {}",
+                    name, cause
+                )
+            }
+            IncludeError::Metadata { name, cause } => {
+                write!(f, "in included template `{}`: {}", name, cause)
+            }
+            IncludeError::IllFormed(error) => write!(f, "{}", error),
+            IncludeError::Cycle(names) => {
+                write!(
+                    f,
+                    "templates include each other: {}",
+                    names.join(" -> ")
+                )
+            }
+        }
+    }
+}
+
+/// Replace every `Include` in `template`, including those within included
+/// templates, by the `Partial`s of the template it names.
+///
+/// Every `Layout` is replaced the same way, with the `Block`s of the layout
+/// and of the templates it includes overridden by those listed in the
+/// `Layout`. A layout may itself use a layout: overrides of the outermost
+/// template take precedence. Each remaining `Block` is replaced by its
+/// content.
+pub fn resolve_includes(
+    template: &DocumentTemplate,
+    source: &dyn TemplateSource,
+) -> Result<DocumentTemplate, IncludeError> {
+    resolve_includes_listed(template, source).map(|(template, _)| template)
+}
+
+/// Resolve the `Include`s of `template` as `resolve_includes` does, also
+/// listing the names of all the templates included or used as a layout,
+/// directly or not, in alphabetical order.
+pub fn resolve_includes_listed(
+    template: &DocumentTemplate,
+    source: &dyn TemplateSource,
+) -> Result<(DocumentTemplate, Vec<Identifier>), IncludeError> {
+    let mut resolver = Resolver {
+        source,
+        delimiters: source.delimiters(),
+        stack: Vec::new(),
+        expanded: HashMap::new(),
+        read: BTreeSet::new(),
+        overrides: HashMap::new(),
+    };
+    let partials = resolver.expand(&template.partials)?;
+
+    let included = resolver.read.into_iter().collect();
+    Ok((DocumentTemplate::with_partials(&partials), included))
+}
+
+struct Resolver<'a> {
+    source: &'a dyn TemplateSource,
+    delimiters: Delimiters,
+    /// Names of the templates currently being expanded, used to detect cycles.
+    stack: Vec<Identifier>,
+    /// Templates which have already been expanded without overrides, so that
+    /// a template included several times is only read and parsed once.
+    expanded: HashMap<Identifier, Vec<Partial>>,
+    /// Names of all the templates read.
+    read: BTreeSet<Identifier>,
+    /// Content of the `Block`s overridden by the `Layout`s being expanded.
+    overrides: HashMap<Identifier, Vec<Partial>>,
+}
+
+impl<'a> Resolver<'a> {
+    fn expand(
+        &mut self,
+        partials: &[Partial],
+    ) -> Result<Vec<Partial>, IncludeError> {
+        let mut expanded = Vec::with_capacity(partials.len());
+
+        for partial in partials {
+            match partial {
+                Partial::Include(name) => expanded.extend(self.include(name)?),
+                Partial::Layout(name, blocks) => {
+                    expanded.extend(self.layout(name, blocks)?)
+                }
+                Partial::Block(name, nested) => {
+                    expanded.extend(self.block(name, nested)?)
+                }
+                Partial::Section(id, nested) => expanded
+                    .push(Partial::Section(id.clone(), self.expand(nested)?)),
+                Partial::InvertedSection(id, nested) => expanded.push(
+                    Partial::InvertedSection(id.clone(), self.expand(nested)?),
+                ),
+                Partial::Conditional(branches, otherwise) => {
+                    let branches = branches
+                        .iter()
+                        .map(|(condition, nested)| {
+                            Ok((condition.clone(), self.expand(nested)?))
+                        })
+                        .collect::<Result<_, _>>()?;
+                    expanded.push(Partial::Conditional(
+                        branches,
+                        self.expand(otherwise)?,
+                    ))
+                }
+                _ => expanded.push(partial.clone()),
+            }
+        }
+
+        Ok(expanded)
+    }
+
+    fn include(&mut self, name: &str) -> Result<Vec<Partial>, IncludeError> {
+        // The expansion of a template depends on the blocks overridden.
+        let cached = self.overrides.is_empty();
+        if let Some(partials) = self.expanded.get(name).filter(|_| cached) {
+            return Ok(partials.clone());
+        }
+
+        let partials = self.expand_template(name)?;
+        if cached {
+            self.expanded.insert(name.to_string(), partials.clone());
+        }
+        Ok(partials)
+    }
+
+    fn layout(
+        &mut self,
+        name: &str,
+        blocks: &[(Identifier, Vec<Partial>)],
+    ) -> Result<Vec<Partial>, IncludeError> {
+        // Overrides of the enclosing templates take precedence.
+        let mut overrides = self.overrides.clone();
+        for (block, nested) in blocks {
+            overrides
+                .entry(block.clone())
+                .or_insert_with(|| nested.clone());
+        }
+
+        let enclosing = std::mem::replace(&mut self.overrides, overrides);
+        let partials = self.expand_template(name);
+        self.overrides = enclosing;
+        partials
+    }
+
+    fn block(
+        &mut self,
+        name: &str,
+        nested: &[Partial],
+    ) -> Result<Vec<Partial>, IncludeError> {
+        // Blocks within the content of the block are not overridden by it
+        // again, which would never end.
+        match self.overrides.remove(name) {
+            Some(overridden) => {
+                let partials = self.expand(&overridden);
+                self.overrides.insert(name.to_string(), overridden);
+                partials
+            }
+            None => self.expand(nested),
+        }
+    }
+
+    /// Read, parse and expand the template called `name`.
+    fn expand_template(
+        &mut self,
+        name: &str,
+    ) -> Result<Vec<Partial>, IncludeError> {
+        if let Some(start) = self.stack.iter().position(|n| n == name) {
+            let mut cycle = self.stack[start..].to_vec();
+            cycle.push(name.to_string());
+            return Err(IncludeError::Cycle(cycle));
+        }
+
+        let raw = self.source.read_template(name).map_err(|cause| {
+            IncludeError::NotFound {
+                name: name.to_string(),
+                cause,
+            }
+        })?;
+        self.read.insert(name.to_string());
+        // The metadata of included templates is not used.
+        let file = TemplateFile::parse(&raw).map_err(|cause| {
+            IncludeError::Metadata {
+                name: name.to_string(),
+                cause,
+            }
+        })?;
+        // Delimiters set by the including template do not apply to the
+        // included one.
+        let template =
+            parser::parse_template_with(file.template, &self.delimiters)
+                .map_err(|e| {
+                    IncludeError::IllFormed(
+                        e.with_origin(name)
+                            .offset_lines(file.front_matter_lines),
+                    )
+                })?;
+
+        self.stack.push(name.to_string());
+        let partials = self.expand(&template.partials);
+        self.stack.pop();
+        partials
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::document::Tag;
+    use crate::core::value::Value;
+    use pretty_assertions::assert_eq;
+
+    fn source(templates: &[(&str, &str)]) -> HashMap<String, String> {
+        templates
+            .iter()
+            .map(|(name, raw)| (name.to_string(), raw.to_string()))
+            .collect()
+    }
+
+    fn parse(raw: &str) -> DocumentTemplate {
+        parser::parse_template(raw).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_includes() {
+        let source = source(&[
+            ("letterhead", "GOSH\n"),
+            ("signature", "{{> sign_off }}{{ sender }}"),
+            ("sign_off", "Sincerely, "),
+        ]);
+        let template =
+            parse("{{> letterhead }}Dear {{ name }},{{> signature }}");
+
+        let expected = DocumentTemplate::with_partials(&[
+            Partial::StringLiteral("GOSH\n".to_string()),
+            Partial::StringLiteral("Dear ".to_string()),
+            Partial::Tag(Tag::new("name")),
+            Partial::StringLiteral(",".to_string()),
+            Partial::StringLiteral("Sincerely, ".to_string()),
+            Partial::Tag(Tag::new("sender")),
+        ]);
+
+        assert_eq!(Ok(expected), resolve_includes(&template, &source));
+    }
+
+    #[test]
+    fn test_resolve_includes_in_section() {
+        let source = source(&[("row", "{{ name }};")]);
+        let template = parse("{{#patients}}{{> row }}{{/patients}}");
+
+        let expected = DocumentTemplate::with_partials(&[Partial::Section(
+            "patients".to_string(),
+            vec![
+                Partial::Tag(Tag::new("name")),
+                Partial::StringLiteral(";".to_string()),
+            ],
+        )]);
+
+        assert_eq!(Ok(expected), resolve_includes(&template, &source));
+    }
+
+    #[test]
+    fn test_list_included_templates() {
+        let source = source(&[
+            ("signature", "{{> sign_off }}"),
+            ("sign_off", "Sincerely,"),
+            ("letterhead", "GOSH"),
+        ]);
+        let template =
+            parse("{{> signature }}{{> letterhead }}{{> signature }}");
+
+        let (_, included) =
+            resolve_includes_listed(&template, &source).unwrap();
+        assert_eq!(vec!["letterhead", "sign_off", "signature"], included);
+    }
+
+    #[test]
+    fn test_same_include_twice_is_not_a_cycle() {
+        let source = source(&[("rule", "---")]);
+        let template = parse("{{> rule }}x{{> rule }}");
+
+        assert!(resolve_includes(&template, &source).is_ok());
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let source =
+            source(&[("a", "{{> b }}"), ("b", "{{#x}}{{> a }}{{/x}}")]);
+        let template = parse("{{> a }}");
+
+        assert_eq!(
+            Err(IncludeError::Cycle(vec![
+                "a".to_string(),
+                "b".to_string(),
+                "a".to_string()
+            ])),
+            resolve_includes(&template, &source)
+        );
+    }
+
+    #[test]
+    fn test_layout() {
+        let source = source(&[
+            ("letterhead", "GOSH\n"),
+            (
+                "letter",
+                "{{> letterhead }}{{$salutation}}Dear {{ name }},{{/salutation}}\
+                 \n{{$body}}{{/body}}",
+            ),
+        ]);
+        let template = parse("{{<letter}}{{$body}}Hello{{/body}}{{/letter}}!");
+
+        let expected = DocumentTemplate::with_partials(&[
+            Partial::StringLiteral("GOSH\n".to_string()),
+            Partial::StringLiteral("Dear ".to_string()),
+            Partial::Tag(Tag::new("name")),
+            Partial::StringLiteral(",".to_string()),
+            Partial::StringLiteral("\n".to_string()),
+            Partial::StringLiteral("Hello".to_string()),
+            Partial::StringLiteral("!".to_string()),
+        ]);
+        let (resolved, included) =
+            resolve_includes_listed(&template, &source).unwrap();
+        assert_eq!(expected, resolved);
+        assert_eq!(vec!["letter", "letterhead"], included);
+    }
+
+    #[test]
+    fn test_nested_layouts() {
+        let source = source(&[
+            ("base", "[{{$title}}Base{{/title}}|{{> footer }}]"),
+            ("footer", "{{$footer}}Base footer{{/footer}}"),
+            (
+                "letter",
+                "{{<base}}{{$title}}Letter{{/title}}\
+                 {{$footer}}Letter footer{{/footer}}{{/base}}",
+            ),
+        ]);
+        let expand = |raw: &str| {
+            let resolved = resolve_includes(&parse(raw), &source).unwrap();
+            resolved
+                .saturate(&Value::default())
+                .unwrap()
+                .document()
+                .to_string()
+        };
+
+        assert_eq!("[Letter|Letter footer]", expand("{{<letter}}{{/letter}}"));
+        // Overrides of the outermost template take precedence.
+        assert_eq!(
+            "[Child|Letter footer]",
+            expand("{{<letter}}{{$title}}Child{{/title}}{{/letter}}")
+        );
+        // A block is filled with its default content without a layout.
+        assert_eq!("Default", expand("{{$title}}Default{{/title}}"));
+        // A block overridden with itself is not expanded again.
+        assert_eq!(
+            "[Base|Base footer]",
+            expand("{{<base}}{{$title}}{{$title}}Base{{/title}}{{/title}}{{/base}}")
+        );
+    }
+
+    #[test]
+    fn test_layout_cycle() {
+        let source = source(&[("a", "{{<b}}{{/b}}"), ("b", "{{> a }}")]);
+        let template = parse("{{<a}}{{/a}}");
+
+        assert_eq!(
+            Err(IncludeError::Cycle(vec![
+                "a".to_string(),
+                "b".to_string(),
+                "a".to_string()
+            ])),
+            resolve_includes(&template, &source)
+        );
+    }
+
+    #[test]
+    fn test_missing_include() {
+        let source = source(&[]);
+        let template = parse("{{> letterhead }}");
+
+        assert!(matches!(
+            resolve_includes(&template, &source),
+            Err(IncludeError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_include_with_front_matter() {
+        let source = source(&[
+            ("letterhead", "+++\ntitle = \"Letterhead\"\n+++\nGOSH\n"),
+            ("signature", "+++\ntitle = \"Signature\"\n+++\n{{ sender }"),
+        ]);
+
+        let resolved =
+            resolve_includes(&parse("{{> letterhead }}"), &source).unwrap();
+        assert_eq!(
+            DocumentTemplate::with_partials(&[Partial::StringLiteral(
+                "GOSH\n".to_string()
+            )]),
+            resolved
+        );
+
+        match resolve_includes(&parse("{{> signature }}"), &source) {
+            Err(IncludeError::IllFormed(error)) => assert_eq!(4, error.line),
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_ill_formed_include() {
+        let source = source(&[("signature", "Sincerely,\n{{ sender }")]);
+        let template = parse("{{> signature }}");
+
+        match resolve_includes(&template, &source) {
+            Err(IncludeError::IllFormed(error)) => {
+                assert_eq!(Some("signature".to_string()), error.origin);
+                assert_eq!((2, 11), (error.line, error.column));
+            }
+            result => {
+                panic!("expected an ill-formed include, got {:?}", result)
+            }
+        }
+    }
+
+    #[test]
+    fn test_include_delimiters() {
+        let directory = std::env::temp_dir()
+            .join(format!("docugen-loader-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(
+            directory.join("signature.template"),
+            "\\textit{<% sender %>}",
+        )
+        .unwrap();
+        let source = DirectorySource::new(&directory)
+            .with_delimiters(Delimiters::new("<%", "%>").unwrap());
+        // Delimiters set by a template do not apply to its includes.
+        let template = parse("{{=[ ]=}}[ name ],[> signature ]");
+
+        let expected = DocumentTemplate::with_partials(&[
+            Partial::Tag(Tag::new("name")),
+            Partial::StringLiteral(",".to_string()),
+            Partial::StringLiteral("\\textit{".to_string()),
+            Partial::Tag(Tag::new("sender")),
+            Partial::StringLiteral("}".to_string()),
+        ]);
+        let resolved = resolve_includes(&template, &source);
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(expected, resolved.unwrap());
+    }
+
+    #[test]
+    fn test_read_outside_of_directory() {
+        let directory = std::env::temp_dir()
+            .join(format!("docugen-outside-{}", std::process::id()));
+        fs::create_dir_all(directory.join("templates")).unwrap();
+        fs::write(directory.join("secret.template"), "Secret").unwrap();
+        let source = DirectorySource::new(directory.join("templates"));
+        let absolute = directory.join("secret");
+
+        let read = [
+            source.read_template(&absolute.to_string_lossy()),
+            source.read_template("../secret"),
+            source.read_template("common//secret"),
+        ];
+        fs::remove_dir_all(&directory).unwrap();
+
+        for read in &read {
+            assert!(read.as_ref().unwrap_err().contains("not the name"));
+        }
+    }
+
+    #[test]
+    fn test_localized_includes() {
+        let directory = std::env::temp_dir()
+            .join(format!("docugen-localized-{}", std::process::id()));
+        fs::create_dir_all(directory.join("common")).unwrap();
+        fs::write(directory.join("common/greeting.template"), "Dear").unwrap();
+        fs::write(directory.join("common/greeting.fr.template"), "Cher")
+            .unwrap();
+        fs::write(directory.join("common/greeting.fr-CA.template"), "Allô")
+            .unwrap();
+        let read = |locale: Option<&str>| {
+            let source = DirectorySource::new(&directory);
+            let source = match locale {
+                Some(locale) => source.with_locale(locale.parse().unwrap()),
+                None => source,
+            };
+            source.read_template("common/greeting").unwrap()
+        };
+
+        let read = [
+            read(None),
+            read(Some("fr-FR")),
+            read(Some("fr-CA")),
+            read(Some("de")),
+        ];
+        let localized = localized_path(
+            &directory.join("common/greeting.template"),
+            &"fr-BE".parse().unwrap(),
+        );
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(["Dear", "Cher", "Allô", "Dear"], read);
+        assert_eq!(
+            Some(directory.join("common/greeting.fr.template")),
+            localized
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use super::document::Identifier;
+use crate::data::locale::Locale;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// File extension of message catalogues.
+pub const CATALOGUE_EXTENSION: &str = "toml";
+
+/// A `MessageCatalogue` holds the translations, in one `Locale`, of the
+/// strings written by `{{ t "key" }}` tags. Catalogues are TOML files of
+/// strings, in which the keys of nested tables are joined with `.`:
+///
+/// ```toml
+/// greeting = "Bonjour"
+///
+/// [birthdate]
+/// intro = "Voici la date de naissance enregistrée :"
+/// ```
+///
+/// defines the messages `greeting` and `birthdate.intro`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MessageCatalogue {
+    messages: HashMap<Identifier, String>,
+}
+
+impl MessageCatalogue {
+    pub fn new() -> Self {
+        MessageCatalogue::default()
+    }
+
+    /// Parse the catalogue `raw`, written in TOML.
+    pub fn from_toml(raw: &str) -> Result<Self, String> {
+        let table = raw.parse::<toml::Value>().map_err(|e| e.to_string())?;
+        let mut catalogue = Self::new();
+        catalogue.add_table("", &table)?;
+        Ok(catalogue)
+    }
+
+    /// Read the catalogue of `locale` from `directory`. The catalogue of each
+    /// of the tags of the locale, e.g. `fr.toml` then `fr-CA.toml`, is read if
+    /// it exists, with the messages of the more specific one taking
+    /// precedence. A locale without catalogues has no messages.
+    pub fn read_from_directory(
+        directory: &Path,
+        locale: &Locale,
+    ) -> Result<Self, String> {
+        let mut catalogue = Self::new();
+        for tag in locale.tags().iter().rev() {
+            let path =
+                directory.join(format!("{}.{}", tag, CATALOGUE_EXTENSION));
+            if !path.exists() {
+                continue;
+            }
+            let raw = fs::read_to_string(&path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            let read = Self::from_toml(&raw)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            catalogue.messages.extend(read.messages);
+        }
+        Ok(catalogue)
+    }
+
+    /// Add the message `key`, replacing any message of the same key.
+    pub fn insert(&mut self, key: &str, message: &str) {
+        self.messages.insert(key.to_string(), message.to_string());
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(String::as_str)
+    }
+
+    fn add_table(
+        &mut self,
+        prefix: &str,
+        value: &toml::Value,
+    ) -> Result<(), String> {
+        match value {
+            toml::Value::String(message) => {
+                self.insert(prefix, message);
+                Ok(())
+            }
+            toml::Value::Table(table) => {
+                for (key, value) in table {
+                    let key = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", prefix, key)
+                    };
+                    self.add_table(&key, value)?;
+                }
+                Ok(())
+            }
+            _ => Err(format!("message `{}` must be a string", prefix)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_from_toml() {
+        let catalogue = MessageCatalogue::from_toml(
+            "greeting = \"Bonjour\"\n\
+             [birthdate]\n\
+             intro = \"Voici votre date de naissance :\"\n\
+             [birthdate.minor]\n\
+             notice = \"Montrez cette lettre à vos parents.\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(Some("Bonjour"), catalogue.get("greeting"));
+        assert_eq!(
+            Some("Voici votre date de naissance :"),
+            catalogue.get("birthdate.intro")
+        );
+        assert_eq!(
+            Some("Montrez cette lettre à vos parents."),
+            catalogue.get("birthdate.minor.notice")
+        );
+        assert_eq!(None, catalogue.get("birthdate"));
+    }
+
+    #[test]
+    fn test_ill_formed_catalogue() {
+        assert_eq!(
+            Err("message `count` must be a string".to_string()),
+            MessageCatalogue::from_toml("count = 3")
+        );
+        assert!(MessageCatalogue::from_toml("greeting = ").is_err());
+    }
+
+    #[test]
+    fn test_read_from_directory() {
+        let directory = std::env::temp_dir()
+            .join(format!("docugen-messages-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(
+            directory.join("fr.toml"),
+            "greeting = \"Bonjour\"\nfarewell = \"Au revoir\"",
+        )
+        .unwrap();
+        fs::write(directory.join("fr-CA.toml"), "greeting = \"Allô\"").unwrap();
+
+        let read = |locale: &str| {
+            MessageCatalogue::read_from_directory(
+                &directory,
+                &locale.parse().unwrap(),
+            )
+            .unwrap()
+        };
+        let canadian = read("fr-CA");
+        let french = read("fr-FR");
+        let german = read("de");
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(Some("Allô"), canadian.get("greeting"));
+        assert_eq!(Some("Au revoir"), canadian.get("farewell"));
+        assert_eq!(Some("Bonjour"), french.get("greeting"));
+        assert_eq!(MessageCatalogue::new(), german);
+    }
+}
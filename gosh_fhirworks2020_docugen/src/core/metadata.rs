@@ -0,0 +1,220 @@
+use super::escape::Escape;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Line opening and closing the front matter of a template file.
+pub const FRONT_MATTER_DELIMITER: &str = "+++";
+
+/// Metadata of a template, written as TOML in the front matter of its file,
+/// between two `+++` lines before the template itself:
+///
+/// ```text
+/// +++
+/// title = "Birthdate confirmation"
+/// resource_type = "Patient"
+/// related = ["Observation"]
+/// format = "text"
+/// filename = "birthdate-{{ patient.id }}.txt"
+/// author = "GOSH DRIVE"
+/// version = "1.0.0"
+/// +++
+/// Dear {{ patient.name.0.family }},
+/// ```
+///
+/// Every field is optional, and a template without front matter has no
+/// metadata.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateMetadata {
+    /// What the documents filled from the template are, e.g. "Birthdate
+    /// confirmation".
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Type of the FHIR resources filling the template, which names the
+    /// endpoint they are fetched from: `"Patient"`, for a document per
+    /// patient as `patient`, or `"Appointment"`, for a document per upcoming
+    /// appointment as `appointment` along with its `patient`.
+    #[serde(default)]
+    pub resource_type: Option<String>,
+    /// Types of the FHIR resources about each patient filling the template
+    /// which are also fetched for it, listed in templates under a name of
+    /// their own: `Observation` as `observations`, `Condition` as
+    /// `conditions`, `AllergyIntolerance` as `allergies`,
+    /// `MedicationRequest` as `medications` and `Encounter` as `encounters`.
+    /// Combined documents list them within each patient or appointment, e.g.
+    /// as `patients.0.observations`.
+    #[serde(default)]
+    pub related: Vec<String>,
+    /// Format of the filled documents, which sets how data is escaped:
+    /// `"text"`, `"html"`, `"latex"` or `"csv"`.
+    #[serde(default)]
+    pub format: Option<Escape>,
+    /// Name of the file each document is written to, itself a template filled
+    /// with the data of the document, e.g. `"letter-{{ patient.id }}.txt"`.
+    #[serde(default)]
+    pub filename: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+/// A template file, split into the metadata of its front matter and the
+/// template following it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TemplateFile<'t> {
+    pub metadata: TemplateMetadata,
+    /// The source of the template, after the front matter.
+    pub template: &'t str,
+    /// Number of lines of the front matter, including its `+++` lines, so
+    /// that errors in the template can be located in the file.
+    pub front_matter_lines: u32,
+}
+
+impl<'t> TemplateFile<'t> {
+    /// Split the content of a template file into its metadata and template.
+    /// A file which does not start with a `+++` line has no front matter.
+    pub fn parse(raw: &'t str) -> Result<Self, MetadataError> {
+        let mut lines = raw.split_inclusive('\n');
+        let start = match lines.next() {
+            Some(line) if is_delimiter_line(line) && line.ends_with('\n') => {
+                line.len()
+            }
+            _ => {
+                return Ok(Self {
+                    metadata: TemplateMetadata::default(),
+                    template: raw,
+                    front_matter_lines: 0,
+                })
+            }
+        };
+
+        let mut end = start;
+        for (front_matter_lines, line) in (2..).zip(lines) {
+            if is_delimiter_line(line) {
+                let metadata = toml::from_str(&raw[start..end])
+                    .map_err(|e| MetadataError::IllFormed(e.to_string()))?;
+                return Ok(Self {
+                    metadata,
+                    template: &raw[end + line.len()..],
+                    front_matter_lines,
+                });
+            }
+            end += line.len();
+        }
+
+        Err(MetadataError::Unterminated)
+    }
+}
+
+fn is_delimiter_line(line: &str) -> bool {
+    line.trim_end_matches(['\n', '\r']) == FRONT_MATTER_DELIMITER
+}
+
+/// Cause of error when trying to read the front matter of a template file.
+#[derive(Debug, PartialEq, Clone)]
+pub enum MetadataError {
+    /// The front matter is never closed by a `+++` line.
+    Unterminated,
+    /// The front matter is not valid TOML, or has unknown or ill-typed
+    /// fields.
+    IllFormed(String),
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataError::Unterminated => write!(
+                f,
+                "front matter is not closed by a `{}` line",
+                FRONT_MATTER_DELIMITER
+            ),
+            MetadataError::IllFormed(cause) => {
+                write!(f, "ill-formed front matter: {}", cause)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_front_matter() {
+        let raw = "+++\n\
+                   title = \"Birthdate confirmation\"\n\
+                   resource_type = \"Patient\"\n\
+                   related = [\"Observation\"]\n\
+                   format = \"html\"\n\
+                   filename = \"letter-{{ patient.id }}.html\"\n\
+                   author = \"GOSH DRIVE\"\n\
+                   version = \"1.0.0\"\n\
+                   +++\n\
+                   Dear {{ name }},\n";
+
+        let expected = TemplateFile {
+            metadata: TemplateMetadata {
+                title: Some("Birthdate confirmation".to_string()),
+                resource_type: Some("Patient".to_string()),
+                related: vec!["Observation".to_string()],
+                format: Some(Escape::Html),
+                filename: Some("letter-{{ patient.id }}.html".to_string()),
+                author: Some("GOSH DRIVE".to_string()),
+                version: Some("1.0.0".to_string()),
+            },
+            template: "Dear {{ name }},\n",
+            front_matter_lines: 9,
+        };
+        assert_eq!(expected, TemplateFile::parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_without_front_matter() {
+        for raw in &["Dear {{ name }},\n", "+++ not front matter\n", "+++", ""]
+        {
+            let file = TemplateFile::parse(raw).unwrap();
+            assert_eq!(TemplateMetadata::default(), file.metadata);
+            assert_eq!(*raw, file.template);
+            assert_eq!(0, file.front_matter_lines);
+        }
+    }
+
+    #[test]
+    fn test_crlf_front_matter() {
+        let raw = "+++\r\ntitle = \"Letter\"\r\n+++\r\nDear {{ name }},\r\n";
+
+        let file = TemplateFile::parse(raw).unwrap();
+        assert_eq!(Some("Letter".to_string()), file.metadata.title);
+        assert_eq!("Dear {{ name }},\r\n", file.template);
+        assert_eq!(3, file.front_matter_lines);
+    }
+
+    #[test]
+    fn test_empty_front_matter() {
+        let file = TemplateFile::parse("+++\n+++").unwrap();
+        assert_eq!(TemplateMetadata::default(), file.metadata);
+        assert_eq!("", file.template);
+        assert_eq!(2, file.front_matter_lines);
+    }
+
+    #[test]
+    fn test_unterminated_front_matter() {
+        assert_eq!(
+            Err(MetadataError::Unterminated),
+            TemplateFile::parse("+++\ntitle = \"Letter\"\nDear {{ name }},\n")
+        );
+    }
+
+    #[test]
+    fn test_ill_formed_front_matter() {
+        for raw in &["+++\ntitle = Letter\n+++\n", "+++\nauthr = \"Jo\"\n+++\n"]
+        {
+            assert!(matches!(
+                TemplateFile::parse(raw),
+                Err(MetadataError::IllFormed(_))
+            ));
+        }
+    }
+}
@@ -1,2 +1,12 @@
+pub mod cache;
+pub mod compiled;
 pub mod document;
+pub mod escape;
+pub mod expr;
+pub mod filter;
+pub mod lint;
+pub mod loader;
+pub mod messages;
+pub mod metadata;
 pub mod parser;
+pub mod value;
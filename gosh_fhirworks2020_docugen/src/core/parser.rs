@@ -1,7 +1,80 @@
 use pom::char_class::*;
 use pom::parser::*;
 
-use super::document::{DocumentTemplate, Partial};
+use super::document::{DocumentTemplate, Partial, Tag};
+use super::expr::{Comparator, Expr};
+use super::filter::Filter;
+use super::value::Value;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// The `Delimiters` opening and closing tags, `{{` and `}}` by default. A
+/// template can change them for the rest of the template with a
+/// set-delimiter tag, e.g. `{{=<% %>=}}`, see `set_delimiters`.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(try_from = "(String, String)", into = "(String, String)")]
+pub struct Delimiters {
+    open: String,
+    close: String,
+}
+
+impl Delimiters {
+    /// Delimiters may not be empty, nor contain whitespace or `=`.
+    pub fn new(open: &str, close: &str) -> Result<Self, String> {
+        for delimiter in &[open, close] {
+            if delimiter.is_empty()
+                || delimiter.contains(|c: char| c.is_whitespace() || c == '=')
+            {
+                return Err(format!("invalid tag delimiter `{}`", delimiter));
+            }
+        }
+
+        Ok(Self {
+            open: open.to_string(),
+            close: close.to_string(),
+        })
+    }
+
+    pub fn open(&self) -> &str {
+        &self.open
+    }
+
+    pub fn close(&self) -> &str {
+        &self.close
+    }
+
+    fn is_default(&self) -> bool {
+        self.open == "{{" && self.close == "}}"
+    }
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self {
+            open: "{{".to_string(),
+            close: "}}".to_string(),
+        }
+    }
+}
+
+impl TryFrom<(String, String)> for Delimiters {
+    type Error = String;
+
+    fn try_from((open, close): (String, String)) -> Result<Self, String> {
+        Self::new(&open, &close)
+    }
+}
+
+impl From<Delimiters> for (String, String) {
+    fn from(delimiters: Delimiters) -> Self {
+        (delimiters.open, delimiters.close)
+    }
+}
 
 /// A `StringLiteral` parser combinator is responsible for parsing the following
 /// fragment:
@@ -21,36 +94,502 @@ use super::document::{DocumentTemplate, Partial};
 ///
 /// <UnescapedCharacter> ::= [^\\{}]
 /// ```
+///
+/// With other `Delimiters` than `{{` and `}}`, text runs up to the next
+/// opening delimiter, and braces and backslashes are ordinary characters.
+///
+/// Whitespace at the end of the text is dropped when it is followed by a tag
+/// opened with `{{-`. The indentation of a block tag standing alone on its
+/// line is dropped as well, see `standalone`.
 pub fn string_literal<'a>() -> Parser<'a, u8, Partial> {
     let special_char = sym(b'\\').map(|_| b'\\')
         | sym(b'{').map(|_| b'{')
         | sym(b'}').map(|_| b'}');
     let escape_sequence = sym(b'\\') * special_char;
     let string = (none_of(b"\\}{") | escape_sequence).repeat(1..);
-    string
-        .convert(String::from_utf8)
-        .map(Partial::StringLiteral)
+    let string = string.convert(String::from_utf8);
+
+    Parser::new(move |input: &'a [u8], start: usize| {
+        let delimiters = delimiters_at(start);
+        let (mut text, end) = if delimiters.is_default() {
+            string.parse_at(input, start)?
+        } else {
+            let open = delimiters.open.as_bytes();
+            let end = input[start..]
+                .windows(open.len())
+                .position(|w| w == open)
+                .map_or(input.len(), |i| start + i);
+            if end == start {
+                return Err(pom::Error::Mismatch {
+                    message: "expected text".to_string(),
+                    position: start,
+                });
+            }
+            // The text ends before a delimiter or at the end of the input,
+            // so it is valid UTF-8.
+            let text = String::from_utf8_lossy(&input[start..end]);
+            (text.into_owned(), end)
+        };
+
+        let trimmed = input[end..].starts_with(delimiters.open.as_bytes())
+            && input.get(end + delimiters.open.len()) == Some(&b'-');
+        if trimmed {
+            text.truncate(text.trim_end().len());
+        } else if block_tag_end(input, end)
+            .and_then(|tag_end| standalone_end(input, end, tag_end))
+            .is_some()
+        {
+            text.truncate(text.trim_end_matches(&[' ', '\t'][..]).len());
+        }
+        Ok((Partial::StringLiteral(text), end))
+    })
 }
 
-/// The `tag` parser combinator is responsible for parsing a `Tag(identifier)`
-/// which is delimited between `{{ tag_id }}`.
+/// The `tag` parser combinator is responsible for parsing a `Tag(tag)` which
+/// is delimited between `{{ tag_id }}`.
+///
+/// The identifier `.` is the implicit iterator, referring to the current item
+/// while iterating over a list in a `Section`. Otherwise an identifier is a
+/// dotted path of keys and list indices, e.g. `patient.name.0.family`.
+///
+/// The identifier may be followed by a default value, used when there is no
+/// data for the identifier, e.g. `{{ family ? "Unknown" }}`, and by a pipeline
+/// of filters, each optionally taking literal arguments, e.g.
+/// `{{ birth_date | format("%d %B %Y") }}`.
+///
+/// `else` is reserved for the branches of a `Conditional` and is not a valid
+/// identifier.
+///
+/// A raw tag, `{{{ tag_id }}}` or `{{& tag_id }}`, is filled with its data
+/// without escaping it, see `RenderOptions::escape`. The `{{{ tag_id }}}` form
+/// is only available with the default `Delimiters`.
 ///
 /// ```enbf
-/// <Tag> ::= "{{" <TagId> "}}"
-/// <TagId> ::= [a-zA-Z][_a-zA-Z0-9]*
+/// <Tag> ::= <LeftDelimiter> "&"? <TagBody> <RightDelimiter>
+///       |   "{{{" <TagBody> "}}}"
+/// <TagBody> ::= <TagId> ("?" <Literal>)? <Filter>*
+/// <TagId> ::= "." | <Key> ("." <PathSegment>)*
+/// <PathSegment> ::= <Key> | [0-9]+
+/// <Key> ::= [a-zA-Z_][_a-zA-Z0-9]*
+///
+/// <Filter> ::= "|" <Key> ("(" <Literal> ("," <Literal>)* ")")?
+/// <Literal> ::= <QuotedString> | <Number>
+/// <QuotedString> ::= '"' ([^"\\] | '\\"' | '\\\\')* '"'
+/// <Number> ::= "-"? [0-9]+ ("." [0-9]+)?
 /// ```
 pub fn tag<'a>() -> Parser<'a, u8, Partial> {
-    let tag_left_delimiter = seq(b"{{").discard();
-    let tag_right_delimiter = seq(b"}}").discard();
+    let else_tag = tag_left_delimiter() * skip_whitespace() * keyword(b"else");
+    let tag = !else_tag * tag_left_delimiter() * sym(b'&').opt() + tag_body()
+        - labelled(tag_right_delimiter(), "`}}`");
+    let default_delimiters = Parser::new(|_, start: usize| {
+        if delimiters_at(start).is_default() {
+            Ok(((), start))
+        } else {
+            Err(pom::Error::Mismatch {
+                message: "`{{{` needs the default delimiters".to_string(),
+                position: start,
+            })
+        }
+    });
+    let triple = default_delimiters * seq(b"{{{") * tag_body()
+        - labelled(seq(b"}}}"), "`}}}`");
+
+    triple.map(|tag| Partial::Tag(Tag { raw: true, ..tag }))
+        | tag.map(|(raw, tag)| {
+            Partial::Tag(Tag {
+                raw: raw.is_some(),
+                ..tag
+            })
+        })
+}
+
+/// The identifier, default value and filters of a tag, between its
+/// delimiters.
+fn tag_body<'a>() -> Parser<'a, u8, Tag> {
+    let body = skip_whitespace() * labelled(tag_id(), "a tag identifier")
+        - skip_whitespace()
+        + labelled(tag_default(), "`?`").opt()
+        + labelled(filter(), "`|`").repeat(0..);
+
+    body.map(|((id, default), filters)| Tag {
+        default,
+        ..Tag::with_filters(&id, &filters)
+    })
+}
+
+fn tag_default<'a>() -> Parser<'a, u8, Value> {
+    sym(b'?') * skip_whitespace() * labelled(literal(), "a string or number")
+        - skip_whitespace()
+}
+
+fn filter<'a>() -> Parser<'a, u8, Filter> {
+    let separator =
+        skip_whitespace() * labelled(sym(b','), "`,`") - skip_whitespace();
+    let argument = labelled(literal(), "a string or number");
+    let args = sym(b'(') * skip_whitespace() * list(argument, separator)
+        - skip_whitespace()
+        - labelled(sym(b')'), "`)`");
+    let filter =
+        sym(b'|') * skip_whitespace() * labelled(key(), "a filter name")
+            - skip_whitespace()
+            + (args - skip_whitespace()).opt();
+
+    filter.map(|(name, args)| Filter {
+        name,
+        args: args.unwrap_or_default(),
+    })
+}
+
+/// A literal argument, either a double-quoted string or a number.
+fn literal<'a>() -> Parser<'a, u8, Value> {
+    quoted_string().map(Value::String) | number().map(Value::Number)
+}
+
+fn quoted_string<'a>() -> Parser<'a, u8, String> {
+    let escape_sequence = sym(b'\\') * (sym(b'\\') | sym(b'"'));
+    let string = sym(b'"') * (none_of(b"\\\"") | escape_sequence).repeat(0..)
+        - labelled(sym(b'"'), "`\"`");
+    string.convert(String::from_utf8)
+}
+
+fn number<'a>() -> Parser<'a, u8, f64> {
+    let integer = is_a(digit).repeat(1..);
+    let fraction = sym(b'.') * is_a(digit).repeat(1..);
+    let number = sym(b'-').opt() + integer + fraction.opt();
+    number
+        .collect()
+        .convert(std::str::from_utf8)
+        .convert(f64::from_str)
+}
+
+/// The `section` parser combinator is responsible for parsing a
+/// `Section(identifier, partials)` or an `InvertedSection(identifier,
+/// partials)`. The closing tag must name the same identifier as the opening
+/// tag. When the identifier refers to a list, a `Section` is repeated once per
+/// item of the list.
+///
+/// ```ebnf
+/// <Section> ::= <LeftDelimiter> ("#" | "^") <TagId> <RightDelimiter>
+///               <Partial>*
+///               <LeftDelimiter> "/" <TagId> <RightDelimiter>
+/// ```
+pub fn section<'a>() -> Parser<'a, u8, Partial> {
+    let opening_tag = tag_left_delimiter() * (sym(b'#') | sym(b'^'))
+        + (skip_whitespace() * labelled(tag_id(), "a tag identifier")
+            - skip_whitespace())
+        - labelled(tag_right_delimiter(), "`}}`");
+
+    standalone(opening_tag)
+        >> |(kind, id): (u8, String)| {
+            let nested = call(partials) - closing_tag(id.clone(), tag_id);
+            nested.map(move |partials| match kind {
+                b'#' => Partial::Section(id.clone(), partials),
+                _ => Partial::InvertedSection(id.clone(), partials),
+            })
+        }
+}
+
+/// The `include` parser combinator is responsible for parsing an
+/// `Include(name)`, which is replaced by the template called `name` when the
+/// template is loaded. Names may contain `/` to refer to templates in
+/// sub-directories.
+///
+/// ```ebnf
+/// <Include> ::= <LeftDelimiter> ">" <TemplateName> <RightDelimiter>
+/// <TemplateName> ::= <Segment> ("/" <Segment>)*
+/// <Segment> ::= <Word> ("-" <Word>)*
+/// <Word> ::= [a-zA-Z0-9_]+
+/// ```
+pub fn include<'a>() -> Parser<'a, u8, Partial> {
+    let include = tag_left_delimiter()
+        * sym(b'>')
+        * skip_whitespace()
+        * labelled(template_name(), "a template name")
+        - skip_whitespace()
+        - labelled(tag_right_delimiter(), "`}}`");
 
-    let tag = tag_left_delimiter * skip_whitespace() * tag_id()
+    standalone(include.map(Partial::Include))
+}
+
+/// The `message` parser combinator is responsible for parsing a
+/// `Message(key)`, which is replaced by the translation of the message `key`
+/// in the locale of the document.
+///
+/// ```ebnf
+/// <Message> ::= <LeftDelimiter> "t" <QuotedString> <RightDelimiter>
+/// ```
+pub fn message<'a>() -> Parser<'a, u8, Partial> {
+    let message = tag_left_delimiter()
+        * skip_whitespace()
+        * keyword(b"t")
+        * skip_whitespace()
+        * quoted_string()
+        - skip_whitespace()
+        - labelled(tag_right_delimiter(), "`}}`");
+
+    message.map(Partial::Message)
+}
+
+/// Template names are relative paths within the templates directory, e.g.
+/// `common/sign-off`: they neither start with `/` nor have empty segments.
+fn template_name<'a>() -> Parser<'a, u8, String> {
+    let word = || (is_a(alphanum) | sym(b'_')).repeat(1..);
+    let segment = || word() + (sym(b'-') + word()).repeat(0..);
+    let name = segment() + (sym(b'/') + segment()).repeat(0..);
+    name.collect()
+        .convert(|name| String::from_utf8(name.to_vec()))
+}
+
+/// The `block` parser combinator is responsible for parsing a
+/// `Block(name, partials)`, a part of a layout which templates using the
+/// layout may override.
+///
+/// ```ebnf
+/// <Block> ::= <LeftDelimiter> "$" <Key> <RightDelimiter>
+///             <Partial>*
+///             <LeftDelimiter> "/" <Key> <RightDelimiter>
+/// ```
+pub fn block<'a>() -> Parser<'a, u8, Partial> {
+    let opening_tag = tag_left_delimiter()
+        * sym(b'$')
+        * skip_whitespace()
+        * labelled(key(), "a block name")
+        - skip_whitespace()
+        - labelled(tag_right_delimiter(), "`}}`");
+
+    standalone(opening_tag)
+        >> |name: String| {
+            let nested = call(partials) - closing_tag(name.clone(), tag_id);
+            nested.map(move |partials| Partial::Block(name.clone(), partials))
+        }
+}
+
+/// The `layout` parser combinator is responsible for parsing a
+/// `Layout(name, blocks)`, which is replaced by the template called `name`
+/// with its `Block`s overridden by `blocks` when the template is loaded.
+///
+/// ```ebnf
+/// <Layout> ::= <LeftDelimiter> "<" <TemplateName> <RightDelimiter>
+///              <Partial>*
+///              <LeftDelimiter> "/" <TemplateName> <RightDelimiter>
+/// ```
+pub fn layout<'a>() -> Parser<'a, u8, Partial> {
+    let opening_tag = tag_left_delimiter()
+        * sym(b'<')
+        * skip_whitespace()
+        * labelled(template_name(), "a template name")
+        - skip_whitespace()
+        - labelled(tag_right_delimiter(), "`}}`");
+
+    standalone(opening_tag)
+        >> |name: String| {
+            let nested =
+                call(partials) - closing_tag(name.clone(), template_name);
+            nested.map(move |partials| {
+                let blocks = partials
+                    .into_iter()
+                    .filter_map(|partial| match partial {
+                        Partial::Block(block, nested) => Some((block, nested)),
+                        _ => None,
+                    })
+                    .collect();
+                Partial::Layout(name.clone(), blocks)
+            })
+        }
+}
+
+/// The tag closing the section, block or layout `id`, whose name is parsed
+/// by `name`.
+fn closing_tag<'a>(
+    id: String,
+    name: fn() -> Parser<'a, u8, String>,
+) -> Parser<'a, u8, ()> {
+    let label = format!("`{{{{/{}}}}}`", id);
+    let closing_tag = tag_left_delimiter() * sym(b'/') * skip_whitespace()
+        + name()
         - skip_whitespace()
-        - tag_right_delimiter;
+        - tag_right_delimiter();
+
+    let closing_tag = (empty().pos() + closing_tag).convert(
+        move |(start, (_, closing_id))| {
+            if closing_id == id {
+                Ok(())
+            } else {
+                let delimiters = delimiters_at(start);
+                let message = format!(
+                    "section `{}` closed by mismatched tag `{}/{}{}`",
+                    id, delimiters.open, closing_id, delimiters.close
+                );
+                // No other parse can succeed past a mismatched closing tag,
+                // so this is reported rather than the furthest failure.
+                FURTHEST_FAILURE
+                    .with(|f| f.borrow_mut().fatal(start, message.clone()));
+                Err(message)
+            }
+        },
+    );
+    standalone(labelled(closing_tag, &label))
+}
+
+/// A tag opened with `{{-` drops the whitespace before it.
+///
+/// ```ebnf
+/// <LeftDelimiter> ::= "{{" "-"?
+/// ```
+fn tag_left_delimiter<'a>() -> Parser<'a, u8, ()> {
+    (delimiter(Delimiters::open) - sym(b'-').opt()).discard()
+}
 
-    tag.map(Partial::Tag)
+/// A tag closed with `-}}` drops the whitespace after it.
+///
+/// ```ebnf
+/// <RightDelimiter> ::= "-"? "}}"
+/// ```
+fn tag_right_delimiter<'a>() -> Parser<'a, u8, ()> {
+    let trimmed = sym(b'-') * delimiter(Delimiters::close) * skip_whitespace();
+    trimmed | delimiter(Delimiters::close)
+}
+
+/// The opening or closing delimiter, as picked by `pick`, of the
+/// `Delimiters` in use where it is parsed.
+fn delimiter<'a>(pick: fn(&Delimiters) -> &str) -> Parser<'a, u8, ()> {
+    Parser::new(move |input: &'a [u8], start: usize| {
+        let delimiters = delimiters_at(start);
+        let delimiter = pick(&delimiters);
+        if input[start..].starts_with(delimiter.as_bytes()) {
+            Ok(((), start + delimiter.len()))
+        } else {
+            Err(pom::Error::Mismatch {
+                message: format!("expected `{}`", delimiter),
+                position: start,
+            })
+        }
+    })
+}
+
+/// The `set_delimiters` parser combinator is responsible for parsing a tag
+/// which changes the `Delimiters` of the tags which follow it, up to the end
+/// of the template or the next set-delimiter tag. Included templates start
+/// with the delimiters of their `TemplateSource` instead. The delimiters set
+/// are only reset by `document_template` and `parse_template_with`, which is
+/// why this parser is not public.
+///
+/// ```ebnf
+/// <SetDelimiters> ::= <LeftDelimiter> "=" <Delimiter> " "+ <Delimiter> "="
+///                     <RightDelimiter>
+/// <Delimiter> ::= [^ \t\r\n=]+
+/// ```
+fn set_delimiters<'a>() -> Parser<'a, u8, ()> {
+    let delimiter = || {
+        let delimiter = none_of(b" \t\r\n=").repeat(1..);
+        labelled(delimiter.convert(String::from_utf8), "a delimiter")
+    };
+    let tag =
+        tag_left_delimiter() * sym(b'=') * skip_whitespace() * delimiter()
+            - whitespace()
+            + delimiter()
+            - skip_whitespace()
+            - labelled(sym(b'='), "`=`")
+            - labelled(tag_right_delimiter(), "`}}`");
+
+    Parser::new(move |input: &'a [u8], start: usize| {
+        let ((open, close), end) = tag.parse_at(input, start)?;
+        let delimiters = Delimiters::new(&open, &close).map_err(|message| {
+            FURTHEST_FAILURE
+                .with(|f| f.borrow_mut().fatal(start, message.clone()));
+            pom::Error::Custom {
+                message,
+                position: start,
+                inner: None,
+            }
+        })?;
+        DELIMITERS
+            .with(|d| d.borrow_mut().changes.insert(end, Rc::new(delimiters)));
+        Ok(((), standalone_end(input, start, end).unwrap_or(end)))
+    })
+}
+
+/// Characters following the left delimiter of the tags which may stand alone
+/// on a line: sections, conditionals, comments, includes, set-delimiter tags,
+/// blocks and layouts. The `else` branches of conditionals may stand alone as
+/// well.
+const BLOCK_TAG_SIGILS: &[u8] = b"#^/!>=$<";
+
+/// A block tag which stands alone on its line, with nothing but whitespace
+/// before and after it, does not leave a blank line in the document: the
+/// whitespace before the tag is dropped from the preceding `StringLiteral`, and
+/// the whitespace after it, up to and including the line break, is skipped.
+fn standalone<'a, O: 'a>(parser: Parser<'a, u8, O>) -> Parser<'a, u8, O> {
+    Parser::new(move |input: &'a [u8], start: usize| {
+        let (output, end) = parser.parse_at(input, start)?;
+        Ok((output, standalone_end(input, start, end).unwrap_or(end)))
+    })
+}
+
+/// If the tag between `start` and `end` stands alone on its line, the position
+/// just after the line.
+fn standalone_end(input: &[u8], start: usize, end: usize) -> Option<usize> {
+    let is_blank = |c: &u8| *c == b' ' || *c == b'\t' || *c == b'\r';
+    let line_start = input[..start]
+        .iter()
+        .rposition(|c| *c == b'\n')
+        .map_or(0, |i| i + 1);
+    if !input[line_start..start].iter().all(is_blank) {
+        return None;
+    }
+
+    let rest = &input[end..];
+    let blank = rest.iter().take_while(|c| is_blank(c)).count();
+    match rest.get(blank) {
+        None => Some(input.len()),
+        Some(b'\n') => Some(end + blank + 1),
+        Some(_) => None,
+    }
+}
+
+/// If a block tag starts at `start`, the position just after it.
+fn block_tag_end(input: &[u8], start: usize) -> Option<usize> {
+    let delimiters = delimiters_at(start);
+    let (open, close) =
+        (delimiters.open.as_bytes(), delimiters.close.as_bytes());
+    let rest = &input[start..];
+    if !rest.starts_with(open) {
+        return None;
+    }
+    let sigil = open.len() + (rest.get(open.len()) == Some(&b'-')) as usize;
+    if !rest
+        .get(sigil)
+        .is_some_and(|c| BLOCK_TAG_SIGILS.contains(c))
+        && !rest[sigil..].starts_with(b"else")
+    {
+        return None;
+    }
+    rest.windows(close.len())
+        .skip(sigil)
+        .position(|w| w == close)
+        .map(|i| start + sigil + i + close.len())
 }
 
 fn tag_id<'a>() -> Parser<'a, u8, String> {
+    let implicit_iterator = sym(b'.').map(|_| ".".to_string());
+    let path = key() + (sym(b'.') * path_segment()).repeat(0..);
+    let path = path.map(|(head, tail)| {
+        let mut s = head;
+        for segment in tail {
+            s.push('.');
+            s.push_str(&segment);
+        }
+        s
+    });
+    implicit_iterator | path
+}
+
+fn path_segment<'a>() -> Parser<'a, u8, String> {
+    let index = is_a(digit).repeat(1..).convert(String::from_utf8);
+    key() | index
+}
+
+fn key<'a>() -> Parser<'a, u8, String> {
     let id = tag_id_head() + tag_id_tail();
     id.map(|(head, tail)| {
         let mut s = String::new();
@@ -74,15 +613,527 @@ fn skip_whitespace<'a>() -> Parser<'a, u8, ()> {
     one_of(b" \t\r\n").repeat(0..).discard()
 }
 
-/// A `Partial` is either a `StringLiteral` or a `Tag`.
+fn whitespace<'a>() -> Parser<'a, u8, ()> {
+    one_of(b" \t\r\n").repeat(1..).discard()
+}
+
+/// A `word` which is not the beginning of a longer key.
+fn keyword<'a>(word: &'static [u8]) -> Parser<'a, u8, ()> {
+    (seq(word) - !(is_a(alphanum) | sym(b'_'))).discard()
+}
+
+/// The `conditional` parser combinator is responsible for parsing a
+/// `Conditional(branches, otherwise)`. Each branch has a condition, see
+/// `expression`, and the branches are closed by `{{/if}}`.
+///
+/// ```ebnf
+/// <Conditional> ::= <LeftDelimiter> "#if" <Expression> <RightDelimiter>
+///                   <Partial>*
+///                   (<LeftDelimiter> "else if" <Expression> <RightDelimiter>
+///                    <Partial>*)*
+///                   (<LeftDelimiter> "else" <RightDelimiter> <Partial>*)?
+///                   <LeftDelimiter> "/if" <RightDelimiter>
+/// ```
+pub fn conditional<'a>() -> Parser<'a, u8, Partial> {
+    let condition = || {
+        whitespace() * labelled(expression(), "an expression")
+            - skip_whitespace()
+            - labelled(tag_right_delimiter(), "`}}`")
+    };
+    let if_tag = tag_left_delimiter() * seq(b"#if") * condition();
+    let else_if_tag = tag_left_delimiter()
+        * seq(b"else")
+        * whitespace()
+        * seq(b"if")
+        * condition();
+    let else_tag = tag_left_delimiter() * keyword(b"else") * skip_whitespace()
+        - labelled(tag_right_delimiter(), "`}}`");
+
+    let first = standalone(if_tag) + call(partials);
+    let others = (standalone(else_if_tag) + call(partials)).repeat(0..);
+    let otherwise = (standalone(else_tag) * call(partials)).opt();
+    let conditional =
+        first + others + otherwise - closing_tag("if".to_string(), tag_id);
+
+    conditional.map(|((first, others), otherwise)| {
+        let mut branches = vec![first];
+        branches.extend(others);
+        Partial::Conditional(branches, otherwise.unwrap_or_default())
+    })
+}
+
+/// The `expression` parser combinator is responsible for parsing the condition
+/// of a `Conditional`, e.g. `patient.birthDate | age < 16 and not guardian`.
+/// Operands are literals, or tag identifiers followed by filters as in a
+/// `Tag`.
+///
+/// ```ebnf
+/// <Expression> ::= <Conjunction> ("or" <Conjunction>)*
+/// <Conjunction> ::= <Negation> ("and" <Negation>)*
+/// <Negation> ::= "not" <Negation> | <Comparison>
+/// <Comparison> ::= <Operand> (<Comparator> <Operand>)?
+/// <Comparator> ::= "==" | "!=" | "<=" | ">=" | "<" | ">"
+/// <Operand> ::= "(" <Expression> ")" | "true" | "false" | <Literal>
+///             | <TagId> <Filter>*
+/// ```
+pub fn expression<'a>() -> Parser<'a, u8, Expr> {
+    let or = skip_whitespace() * keyword(b"or") * skip_whitespace();
+    let expression = call(conjunction)
+        + (or * labelled(call(conjunction), "an operand")).repeat(0..);
+
+    expression.map(|(first, others)| {
+        others.into_iter().fold(first, |left, right| {
+            Expr::Or(Box::new(left), Box::new(right))
+        })
+    })
+}
+
+fn conjunction<'a>() -> Parser<'a, u8, Expr> {
+    let and = skip_whitespace() * keyword(b"and") * skip_whitespace();
+    let conjunction = call(negation)
+        + (and * labelled(call(negation), "an operand")).repeat(0..);
+
+    conjunction.map(|(first, others)| {
+        others.into_iter().fold(first, |left, right| {
+            Expr::And(Box::new(left), Box::new(right))
+        })
+    })
+}
+
+fn negation<'a>() -> Parser<'a, u8, Expr> {
+    let not = keyword(b"not")
+        * skip_whitespace()
+        * labelled(call(negation), "an operand");
+    not.map(|e| Expr::Not(Box::new(e))) | comparison()
+}
+
+fn comparison<'a>() -> Parser<'a, u8, Expr> {
+    let comparator = seq(b"==").map(|_| Comparator::Eq)
+        | seq(b"!=").map(|_| Comparator::Ne)
+        | seq(b"<=").map(|_| Comparator::Le)
+        | seq(b">=").map(|_| Comparator::Ge)
+        | sym(b'<').map(|_| Comparator::Lt)
+        | sym(b'>').map(|_| Comparator::Gt);
+    let comparison = operand()
+        + (skip_whitespace() * labelled(comparator, "a comparison")
+            - skip_whitespace()
+            + labelled(operand(), "an operand"))
+        .opt();
+
+    comparison.map(|(left, right)| match right {
+        Some((comparator, right)) => {
+            Expr::Compare(Box::new(left), comparator, Box::new(right))
+        }
+        None => left,
+    })
+}
+
+fn operand<'a>() -> Parser<'a, u8, Expr> {
+    let group = sym(b'(')
+        * skip_whitespace()
+        * labelled(call(expression), "an expression")
+        - skip_whitespace()
+        - labelled(sym(b')'), "`)`");
+    let boolean = keyword(b"true").map(|_| Value::Bool(true))
+        | keyword(b"false").map(|_| Value::Bool(false));
+    let tag =
+        tag_id() - skip_whitespace() + labelled(filter(), "`|`").repeat(0..);
+    let tag =
+        tag.map(|(id, filters)| Expr::Tag(Tag::with_filters(&id, &filters)));
+
+    group | (boolean | literal()).map(Expr::Literal) | tag
+}
+
+/// A `Partial` is either a `StringLiteral`, a `Tag`, a (possibly inverted)
+/// `Section`, a `Conditional`, an `Include`, a `Block`, a `Layout` or a
+/// `Message`.
 pub fn partial<'a>() -> Parser<'a, u8, Partial> {
-    string_literal() | tag()
+    string_literal()
+        | conditional()
+        | section()
+        | include()
+        | block()
+        | layout()
+        | message()
+        | tag()
 }
 
-/// A `DocumentTemplate` consists of a list of `Partial`s.
+/// The `comment` parser combinator is responsible for parsing a comment for
+/// the maintainers of a template, which may span several lines. Comments are
+/// left out of the `DocumentTemplate`.
+///
+/// ```ebnf
+/// <Comment> ::= "{{!" ([^}] | "}" [^}])* "}}"
+/// ```
+pub fn comment<'a>() -> Parser<'a, u8, ()> {
+    let text = (!tag_right_delimiter() * any()).repeat(0..);
+    let comment = tag_left_delimiter() * sym(b'!') * text
+        - labelled(tag_right_delimiter(), "`}}`");
+    standalone(comment.discard())
+}
+
+/// The `Partial`s of a template or of a `Section`, without the comments and
+/// set-delimiter tags in between them. Text on either side of those is joined
+/// into a single `StringLiteral`.
+pub fn partials<'a>() -> Parser<'a, u8, Vec<Partial>> {
+    let partial = comment().map(|_| None)
+        | set_delimiters().map(|_| None)
+        | partial().map(Some);
+    partial.repeat(0..).map(|partials| {
+        let mut joined: Vec<Partial> = Vec::with_capacity(partials.len());
+        for partial in partials.into_iter().flatten() {
+            if let Partial::StringLiteral(text) = &partial {
+                if text.is_empty() {
+                    continue;
+                }
+                if let Some(Partial::StringLiteral(last)) = joined.last_mut() {
+                    last.push_str(text);
+                    continue;
+                }
+            }
+            joined.push(partial);
+        }
+        joined
+    })
+}
+
+/// A `DocumentTemplate` consists of a list of `Partial`s. Its tags start
+/// delimited by the default `Delimiters`, whatever the templates parsed before
+/// it set them to.
 pub fn document_template<'a>() -> Parser<'a, u8, DocumentTemplate> {
-    let partials = partial().repeat(0..) - end();
-    partials.map(|ps| DocumentTemplate::with_partials(&ps))
+    let partials = partials() - end();
+    Parser::new(move |input: &'a [u8], start: usize| {
+        DELIMITERS.with(|d| d.replace(DelimiterChanges::default()));
+        let parsed = partials.parse_at(input, start);
+        DELIMITERS.with(|d| d.replace(DelimiterChanges::default()));
+        parsed.map(|(ps, end)| (DocumentTemplate::with_partials(&ps), end))
+    })
+}
+
+/// Parse `source` as a `DocumentTemplate`, locating the error if it is not a
+/// valid template.
+pub fn parse_template(
+    source: &str,
+) -> Result<DocumentTemplate, TemplateParseError> {
+    parse_template_with(source, &Delimiters::default())
+}
+
+/// Parse `source` as `parse_template` does, with tags delimited by
+/// `delimiters` until a set-delimiter tag changes them.
+pub fn parse_template_with(
+    source: &str,
+    delimiters: &Delimiters,
+) -> Result<DocumentTemplate, TemplateParseError> {
+    FURTHEST_FAILURE.with(|f| f.replace(Failure::default()));
+    DELIMITERS.with(|d| d.replace(DelimiterChanges::new(delimiters.clone())));
+
+    let input = source.as_bytes();
+    let result = match partials().parse_at(input, 0) {
+        Ok((partials, end)) if end == input.len() => {
+            Ok(DocumentTemplate::with_partials(&partials))
+        }
+        parsed => {
+            let stop = parsed.map_or(0, |(_, end)| end);
+            let failure =
+                FURTHEST_FAILURE.with(|f| f.replace(Failure::default()));
+            Err(diagnose(source, stop, failure))
+        }
+    };
+
+    DELIMITERS.with(|d| d.replace(DelimiterChanges::default()));
+    result
+}
+
+/// A `TemplateParseError` locates the first part of a template which could not
+/// be parsed. It is displayed in the style of `rustc` diagnostics:
+///
+/// ```text
+/// error: expected one of `?`, `|`, `}}`, found `}`
+///  --> letter.template:3:14
+///   |
+/// 3 | Dear {{ name }
+///   |              ^
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct TemplateParseError {
+    /// Where the template comes from, e.g. its path.
+    pub origin: Option<String>,
+    /// Line of the error, starting from 1.
+    pub line: u32,
+    /// Column of the error in characters, starting from 1.
+    pub column: u32,
+    pub message: String,
+    /// What would have been accepted at the error, e.g. "`}}`".
+    pub expected: Vec<String>,
+    /// The line of the template containing the error.
+    pub source_line: String,
+    /// A hint on how to fix the error.
+    pub help: Option<&'static str>,
+}
+
+impl TemplateParseError {
+    fn new(source: &str, position: usize, message: String) -> Self {
+        let (line, column, source_line) = locate(source, position);
+
+        Self {
+            origin: None,
+            line,
+            column,
+            message,
+            expected: Vec::new(),
+            source_line,
+            help: None,
+        }
+    }
+
+    /// Name the template the error comes from.
+    pub fn with_origin(mut self, origin: &str) -> Self {
+        self.origin = Some(origin.to_string());
+        self
+    }
+
+    /// Locate the error in a file in which the template starts after `lines`
+    /// lines, e.g. after its front matter.
+    pub fn offset_lines(mut self, lines: u32) -> Self {
+        self.line += lines;
+        self
+    }
+}
+
+impl fmt::Display for TemplateParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "error: {}", self.message)?;
+        write_snippet(
+            f,
+            self.origin.as_deref(),
+            self.line,
+            self.column,
+            &self.source_line,
+        )?;
+        if let Some(help) = &self.help {
+            let gutter = " ".repeat(self.line.to_string().len());
+            write!(f, "\n{} = help: {}", gutter, help)?;
+        }
+        Ok(())
+    }
+}
+
+/// The line and the column in characters, both starting from 1, of the byte
+/// at `position` in `source`, and the text of that line.
+pub(super) fn locate(source: &str, position: usize) -> (u32, u32, String) {
+    let mut position = position.min(source.len());
+    while !source.is_char_boundary(position) {
+        position -= 1;
+    }
+
+    let line_start = source[..position].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[position..]
+        .find('\n')
+        .map_or(source.len(), |i| position + i);
+
+    (
+        source[..position].matches('\n').count() as u32 + 1,
+        source[line_start..position].chars().count() as u32 + 1,
+        source[line_start..line_end]
+            .trim_end_matches('\r')
+            .to_string(),
+    )
+}
+
+/// Write where in the template `origin` a problem is, pointing a caret at
+/// `column` of `source_line`, the `line`-th line of the template.
+pub(super) fn write_snippet(
+    f: &mut fmt::Formatter<'_>,
+    origin: Option<&str>,
+    line: u32,
+    column: u32,
+    source_line: &str,
+) -> fmt::Result {
+    let gutter = " ".repeat(line.to_string().len());
+    // Tabs are kept so that the caret lines up with the source line.
+    let indent = source_line
+        .chars()
+        .take(column as usize - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+
+    writeln!(
+        f,
+        "{}--> {}:{}:{}",
+        gutter,
+        origin.unwrap_or("<template>"),
+        line,
+        column
+    )?;
+    writeln!(f, "{} |", gutter)?;
+    writeln!(f, "{} | {}", line, source_line)?;
+    write!(f, "{} | {}^", gutter, indent)
+}
+
+thread_local! {
+    /// The furthest failure of a `labelled` parser while parsing a template.
+    /// When a template cannot be parsed, this is usually where it went wrong.
+    static FURTHEST_FAILURE: RefCell<Failure> = RefCell::new(Failure::default());
+
+    /// The `Delimiters` in use while parsing a template.
+    static DELIMITERS: RefCell<DelimiterChanges> =
+        RefCell::new(DelimiterChanges::default());
+}
+
+/// The `Delimiters` a template starts with, and those set by each of its
+/// set-delimiter tags, keyed by the position they are used from. Keying them
+/// by position rather than keeping the latest ones lets parsers backtrack
+/// over set-delimiter tags.
+#[derive(Debug, Default)]
+struct DelimiterChanges {
+    initial: Rc<Delimiters>,
+    changes: BTreeMap<usize, Rc<Delimiters>>,
+}
+
+impl DelimiterChanges {
+    fn new(initial: Delimiters) -> Self {
+        Self {
+            initial: Rc::new(initial),
+            changes: BTreeMap::new(),
+        }
+    }
+}
+
+/// The `Delimiters` in use at `position` of the template being parsed.
+fn delimiters_at(position: usize) -> Rc<Delimiters> {
+    DELIMITERS.with(|d| {
+        let d = d.borrow();
+        d.changes
+            .range(..=position)
+            .next_back()
+            .map_or_else(|| d.initial.clone(), |(_, changed)| changed.clone())
+    })
+}
+
+#[derive(Debug, Default)]
+struct Failure {
+    position: usize,
+    expected: Vec<String>,
+    /// A failure which no alternative can recover from.
+    fatal: Option<(usize, String)>,
+}
+
+impl Failure {
+    fn expect(&mut self, position: usize, label: &str) {
+        if position > self.position || self.expected.is_empty() {
+            self.position = position;
+            self.expected = vec![label.to_string()];
+        } else if position == self.position
+            && !self.expected.iter().any(|l| l == label)
+        {
+            self.expected.push(label.to_string());
+        }
+    }
+
+    fn fatal(&mut self, position: usize, message: String) {
+        if self.fatal.as_ref().is_none_or(|(p, _)| position > *p) {
+            self.fatal = Some((position, message));
+        }
+    }
+}
+
+/// Record `label` as expected at the current position when `parser` fails.
+/// Delimiters in `label` are written as `{{` and `}}`, and replaced by the
+/// `Delimiters` in use.
+fn labelled<'a, O: 'a>(
+    parser: Parser<'a, u8, O>,
+    label: &str,
+) -> Parser<'a, u8, O> {
+    let label = label.to_string();
+    Parser::new(move |input: &'a [u8], start: usize| {
+        let result = parser.parse_at(input, start);
+        if result.is_err() {
+            let delimiters = delimiters_at(start);
+            let label = if delimiters.is_default() {
+                label.clone()
+            } else {
+                label
+                    .replace("{{", &delimiters.open)
+                    .replace("}}", &delimiters.close)
+            };
+            FURTHEST_FAILURE.with(|f| f.borrow_mut().expect(start, &label));
+        }
+        result
+    })
+}
+
+/// Build the error for a template whose `Partial`s could only be parsed up to
+/// `stop`.
+fn diagnose(source: &str, stop: usize, failure: Failure) -> TemplateParseError {
+    if let Some((position, message)) = failure.fatal {
+        return TemplateParseError::new(source, position, message);
+    }
+
+    if !failure.expected.is_empty() && failure.position >= stop {
+        let position = failure.position;
+        let expected = match failure.expected.as_slice() {
+            [label] => label.clone(),
+            labels => format!("one of {}", labels.join(", ")),
+        };
+        let message =
+            format!("expected {}, found {}", expected, found(source, position));
+        let mut error = TemplateParseError::new(source, position, message);
+        error.expected = failure.expected;
+        return error;
+    }
+
+    let rest = &source[stop..];
+    let delimiters = delimiters_at(stop);
+    let (message, help) = match rest.chars().next() {
+        _ if rest.starts_with(delimiters.open.as_str())
+            || !delimiters.is_default() =>
+        {
+            (format!("unexpected {}", found(source, stop)), None)
+        }
+        Some('{') => (
+            "unexpected `{`".to_string(),
+            Some("write `\\{` for a literal `{`"),
+        ),
+        Some('}') => (
+            "unexpected `}`".to_string(),
+            Some("write `\\}` for a literal `}`"),
+        ),
+        Some('\\') => (
+            match rest.chars().nth(1) {
+                Some(c) => format!("invalid escape sequence `\\{}`", c),
+                None => "unfinished escape sequence".to_string(),
+            },
+            Some("only `\\\\`, `\\{` and `\\}` can be escaped"),
+        ),
+        _ => (format!("unexpected {}", found(source, stop)), None),
+    };
+    let mut error = TemplateParseError::new(source, stop, message);
+    error.help = help;
+    error
+}
+
+/// Describe what was found at `position` for an error message.
+fn found(source: &str, position: usize) -> String {
+    let rest = source.get(position..).unwrap_or("");
+    let delimiters = delimiters_at(position);
+    let (open, close) = (delimiters.open.as_str(), delimiters.close.as_str());
+    if rest.starts_with(open) {
+        // Quote the whole tag if it is short enough.
+        let tag = rest
+            .find(close)
+            .map(|end| &rest[..end + close.len()])
+            .filter(|tag| tag.len() <= 40 && !tag.contains('\n'))
+            .unwrap_or(open);
+        format!("`{}`", tag)
+    } else if rest.starts_with(close) {
+        format!("`{}`", close)
+    } else {
+        match rest.chars().next() {
+            None => "end of template".to_string(),
+            Some('\n') | Some('\r') => "end of line".to_string(),
+            Some(c) => format!("`{}`", c),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -166,58 +1217,833 @@ mod tests {
     #[test]
     fn test_tag() {
         let raw = b"{{abc}}";
-        let expected_tag = Partial::Tag("abc".to_string());
+        let expected_tag = Partial::Tag(Tag::new("abc"));
         assert_eq!(expected_tag, tag().parse(raw).unwrap());
     }
 
     #[test]
     fn test_tag_id_with_middle_underscore() {
         let raw = b"{{ a_c }}";
-        let expected_tag = Partial::Tag("a_c".to_string());
+        let expected_tag = Partial::Tag(Tag::new("a_c"));
         assert_eq!(expected_tag, tag().parse(raw).unwrap());
     }
 
     #[test]
     fn test_tag_id_with_starting_underscore() {
         let raw = b"{{ _x }}";
-        let expected_tag = Partial::Tag("_x".to_string());
+        let expected_tag = Partial::Tag(Tag::new("_x"));
         assert_eq!(expected_tag, tag().parse(raw).unwrap());
     }
 
     #[test]
     fn test_tag_id_with_trailing_underscore() {
         let raw = b"{{ a_ }}";
-        let expected_tag = Partial::Tag("a_".to_string());
+        let expected_tag = Partial::Tag(Tag::new("a_"));
         assert_eq!(expected_tag, tag().parse(raw).unwrap());
     }
 
     #[test]
-    fn test_tag_whitespace() {
-        let raw = b"{{ \t xxxx   }}";
-        let expected_tag = Partial::Tag("xxxx".to_string());
+    fn test_implicit_iterator_tag() {
+        let raw = b"{{ . }}";
+        let expected_tag = Partial::Tag(Tag::new("."));
+        assert_eq!(expected_tag, tag().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_dotted_path_tag() {
+        let raw = b"{{ patient.name.0.family }}";
+        let expected_tag = Partial::Tag(Tag::new("patient.name.0.family"));
         assert_eq!(expected_tag, tag().parse(raw).unwrap());
     }
 
     #[test]
     #[should_panic]
-    fn test_malformed_tag() {
-        let raw = b"{{ \t separated identifiers illegal }}";
+    fn test_dotted_path_trailing_dot() {
+        let raw = b"{{ patient. }}";
         tag().parse(raw).unwrap();
     }
 
     #[test]
-    fn test_document_template() {
-        let raw = b"abc {{def}} ghi";
-        let expected_document_template =
-            DocumentTemplate::with_partials(&[
-                Partial::StringLiteral("abc ".to_string()),
-                Partial::Tag("def".to_string()),
-                Partial::StringLiteral(" ghi".to_string()),
-            ]);
+    #[should_panic]
+    fn test_dotted_path_leading_index() {
+        let raw = b"{{ 0.family }}";
+        tag().parse(raw).unwrap();
+    }
 
-        assert_eq!(
-            expected_document_template,
-            document_template().parse(raw).unwrap()
+    #[test]
+    fn test_tag_with_filters() {
+        let raw = b"{{ name | upper }}";
+        let expected_tag = Partial::Tag(Tag::with_filters(
+            "name",
+            &[Filter::new("upper", &[])],
+        ));
+        assert_eq!(expected_tag, tag().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_tag_with_filter_arguments() {
+        let raw =
+            b"{{birth_date|format(\"%d %B %Y\")|pad_left( 12 , \"\\\"\" )}}";
+        let expected_tag = Partial::Tag(Tag::with_filters(
+            "birth_date",
+            &[
+                Filter::new("format", &["%d %B %Y".into()]),
+                Filter::new("pad_left", &[Value::Number(12.0), "\"".into()]),
+            ],
+        ));
+        assert_eq!(expected_tag, tag().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_tag_with_default() {
+        let raw = b"{{ family ? \"Unknown\" }}";
+        let expected_tag =
+            Partial::Tag(Tag::with_default("family", "Unknown".into()));
+        assert_eq!(expected_tag, tag().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_tag_with_default_and_filters() {
+        let raw = b"{{ family?\"Unknown\" | upper }}";
+        let expected_tag = Partial::Tag(Tag {
+            default: Some("Unknown".into()),
+            ..Tag::with_filters("family", &[Filter::new("upper", &[])])
+        });
+        assert_eq!(expected_tag, tag().parse(raw).unwrap());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tag_with_empty_default() {
+        let raw = b"{{ family ? }}";
+        tag().parse(raw).unwrap();
+    }
+
+    #[test]
+    fn test_number_literals() {
+        assert_eq!(Value::Number(-1.5), literal().parse(b"-1.5").unwrap());
+        assert_eq!(Value::Number(3.0), literal().parse(b"3").unwrap());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unterminated_filter_arguments() {
+        let raw = b"{{ name | truncate(3 }}";
+        tag().parse(raw).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_filter() {
+        let raw = b"{{ name | }}";
+        tag().parse(raw).unwrap();
+    }
+
+    #[test]
+    fn test_tag_whitespace() {
+        let raw = b"{{ \t xxxx   }}";
+        let expected_tag = Partial::Tag(Tag::new("xxxx"));
+        assert_eq!(expected_tag, tag().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_raw_tags() {
+        let expected_tag = Partial::Tag(Tag::raw("family"));
+        assert_eq!(expected_tag, tag().parse(b"{{{ family }}}").unwrap());
+        assert_eq!(expected_tag, tag().parse(b"{{& family }}").unwrap());
+
+        let expected_tag = Partial::Tag(Tag {
+            raw: true,
+            ..Tag::with_filters("family", &[Filter::new("upper", &[])])
+        });
+        assert_eq!(expected_tag, tag().parse(b"{{{family|upper}}}").unwrap());
+    }
+
+    #[test]
+    fn test_unterminated_raw_tag() {
+        let error = parse_template("Dear {{{ family }},").unwrap_err();
+        assert_eq!(
+            "expected one of `?`, `|`, `}}}`, found `}}`",
+            error.message
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_malformed_tag() {
+        let raw = b"{{ \t separated identifiers illegal }}";
+        tag().parse(raw).unwrap();
+    }
+
+    #[test]
+    fn test_document_template() {
+        let raw = b"abc {{def}} ghi";
+        let expected_document_template = DocumentTemplate::with_partials(&[
+            Partial::StringLiteral("abc ".to_string()),
+            Partial::Tag(Tag::new("def")),
+            Partial::StringLiteral(" ghi".to_string()),
+        ]);
+
+        assert_eq!(
+            expected_document_template,
+            document_template().parse(raw).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_section() {
+        let raw = b"{{#family}}Mx {{ family }}{{/family}}";
+        let expected_section = Partial::Section(
+            "family".to_string(),
+            vec![
+                Partial::StringLiteral("Mx ".to_string()),
+                Partial::Tag(Tag::new("family")),
+            ],
+        );
+        assert_eq!(expected_section, section().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_inverted_section() {
+        let raw = b"{{^ family }}Unknown{{/ family }}";
+        let expected_section = Partial::InvertedSection(
+            "family".to_string(),
+            vec![Partial::StringLiteral("Unknown".to_string())],
+        );
+        assert_eq!(expected_section, section().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_nested_sections() {
+        let raw = b"{{#a}}{{^b}}x{{/b}}{{/a}}";
+        let expected_section = Partial::Section(
+            "a".to_string(),
+            vec![Partial::InvertedSection(
+                "b".to_string(),
+                vec![Partial::StringLiteral("x".to_string())],
+            )],
+        );
+        assert_eq!(expected_section, section().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_section_over_implicit_iterator() {
+        let raw = b"{{#given}}{{.}} {{/given}}";
+        let expected_section = Partial::Section(
+            "given".to_string(),
+            vec![
+                Partial::Tag(Tag::new(".")),
+                Partial::StringLiteral(" ".to_string()),
+            ],
+        );
+        assert_eq!(expected_section, section().parse(raw).unwrap());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_mismatched_section_closing_tag() {
+        let raw = b"{{#a}}x{{/b}}";
+        section().parse(raw).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unclosed_section() {
+        let raw = b"{{#a}}x";
+        document_template().parse(raw).unwrap();
+    }
+
+    #[test]
+    fn test_include() {
+        let raw = b"{{> letterhead }}";
+        let expected_include = Partial::Include("letterhead".to_string());
+        assert_eq!(expected_include, include().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_include_in_sub_directory() {
+        let raw = b"{{>common/sign-off}}";
+        let expected_include = Partial::Include("common/sign-off".to_string());
+        assert_eq!(expected_include, include().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_message() {
+        let template = parse_template(
+            "{{ t \"greeting\" }} {{t \"letter.intro\"}}{{ t }}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            DocumentTemplate::with_partials(&[
+                Partial::Message("greeting".to_string()),
+                Partial::StringLiteral(" ".to_string()),
+                Partial::Message("letter.intro".to_string()),
+                Partial::Tag(Tag::new("t")),
+            ]),
+            template
+        );
+        assert!(parse_template("{{ t \"greeting }}").is_err());
+    }
+
+    #[test]
+    fn test_block() {
+        let raw = b"{{$body}}Dear {{ name }},{{/body}}";
+        let expected_block = Partial::Block(
+            "body".to_string(),
+            vec![
+                Partial::StringLiteral("Dear ".to_string()),
+                Partial::Tag(Tag::new("name")),
+                Partial::StringLiteral(",".to_string()),
+            ],
+        );
+        assert_eq!(expected_block, block().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_layout() {
+        let template = parse_template(
+            "{{< common/letter }}\n\
+             Ignored {{ text }}\n\
+             {{$body}}\n\
+             Hello\n\
+             {{/body}}\n\
+             {{/common/letter}}\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            DocumentTemplate::with_partials(&[Partial::Layout(
+                "common/letter".to_string(),
+                vec![(
+                    "body".to_string(),
+                    vec![Partial::StringLiteral("Hello\n".to_string())]
+                )]
+            )]),
+            template
+        );
+    }
+
+    #[test]
+    fn test_mismatched_layout_closing_tag() {
+        let error = parse_template("{{<letter}}{{/body}}").unwrap_err();
+        assert_eq!(
+            "section `letter` closed by mismatched tag `{{/body}}`",
+            error.message
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_include() {
+        let raw = b"{{> }}";
+        include().parse(raw).unwrap();
+    }
+
+    #[test]
+    fn test_include_outside_of_templates_directory() {
+        for raw in [
+            &b"{{> /etc/passwd }}"[..],
+            b"{{> common//sign-off }}",
+            b"{{> common/ }}",
+            b"{{> ../secrets }}",
+        ] {
+            assert!(include().parse(raw).is_err());
+        }
+    }
+
+    #[test]
+    fn test_document_template_with_section() {
+        let raw = b"Dear{{#name}} {{name}}{{/name}},";
+        let expected_document_template = DocumentTemplate::with_partials(&[
+            Partial::StringLiteral("Dear".to_string()),
+            Partial::Section(
+                "name".to_string(),
+                vec![
+                    Partial::StringLiteral(" ".to_string()),
+                    Partial::Tag(Tag::new("name")),
+                ],
+            ),
+            Partial::StringLiteral(",".to_string()),
+        ]);
+
+        assert_eq!(
+            expected_document_template,
+            document_template().parse(raw).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_template() {
+        assert_eq!(
+            document_template().parse(b"Dear {{ name }},").unwrap(),
+            parse_template("Dear {{ name }},").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let error = parse_template("Dear\n  {{ name }\nBye").unwrap_err();
+
+        assert_eq!(2, error.line);
+        assert_eq!(11, error.column);
+        assert_eq!("  {{ name }", error.source_line);
+        assert_eq!(vec!["`?`", "`|`", "`}}`"], error.expected);
+        assert_eq!("expected one of `?`, `|`, `}}`, found `}`", error.message);
+    }
+
+    #[test]
+    fn test_parse_error_within_section() {
+        let error =
+            parse_template("{{#patients}}\n{{ name | }}\n{{/patients}}")
+                .unwrap_err();
+
+        assert_eq!((2, 11), (error.line, error.column));
+        assert_eq!("expected a filter name, found `}}`", error.message);
+    }
+
+    #[test]
+    fn test_parse_error_unterminated_filter_arguments() {
+        let error = parse_template("{{ d | format(\"%Y\" }}").unwrap_err();
+
+        assert_eq!(vec!["`,`", "`)`"], error.expected);
+    }
+
+    #[test]
+    fn test_parse_error_unclosed_section() {
+        let error = parse_template("{{#name}}Dear").unwrap_err();
+
+        assert_eq!(
+            "expected `{{/name}}`, found end of template",
+            error.message
+        );
+        assert_eq!(14, error.column);
+    }
+
+    #[test]
+    fn test_parse_error_mismatched_closing_tag() {
+        let error = parse_template("{{#a}}\n{{#b}}x{{/a}}").unwrap_err();
+
+        assert_eq!(
+            "section `b` closed by mismatched tag `{{/a}}`",
+            error.message
+        );
+        assert_eq!((2, 8), (error.line, error.column));
+    }
+
+    #[test]
+    fn test_parse_error_unescaped_brace() {
+        let error = parse_template("a { b").unwrap_err();
+
+        assert_eq!("unexpected `{`", error.message);
+        assert_eq!(3, error.column);
+        assert_eq!(Some("write `\\{` for a literal `{`"), error.help);
+    }
+
+    #[test]
+    fn test_parse_error_invalid_escape() {
+        let error = parse_template("a\\nb").unwrap_err();
+
+        assert_eq!("invalid escape sequence `\\n`", error.message);
+    }
+
+    #[test]
+    fn test_display_parse_error() {
+        let error = parse_template("Dear\n\t{{ name }")
+            .unwrap_err()
+            .with_origin("letter.template");
+
+        let expected = "error: expected one of `?`, `|`, `}}`, found `}`\n \
+                        --> letter.template:2:10\n  \
+                        |\n\
+                        2 | \t{{ name }\n  \
+                        | \t        ^";
+        assert_eq!(expected, error.to_string());
+    }
+
+    #[test]
+    fn test_comment() {
+        let raw = b"Dear {{! the patient's given name }}{{ name }},";
+        let expected_document_template = DocumentTemplate::with_partials(&[
+            Partial::StringLiteral("Dear ".to_string()),
+            Partial::Tag(Tag::new("name")),
+            Partial::StringLiteral(",".to_string()),
+        ]);
+
+        assert_eq!(
+            expected_document_template,
+            document_template().parse(raw).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_multi_line_comment_joins_text() {
+        let raw = b"Dear{{!\n  Keep the comma:\n  {not a tag}\n}} Sir,";
+        let expected_document_template =
+            DocumentTemplate::with_partials(&[Partial::StringLiteral(
+                "Dear Sir,".to_string(),
+            )]);
+
+        assert_eq!(
+            expected_document_template,
+            document_template().parse(raw).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_comment_in_section() {
+        let raw = b"{{#names}}{{! one per line }}{{ . }}\\{{{/names}}";
+        let expected_document_template =
+            DocumentTemplate::with_partials(&[Partial::Section(
+                "names".to_string(),
+                vec![
+                    Partial::Tag(Tag::new(".")),
+                    Partial::StringLiteral("{".to_string()),
+                ],
+            )]);
+
+        assert_eq!(
+            expected_document_template,
+            document_template().parse(raw).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_escaped_text_around_comment() {
+        let raw = b"\\{{{! literal braces }}\\}";
+        let expected_document_template =
+            DocumentTemplate::with_partials(&[Partial::StringLiteral(
+                "{}".to_string(),
+            )]);
+
+        assert_eq!(
+            expected_document_template,
+            document_template().parse(raw).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unterminated_comment() {
+        let error = parse_template("Dear {{! name ").unwrap_err();
+
+        assert_eq!("expected `}}`, found end of template", error.message);
+    }
+
+    #[test]
+    fn test_trim_markers() {
+        let raw = b"Dear \n {{- name -}} \n ,";
+        let expected_document_template = DocumentTemplate::with_partials(&[
+            Partial::StringLiteral("Dear".to_string()),
+            Partial::Tag(Tag::new("name")),
+            Partial::StringLiteral(",".to_string()),
+        ]);
+
+        assert_eq!(
+            expected_document_template,
+            document_template().parse(raw).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_trim_markers_on_section() {
+        let raw = b"[ {{-#names-}} \n {{ . }} {{-/names -}} ]";
+        let expected_document_template = DocumentTemplate::with_partials(&[
+            Partial::StringLiteral("[".to_string()),
+            Partial::Section(
+                "names".to_string(),
+                vec![Partial::Tag(Tag::new("."))],
+            ),
+            Partial::StringLiteral("]".to_string()),
+        ]);
+
+        assert_eq!(
+            expected_document_template,
+            document_template().parse(raw).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_include_name_before_trim_marker() {
+        let raw = b"{{> common/sign-off-}}";
+        let expected_include = Partial::Include("common/sign-off".to_string());
+
+        assert_eq!(expected_include, include().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_standalone_section_lines() {
+        let raw =
+            b"Patients:\n  {{#patients}}\n- {{ name }}\n  {{/patients}}\nEnd";
+        let expected_document_template = DocumentTemplate::with_partials(&[
+            Partial::StringLiteral("Patients:\n".to_string()),
+            Partial::Section(
+                "patients".to_string(),
+                vec![
+                    Partial::StringLiteral("- ".to_string()),
+                    Partial::Tag(Tag::new("name")),
+                    Partial::StringLiteral("\n".to_string()),
+                ],
+            ),
+            Partial::StringLiteral("End".to_string()),
+        ]);
+
+        assert_eq!(
+            expected_document_template,
+            document_template().parse(raw).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_standalone_comment_and_include_lines() {
+        let raw = b"{{! letterhead }}\r\n{{> letterhead }}\nDear {{ name }},\n{{> signature }}";
+        let expected_document_template = DocumentTemplate::with_partials(&[
+            Partial::Include("letterhead".to_string()),
+            Partial::StringLiteral("Dear ".to_string()),
+            Partial::Tag(Tag::new("name")),
+            Partial::StringLiteral(",\n".to_string()),
+            Partial::Include("signature".to_string()),
+        ]);
+
+        assert_eq!(
+            expected_document_template,
+            document_template().parse(raw).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_tags_sharing_a_line_are_not_standalone() {
+        let raw = b"{{#a}}{{/a}}\n {{ b }}\n{{^c}} x\n{{/c}}";
+        let expected_document_template = DocumentTemplate::with_partials(&[
+            Partial::Section("a".to_string(), vec![]),
+            Partial::StringLiteral("\n ".to_string()),
+            Partial::Tag(Tag::new("b")),
+            Partial::StringLiteral("\n".to_string()),
+            Partial::InvertedSection(
+                "c".to_string(),
+                vec![Partial::StringLiteral(" x\n".to_string())],
+            ),
+        ]);
+
+        assert_eq!(
+            expected_document_template,
+            document_template().parse(raw).unwrap()
+        );
+    }
+
+    fn age_at_least(years: u32) -> Expr {
+        Expr::Compare(
+            Box::new(Expr::Tag(Tag::new("age"))),
+            Comparator::Ge,
+            Box::new(Expr::Literal(years.into())),
+        )
+    }
+
+    #[test]
+    fn test_conditional() {
+        let raw = b"{{#if age >= 18}}adult{{else if age>=16}}young person\
+                    {{else}}child{{/if}}";
+        let expected_conditional = Partial::Conditional(
+            vec![
+                (
+                    age_at_least(18),
+                    vec![Partial::StringLiteral("adult".to_string())],
+                ),
+                (
+                    age_at_least(16),
+                    vec![Partial::StringLiteral("young person".to_string())],
+                ),
+            ],
+            vec![Partial::StringLiteral("child".to_string())],
+        );
+
+        assert_eq!(expected_conditional, conditional().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_conditional_standalone_lines() {
+        let raw = b"{{#if guardian}}\n  Dear guardian,\n{{else}}\nDear patient,\n{{/if}}\n";
+        let expected_document_template =
+            DocumentTemplate::with_partials(&[Partial::Conditional(
+                vec![(
+                    Expr::Tag(Tag::new("guardian")),
+                    vec![Partial::StringLiteral(
+                        "  Dear guardian,\n".to_string(),
+                    )],
+                )],
+                vec![Partial::StringLiteral("Dear patient,\n".to_string())],
+            )]);
+
+        assert_eq!(
+            expected_document_template,
+            document_template().parse(raw).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_expression_precedence() {
+        let raw = b"a or not b and c | upper == \"X\"";
+        let c_is_x = Expr::Compare(
+            Box::new(Expr::Tag(Tag::with_filters(
+                "c",
+                &[Filter::new("upper", &[])],
+            ))),
+            Comparator::Eq,
+            Box::new(Expr::Literal("X".into())),
+        );
+        let expected_expression = Expr::Or(
+            Box::new(Expr::Tag(Tag::new("a"))),
+            Box::new(Expr::And(
+                Box::new(Expr::Not(Box::new(Expr::Tag(Tag::new("b"))))),
+                Box::new(c_is_x),
+            )),
+        );
+
+        assert_eq!(expected_expression, expression().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_expression_keywords_and_groups() {
+        let raw = b"(order or notes) and true";
+        let expected_expression = Expr::And(
+            Box::new(Expr::Or(
+                Box::new(Expr::Tag(Tag::new("order"))),
+                Box::new(Expr::Tag(Tag::new("notes"))),
+            )),
+            Box::new(Expr::Literal(Value::Bool(true))),
+        );
+
+        assert_eq!(expected_expression, expression().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_section_named_like_a_keyword() {
+        let raw = b"{{#iffy}}x{{/iffy}}";
+        let expected_section = Partial::Section(
+            "iffy".to_string(),
+            vec![Partial::StringLiteral("x".to_string())],
+        );
+
+        assert_eq!(expected_section, partial().parse(raw).unwrap());
+    }
+
+    #[test]
+    fn test_parse_error_in_condition() {
+        let error = parse_template("{{#if age >>= 18}}x{{/if}}").unwrap_err();
+
+        assert_eq!("expected an operand, found `>`", error.message);
+        assert_eq!(12, error.column);
+    }
+
+    #[test]
+    fn test_parse_error_else_outside_conditional() {
+        let error = parse_template("Dear {{else}} patient").unwrap_err();
+
+        assert_eq!("unexpected `{{else}}`", error.message);
+    }
+
+    #[test]
+    fn test_parse_error_unclosed_conditional() {
+        let error = parse_template("{{#if a}}x{{else}}y").unwrap_err();
+
+        assert_eq!("expected `{{/if}}`, found end of template", error.message);
+    }
+
+    #[test]
+    fn test_set_delimiters() {
+        let template = parse_template(
+            "{{ a }}\n{{=<% %>=}}\n\\textbf{<% b %>} {{ c }}\n<%={{ }}=%>{{ d }}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            DocumentTemplate::with_partials(&[
+                Partial::Tag(Tag::new("a")),
+                Partial::StringLiteral("\n\\textbf{".to_string()),
+                Partial::Tag(Tag::new("b")),
+                Partial::StringLiteral("} {{ c }}\n".to_string()),
+                Partial::Tag(Tag::new("d")),
+            ]),
+            template
+        );
+    }
+
+    #[test]
+    fn test_set_delimiters_in_section() {
+        let template =
+            parse_template("{{#a}}{{=[ ]=}}[ b ][/a][#c]{[ d ]}[/c]").unwrap();
+
+        assert_eq!(
+            DocumentTemplate::with_partials(&[
+                Partial::Section(
+                    "a".to_string(),
+                    vec![Partial::Tag(Tag::new("b"))]
+                ),
+                Partial::Section(
+                    "c".to_string(),
+                    vec![
+                        Partial::StringLiteral("{".to_string()),
+                        Partial::Tag(Tag::new("d")),
+                        Partial::StringLiteral("}".to_string()),
+                    ]
+                ),
+            ]),
+            template
+        );
+    }
+
+    #[test]
+    fn test_parse_template_with_delimiters() {
+        let delimiters = Delimiters::new("<%", "%>").unwrap();
+        let template =
+            parse_template_with("{ \"a\": <%-& a -%> }", &delimiters).unwrap();
+
+        assert_eq!(
+            DocumentTemplate::with_partials(&[
+                Partial::StringLiteral("{ \"a\":".to_string()),
+                Partial::Tag(Tag::raw("a")),
+                Partial::StringLiteral("}".to_string()),
+            ]),
+            template
+        );
+        // The default delimiters are used again by the next template.
+        assert!(parse_template("{{ a }}").is_ok());
+    }
+
+    #[test]
+    fn test_document_template_resets_delimiters() {
+        let template = document_template().parse(b"{{=<% %>=}}<% x %>");
+        assert_eq!(
+            DocumentTemplate::with_partials(&[Partial::Tag(Tag::new("x"))]),
+            template.unwrap()
+        );
+
+        assert_eq!(
+            DocumentTemplate::with_partials(&[
+                Partial::StringLiteral("Dear patient, ".to_string()),
+                Partial::Tag(Tag::new("y")),
+            ]),
+            document_template().parse(b"Dear patient, {{ y }}").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_set_delimiters_errors() {
+        let error =
+            parse_template("{{=<% %>=}}\nDear <% name }},").unwrap_err();
+        assert_eq!("expected one of `?`, `|`, `%>`, found `}`", error.message);
+
+        let error = parse_template("{{=<% %>=}}<%#a%><%/b%>").unwrap_err();
+        assert_eq!(
+            "section `a` closed by mismatched tag `<%/b%>`",
+            error.message
+        );
+
+        assert!(parse_template("{{=<%%>=}}").is_err());
+        assert_eq!(
+            "invalid tag delimiter `<\u{a0}%`",
+            parse_template("{{=<\u{a0}% %>=}}").unwrap_err().message
         );
+        assert!(Delimiters::new("<%", "").is_err());
+        assert!(Delimiters::new("<%", "=%>").is_err());
     }
 }
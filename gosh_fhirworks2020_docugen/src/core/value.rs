@@ -0,0 +1,301 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::iter::FromIterator;
+
+/// A `Value` is the data used to fill a `DocumentTemplate`. It is either a
+/// scalar, a `List` of `Value`s or a `Map` from keys to `Value`s, mirroring
+/// the shape of JSON data.
+///
+/// The root `Value` supplied to `DocumentTemplate::saturate` is usually a
+/// `Map` whose keys are the tag identifiers used in the template.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Whether a `Section` guarded by this `Value` should be rendered.
+    ///
+    /// `Null`, `false`, `0`, the empty string and the empty list are falsy;
+    /// every other `Value` is truthy.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Number(n) => *n != 0.0,
+            Value::String(s) => !s.is_empty(),
+            Value::List(items) => !items.is_empty(),
+            Value::Map(_) => true,
+        }
+    }
+
+    /// Whether this `Value` can be interpolated into a document as text.
+    pub fn is_scalar(&self) -> bool {
+        !matches!(self, Value::List(_) | Value::Map(_))
+    }
+
+    /// Look up `key` if this `Value` is a `Map`, or the item at index `key` if
+    /// this `Value` is a `List`.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(map) => map.get(key),
+            Value::List(items) => {
+                key.parse::<usize>().ok().and_then(|i| items.get(i))
+            }
+            _ => None,
+        }
+    }
+
+    /// Build a `Value` from any serializable data, such as a deserialized FHIR
+    /// resource. Field names are kept as they are serialized.
+    pub fn from_serialize<T: Serialize>(data: &T) -> Result<Value, String> {
+        serde_json::to_value(data)
+            .map(Value::from)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Deserialize data, such as a `HumanName`, from this `Value`: the
+    /// reverse of `from_serialize`. Whole numbers are deserialized as
+    /// integers.
+    pub fn deserialize_into<T: DeserializeOwned>(&self) -> Result<T, String> {
+        serde_json::from_value(serde_json::Value::from(self))
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Value::Map(BTreeMap::new())
+    }
+}
+
+/// Scalars are displayed as they should appear in a filled document. `List`s
+/// and `Map`s have no textual form and are displayed as the empty string.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Bool(b) => write!(f, "{}", b),
+            // Whole numbers are displayed without a trailing `.0`.
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => {
+                write!(f, "{}", *n as i64)
+            }
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::List(_) | Value::Map(_) => Ok(()),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Number(n as f64)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(n: u32) -> Self {
+        Value::Number(f64::from(n))
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(o: Option<T>) -> Self {
+        o.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::List(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<BTreeMap<String, Value>> for Value {
+    fn from(map: BTreeMap<String, Value>) -> Self {
+        Value::Map(map)
+    }
+}
+
+impl From<serde_json::Value> for Value {
+    fn from(json: serde_json::Value) -> Self {
+        match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => {
+                n.as_f64().map_or(Value::Null, Value::Number)
+            }
+            serde_json::Value::String(s) => Value::String(s),
+            serde_json::Value::Array(items) => {
+                Value::List(items.into_iter().map(Value::from).collect())
+            }
+            serde_json::Value::Object(map) => Value::Map(
+                map.into_iter().map(|(k, v)| (k, Value::from(v))).collect(),
+            ),
+        }
+    }
+}
+
+impl From<&Value> for serde_json::Value {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => serde_json::Value::Bool(*b),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 2f64.powi(53) => {
+                serde_json::Value::from(*n as i64)
+            }
+            Value::Number(n) => serde_json::Number::from_f64(*n)
+                .map_or(serde_json::Value::Null, serde_json::Value::Number),
+            Value::String(s) => serde_json::Value::String(s.clone()),
+            Value::List(items) => {
+                serde_json::Value::Array(items.iter().map(Self::from).collect())
+            }
+            Value::Map(map) => serde_json::Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), Self::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Collecting `(key, value)` pairs builds a `Map`.
+impl<K: Into<String>, V: Into<Value>> FromIterator<(K, V)> for Value {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Value::Map(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_truthiness() {
+        assert!(!Value::Null.is_truthy());
+        assert!(!Value::Bool(false).is_truthy());
+        assert!(!Value::Number(0.0).is_truthy());
+        assert!(!Value::from("").is_truthy());
+        assert!(!Value::List(vec![]).is_truthy());
+
+        assert!(Value::Bool(true).is_truthy());
+        assert!(Value::Number(1.5).is_truthy());
+        assert!(Value::from("x").is_truthy());
+        assert!(Value::from(vec!["x"]).is_truthy());
+        assert!(Value::default().is_truthy());
+    }
+
+    #[test]
+    fn test_display_scalars() {
+        assert_eq!("", Value::Null.to_string());
+        assert_eq!("true", Value::Bool(true).to_string());
+        assert_eq!("18", Value::from(18u32).to_string());
+        assert_eq!("36.6", Value::Number(36.6).to_string());
+        assert_eq!("Joe", Value::from("Joe").to_string());
+    }
+
+    #[test]
+    fn test_collect_map() {
+        let value = vec![("name", "Joe")].into_iter().collect::<Value>();
+
+        assert_eq!(Some(&Value::from("Joe")), value.get("name"));
+        assert_eq!(None, value.get("family"));
+    }
+
+    #[test]
+    fn test_get_list_index() {
+        let value = Value::from(vec!["Jane", "Anne"]);
+
+        assert_eq!(Some(&Value::from("Anne")), value.get("1"));
+        assert_eq!(None, value.get("2"));
+        assert_eq!(None, value.get("given"));
+    }
+
+    #[test]
+    fn test_from_serialize() {
+        #[derive(Serialize)]
+        struct Name {
+            family: Option<String>,
+            given: Vec<String>,
+        }
+
+        let value = Value::from_serialize(&Name {
+            family: None,
+            given: vec!["Jane".to_string()],
+        })
+        .unwrap();
+
+        let expected = vec![
+            ("family", Value::Null),
+            ("given", Value::from(vec!["Jane"])),
+        ]
+        .into_iter()
+        .collect::<Value>();
+
+        assert_eq!(expected, value);
+    }
+
+    #[test]
+    fn test_deserialize_into() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Quantity {
+            value: f64,
+            count: u32,
+            unit: Option<String>,
+        }
+
+        let value = vec![
+            ("value", Value::Number(36.6)),
+            ("count", Value::from(2u32)),
+            ("unit", Value::Null),
+        ]
+        .into_iter()
+        .collect::<Value>();
+
+        assert_eq!(
+            Ok(Quantity {
+                value: 36.6,
+                count: 2,
+                unit: None,
+            }),
+            value.deserialize_into()
+        );
+        assert!(Value::from("2").deserialize_into::<Quantity>().is_err());
+    }
+}
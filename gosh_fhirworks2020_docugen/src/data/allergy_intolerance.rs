@@ -0,0 +1,236 @@
+use super::condition::Onset;
+use super::datatypes::{CodeableConcept, Identifier, Reference};
+use super::fhir_date::FHIRDateTime;
+use super::schema::{scalar_schema, Schema, Shape};
+use serde::{Deserialize, Serialize};
+
+/// Each `AllergyIntolerance` is a risk of a harmful reaction of a `Patient`
+/// to a substance, as described in FHIR v4.0.1's `AllergyIntolerance` JSON
+/// template, e.g. a penicillin allergy. The `note` element is not modelled.
+///
+/// # Reference
+///
+/// - [FHIR | AllergyIntolerance](https://www.hl7.org/fhir/allergyintolerance.html#resource)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct AllergyIntolerance {
+    pub id: Option<String>,
+    #[serde(rename = "identifier", default)]
+    pub identifiers: Vec<Identifier>,
+    /// Whether the allergy is `active`, `inactive` or `resolved`.
+    #[serde(rename = "clinicalStatus")]
+    pub clinical_status: Option<CodeableConcept>,
+    /// Whether the allergy is `confirmed`, `unconfirmed`, `refuted`, etc.
+    #[serde(rename = "verificationStatus")]
+    pub verification_status: Option<CodeableConcept>,
+    #[serde(rename = "type")]
+    pub type_: Option<AllergyIntoleranceType>,
+    #[serde(rename = "category", default)]
+    pub categories: Vec<AllergyIntoleranceCategory>,
+    pub criticality: Option<AllergyIntoleranceCriticality>,
+    /// The substance the patient reacts to, e.g. penicillin.
+    pub code: Option<CodeableConcept>,
+    pub patient: Reference,
+    pub encounter: Option<Reference>,
+    #[serde(flatten, deserialize_with = "Onset::deserialize_optional")]
+    pub onset: Option<Onset>,
+    #[serde(rename = "recordedDate")]
+    pub recorded_date: Option<FHIRDateTime>,
+    pub recorder: Option<Reference>,
+    pub asserter: Option<Reference>,
+    #[serde(rename = "lastOccurrence")]
+    pub last_occurrence: Option<FHIRDateTime>,
+    #[serde(rename = "reaction", default)]
+    pub reactions: Vec<Reaction>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum AllergyIntoleranceType {
+    Allergy,
+    Intolerance,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum AllergyIntoleranceCategory {
+    Food,
+    Medication,
+    Environment,
+    Biologic,
+}
+
+/// How bad the worst reaction to the substance could be.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum AllergyIntoleranceCriticality {
+    Low,
+    High,
+    UnableToAssess,
+}
+
+/// A `Reaction` is an adverse reaction of the patient to the substance of an
+/// `AllergyIntolerance`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Reaction {
+    /// The substance the patient was exposed to, if more specific than the
+    /// `code` of the allergy.
+    pub substance: Option<CodeableConcept>,
+    /// The signs and symptoms of the reaction, e.g. hives.
+    #[serde(rename = "manifestation", default)]
+    pub manifestations: Vec<CodeableConcept>,
+    pub description: Option<String>,
+    pub onset: Option<FHIRDateTime>,
+    pub severity: Option<ReactionSeverity>,
+    #[serde(rename = "exposureRoute")]
+    pub exposure_route: Option<CodeableConcept>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ReactionSeverity {
+    Mild,
+    Moderate,
+    Severe,
+}
+
+impl AllergyIntolerance {
+    /// Whether the allergy is known to be high risk.
+    pub fn is_critical(&self) -> bool {
+        self.criticality == Some(AllergyIntoleranceCriticality::High)
+    }
+}
+
+scalar_schema!(
+    AllergyIntoleranceType,
+    AllergyIntoleranceCategory,
+    AllergyIntoleranceCriticality,
+    ReactionSeverity
+);
+
+impl Schema for AllergyIntolerance {
+    fn shape() -> Shape {
+        let mut fields = vec![
+            ("id", Option::<String>::shape()),
+            ("identifier", Vec::<Identifier>::shape()),
+            ("clinicalStatus", Option::<CodeableConcept>::shape()),
+            ("verificationStatus", Option::<CodeableConcept>::shape()),
+            ("type", Option::<AllergyIntoleranceType>::shape()),
+            ("category", Vec::<AllergyIntoleranceCategory>::shape()),
+            (
+                "criticality",
+                Option::<AllergyIntoleranceCriticality>::shape(),
+            ),
+            ("code", Option::<CodeableConcept>::shape()),
+            ("patient", Reference::shape()),
+            ("encounter", Option::<Reference>::shape()),
+            ("recordedDate", Option::<FHIRDateTime>::shape()),
+            ("recorder", Option::<Reference>::shape()),
+            ("asserter", Option::<Reference>::shape()),
+            ("lastOccurrence", Option::<FHIRDateTime>::shape()),
+            ("reaction", Vec::<Reaction>::shape()),
+        ];
+        fields.extend(Onset::fields());
+        Shape::Record(fields)
+    }
+}
+
+impl Schema for Reaction {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("substance", Option::<CodeableConcept>::shape()),
+            ("manifestation", Vec::<CodeableConcept>::shape()),
+            ("description", Option::<String>::shape()),
+            ("onset", Option::<FHIRDateTime>::shape()),
+            ("severity", Option::<ReactionSeverity>::shape()),
+            ("exposureRoute", Option::<CodeableConcept>::shape()),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::schema::assert_shape_matches;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_deserialize() {
+        let allergy = serde_json::from_str::<AllergyIntolerance>(
+            r#"{
+                "resourceType": "AllergyIntolerance",
+                "type": "allergy",
+                "category": ["medication"],
+                "criticality": "high",
+                "code": {"text": "Penicillin"},
+                "patient": {"reference": "Patient/example"},
+                "onsetString": "childhood",
+                "reaction": [
+                    {
+                        "manifestation": [{"text": "Hives"}],
+                        "severity": "moderate"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(allergy.is_critical());
+        assert_eq!(
+            vec![AllergyIntoleranceCategory::Medication],
+            allergy.categories
+        );
+        assert_eq!(Some(Onset::String("childhood".to_string())), allergy.onset);
+        assert_eq!(
+            Some(ReactionSeverity::Moderate),
+            allergy.reactions[0].severity
+        );
+
+        let unassessed = serde_json::from_str::<AllergyIntolerance>(
+            r#"{"criticality": "unable-to-assess", "patient": {}}"#,
+        )
+        .unwrap();
+        assert!(!unassessed.is_critical());
+    }
+
+    #[test]
+    fn test_shape_matches_serialized_form() {
+        let concept = CodeableConcept {
+            text: Some("Penicillin".to_string()),
+            ..CodeableConcept::default()
+        };
+        let reference = Reference {
+            reference: Some("Patient/example".to_string()),
+            ..Reference::default()
+        };
+        let allergy = AllergyIntolerance {
+            id: Some("penicillin".to_string()),
+            identifiers: vec![Identifier::default()],
+            clinical_status: Some(concept.clone()),
+            verification_status: Some(concept.clone()),
+            type_: Some(AllergyIntoleranceType::Allergy),
+            categories: vec![AllergyIntoleranceCategory::Medication],
+            criticality: Some(AllergyIntoleranceCriticality::Low),
+            code: Some(concept.clone()),
+            patient: reference.clone(),
+            encounter: Some(reference.clone()),
+            onset: Some(Onset::DateTime("2004".parse().unwrap())),
+            recorded_date: Some("2014-10-09T14:58:00+11:00".parse().unwrap()),
+            recorder: Some(reference.clone()),
+            asserter: Some(reference),
+            last_occurrence: Some("2012-06".parse().unwrap()),
+            reactions: vec![Reaction {
+                substance: Some(concept.clone()),
+                manifestations: vec![concept.clone()],
+                description: Some("Hives on the arms".to_string()),
+                onset: Some("2012-06-12".parse().unwrap()),
+                severity: Some(ReactionSeverity::Mild),
+                exposure_route: Some(concept),
+            }],
+        };
+
+        assert_shape_matches(
+            &AllergyIntolerance::shape(),
+            &serde_json::to_value(&allergy).unwrap(),
+        );
+    }
+}
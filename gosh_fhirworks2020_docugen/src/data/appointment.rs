@@ -0,0 +1,333 @@
+use super::datatypes::{CodeableConcept, Identifier, Period, Reference};
+use super::fhir_date::{FHIRDate, FHIRDateTime};
+use super::schema::{scalar_schema, Schema, Shape};
+use serde::{Deserialize, Serialize};
+
+/// Each `Appointment` is a booking of a healthcare event for a `Patient` and
+/// the practitioners, locations and devices taking part in it, as described
+/// in FHIR v4.0.1's `Appointment` JSON template. Where an appointment takes
+/// place is one of its participants, a `Location`.
+///
+/// # Reference
+///
+/// - [FHIR | Appointment](https://www.hl7.org/fhir/appointment.html#resource)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Appointment {
+    pub id: Option<String>,
+    #[serde(rename = "identifier", default)]
+    pub identifiers: Vec<Identifier>,
+    pub status: AppointmentStatus,
+    #[serde(rename = "cancelationReason")]
+    pub cancelation_reason: Option<CodeableConcept>,
+    #[serde(rename = "serviceCategory", default)]
+    pub service_categories: Vec<CodeableConcept>,
+    #[serde(rename = "serviceType", default)]
+    pub service_types: Vec<CodeableConcept>,
+    #[serde(rename = "specialty", default)]
+    pub specialties: Vec<CodeableConcept>,
+    /// The kind of appointment, e.g. `FOLLOWUP` or `ROUTINE`.
+    #[serde(rename = "appointmentType")]
+    pub appointment_type: Option<CodeableConcept>,
+    #[serde(rename = "reasonCode", default)]
+    pub reason_codes: Vec<CodeableConcept>,
+    #[serde(rename = "reasonReference", default)]
+    pub reason_references: Vec<Reference>,
+    /// The priority of the appointment, from `1`, the highest, with `0` for
+    /// an undefined priority.
+    pub priority: Option<u32>,
+    /// The title of the appointment, e.g. as shown in a list of appointments.
+    pub description: Option<String>,
+    #[serde(rename = "supportingInformation", default)]
+    pub supporting_information: Vec<Reference>,
+    pub start: Option<FHIRDateTime>,
+    pub end: Option<FHIRDateTime>,
+    #[serde(rename = "minutesDuration")]
+    pub minutes_duration: Option<u32>,
+    #[serde(rename = "slot", default)]
+    pub slots: Vec<Reference>,
+    pub created: Option<FHIRDateTime>,
+    pub comment: Option<String>,
+    /// Instructions for the patient, e.g. to arrive 15 minutes early.
+    #[serde(rename = "patientInstruction")]
+    pub patient_instruction: Option<String>,
+    #[serde(rename = "basedOn", default)]
+    pub based_on: Vec<Reference>,
+    #[serde(rename = "participant", default)]
+    pub participants: Vec<AppointmentParticipant>,
+    /// The periods the appointment could be booked in, if it is not yet.
+    #[serde(rename = "requestedPeriod", default)]
+    pub requested_periods: Vec<Period>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum AppointmentStatus {
+    Proposed,
+    Pending,
+    Booked,
+    Arrived,
+    Fulfilled,
+    Cancelled,
+    Noshow,
+    EnteredInError,
+    CheckedIn,
+    Waitlist,
+}
+
+/// An `AppointmentParticipant` is someone or something taking part in an
+/// `Appointment`, e.g. the patient, their consultant or the clinic.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct AppointmentParticipant {
+    /// The roles of the participant, e.g. `ATND` for the attending doctor.
+    #[serde(rename = "type", default)]
+    pub types: Vec<CodeableConcept>,
+    pub actor: Option<Reference>,
+    pub required: Option<ParticipantRequired>,
+    pub status: ParticipationStatus,
+    pub period: Option<Period>,
+}
+
+/// Whether the appointment can take place without a participant.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParticipantRequired {
+    Required,
+    Optional,
+    InformationOnly,
+}
+
+/// Whether a participant has accepted to take part in an appointment.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParticipationStatus {
+    Accepted,
+    Declined,
+    Tentative,
+    NeedsAction,
+}
+
+impl Appointment {
+    /// The first participant of the appointment which refers to a resource
+    /// of type `resource_type`, e.g. `Location` for where it takes place.
+    pub fn actor(&self, resource_type: &str) -> Option<&Reference> {
+        actor(&self.participants, resource_type)
+    }
+
+    /// The id of the patient the appointment is for.
+    pub fn patient_id(&self) -> Option<&str> {
+        self.participants
+            .iter()
+            .filter_map(|participant| participant.actor.as_ref())
+            .find_map(|actor| actor.id_of("Patient"))
+    }
+
+    /// Whether the appointment is still to take place on or after `date`,
+    /// i.e. it is not yet cancelled, fulfilled or attended and it starts on
+    /// that day or later. Appointments without a start are not upcoming.
+    pub fn is_upcoming_on(&self, date: &FHIRDate) -> bool {
+        let day = |date: &FHIRDate| {
+            (
+                date.year(),
+                date.month().unwrap_or(1),
+                date.day().unwrap_or(1),
+            )
+        };
+        matches!(
+            self.status,
+            AppointmentStatus::Proposed
+                | AppointmentStatus::Pending
+                | AppointmentStatus::Booked
+        ) && self
+            .start
+            .as_ref()
+            .is_some_and(|start| day(start.date()) >= day(date))
+    }
+}
+
+/// The actor of the first of `participants` which refers to a resource of
+/// type `resource_type`, by its `type` or its URL.
+pub fn actor<'a>(
+    participants: &'a [AppointmentParticipant],
+    resource_type: &str,
+) -> Option<&'a Reference> {
+    participants
+        .iter()
+        .filter_map(|participant| participant.actor.as_ref())
+        .find(|actor| {
+            actor.type_.as_deref() == Some(resource_type)
+                || actor.id_of(resource_type).is_some()
+        })
+}
+
+scalar_schema!(AppointmentStatus, ParticipantRequired, ParticipationStatus);
+
+impl Schema for Appointment {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("id", Option::<String>::shape()),
+            ("identifier", Vec::<Identifier>::shape()),
+            ("status", AppointmentStatus::shape()),
+            ("cancelationReason", Option::<CodeableConcept>::shape()),
+            ("serviceCategory", Vec::<CodeableConcept>::shape()),
+            ("serviceType", Vec::<CodeableConcept>::shape()),
+            ("specialty", Vec::<CodeableConcept>::shape()),
+            ("appointmentType", Option::<CodeableConcept>::shape()),
+            ("reasonCode", Vec::<CodeableConcept>::shape()),
+            ("reasonReference", Vec::<Reference>::shape()),
+            ("priority", Option::<u32>::shape()),
+            ("description", Option::<String>::shape()),
+            ("supportingInformation", Vec::<Reference>::shape()),
+            ("start", Option::<FHIRDateTime>::shape()),
+            ("end", Option::<FHIRDateTime>::shape()),
+            ("minutesDuration", Option::<u32>::shape()),
+            ("slot", Vec::<Reference>::shape()),
+            ("created", Option::<FHIRDateTime>::shape()),
+            ("comment", Option::<String>::shape()),
+            ("patientInstruction", Option::<String>::shape()),
+            ("basedOn", Vec::<Reference>::shape()),
+            ("participant", Vec::<AppointmentParticipant>::shape()),
+            ("requestedPeriod", Vec::<Period>::shape()),
+        ])
+    }
+}
+
+impl Schema for AppointmentParticipant {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("type", Vec::<CodeableConcept>::shape()),
+            ("actor", Option::<Reference>::shape()),
+            ("required", Option::<ParticipantRequired>::shape()),
+            ("status", ParticipationStatus::shape()),
+            ("period", Option::<Period>::shape()),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::schema::assert_shape_matches;
+    use pretty_assertions::assert_eq;
+
+    const RAW: &str = r#"{
+        "resourceType": "Appointment",
+        "id": "example",
+        "status": "booked",
+        "description": "Discussion on the results of your recent MRI",
+        "start": "2013-12-10T09:00:00Z",
+        "end": "2013-12-10T11:00:00Z",
+        "participant": [
+            {
+                "actor": {
+                    "reference": "Patient/example",
+                    "display": "Peter James Chalmers"
+                },
+                "required": "required",
+                "status": "accepted"
+            },
+            {
+                "type": [{"coding": [{"code": "ATND"}]}],
+                "actor": {"display": "Dr Adam Careful"},
+                "status": "accepted"
+            },
+            {
+                "actor": {
+                    "reference": "Location/1",
+                    "display": "South Wing, second floor"
+                },
+                "required": "information-only",
+                "status": "accepted"
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_deserialize() {
+        let appointment = serde_json::from_str::<Appointment>(RAW).unwrap();
+
+        assert_eq!(AppointmentStatus::Booked, appointment.status);
+        assert_eq!(Some("example"), appointment.patient_id());
+        assert_eq!(
+            Some("South Wing, second floor"),
+            appointment
+                .actor("Location")
+                .and_then(|location| location.display.as_deref())
+        );
+        assert_eq!(None, appointment.actor("Device"));
+        assert_eq!(
+            Some(ParticipantRequired::InformationOnly),
+            appointment.participants[2].required
+        );
+
+        assert!(serde_json::from_str::<Appointment>(
+            r#"{"status": "booked", "participant": [{"actor": {}}]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_is_upcoming_on() {
+        let date = |s: &str| s.parse::<FHIRDate>().unwrap();
+        let mut appointment = serde_json::from_str::<Appointment>(RAW).unwrap();
+
+        assert!(appointment.is_upcoming_on(&date("2013-12-09")));
+        assert!(appointment.is_upcoming_on(&date("2013-12-10")));
+        assert!(!appointment.is_upcoming_on(&date("2013-12-11")));
+
+        appointment.status = AppointmentStatus::Cancelled;
+        assert!(!appointment.is_upcoming_on(&date("2013-12-09")));
+
+        appointment.status = AppointmentStatus::Proposed;
+        appointment.start = None;
+        assert!(!appointment.is_upcoming_on(&date("2013-12-09")));
+    }
+
+    #[test]
+    fn test_shape_matches_serialized_form() {
+        let concept = CodeableConcept {
+            text: Some("Follow-up".to_string()),
+            ..CodeableConcept::default()
+        };
+        let reference = Reference {
+            reference: Some("Patient/example".to_string()),
+            ..Reference::default()
+        };
+        let instant = "2013-12-10T09:00:00Z".parse::<FHIRDateTime>().unwrap();
+        let appointment = Appointment {
+            id: Some("example".to_string()),
+            identifiers: vec![Identifier::default()],
+            status: AppointmentStatus::Booked,
+            cancelation_reason: Some(concept.clone()),
+            service_categories: vec![concept.clone()],
+            service_types: vec![concept.clone()],
+            specialties: vec![concept.clone()],
+            appointment_type: Some(concept.clone()),
+            reason_codes: vec![concept.clone()],
+            reason_references: vec![reference.clone()],
+            priority: Some(5),
+            description: Some("Follow-up".to_string()),
+            supporting_information: vec![reference.clone()],
+            start: Some(instant.clone()),
+            end: Some(instant.clone()),
+            minutes_duration: Some(15),
+            slots: vec![reference.clone()],
+            created: Some(instant),
+            comment: Some("Bring your inhaler".to_string()),
+            patient_instruction: Some("Arrive early".to_string()),
+            based_on: vec![reference.clone()],
+            participants: vec![AppointmentParticipant {
+                types: vec![concept],
+                actor: Some(reference),
+                required: Some(ParticipantRequired::Required),
+                status: ParticipationStatus::Accepted,
+                period: Some(Period::default()),
+            }],
+            requested_periods: vec![Period::default()],
+        };
+
+        assert_shape_matches(
+            &Appointment::shape(),
+            &serde_json::to_value(&appointment).unwrap(),
+        );
+    }
+}
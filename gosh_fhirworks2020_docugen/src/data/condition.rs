@@ -0,0 +1,198 @@
+use super::datatypes::{
+    choice_type, CodeableConcept, Identifier, Period, Quantity, Range,
+    Reference,
+};
+use super::fhir_date::FHIRDateTime;
+use super::schema::{Schema, Shape};
+use serde::{Deserialize, Serialize};
+
+/// System of the codes of the `clinicalStatus` of `Condition`s.
+pub const CLINICAL_STATUS_SYSTEM: &str =
+    "http://terminology.hl7.org/CodeSystem/condition-clinical";
+
+/// Each `Condition` is a problem, diagnosis or other clinical concern of a
+/// `Patient`, as described in FHIR v4.0.1's `Condition` JSON template, e.g.
+/// an entry of their problem list. The `stage`, `evidence` and `note`
+/// elements are not modelled.
+///
+/// # Reference
+///
+/// - [FHIR | Condition](https://www.hl7.org/fhir/condition.html#resource)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Condition {
+    pub id: Option<String>,
+    #[serde(rename = "identifier", default)]
+    pub identifiers: Vec<Identifier>,
+    /// Whether the condition is `active`, `recurrence`, `relapse`,
+    /// `inactive`, `remission` or `resolved`.
+    #[serde(rename = "clinicalStatus")]
+    pub clinical_status: Option<CodeableConcept>,
+    /// Whether the condition is `confirmed`, `provisional`, `refuted`, etc.
+    #[serde(rename = "verificationStatus")]
+    pub verification_status: Option<CodeableConcept>,
+    /// The kinds of condition, e.g. `problem-list-item`.
+    #[serde(rename = "category", default)]
+    pub categories: Vec<CodeableConcept>,
+    pub severity: Option<CodeableConcept>,
+    /// What the condition is, e.g. the SNOMED CT code of asthma.
+    pub code: Option<CodeableConcept>,
+    #[serde(rename = "bodySite", default)]
+    pub body_sites: Vec<CodeableConcept>,
+    pub subject: Reference,
+    pub encounter: Option<Reference>,
+    #[serde(flatten, deserialize_with = "Onset::deserialize_optional")]
+    pub onset: Option<Onset>,
+    #[serde(flatten, deserialize_with = "Abatement::deserialize_optional")]
+    pub abatement: Option<Abatement>,
+    #[serde(rename = "recordedDate")]
+    pub recorded_date: Option<FHIRDateTime>,
+    pub recorder: Option<Reference>,
+    pub asserter: Option<Reference>,
+}
+
+choice_type! {
+    /// When a `Condition` or an `AllergyIntolerance` started, or the age of
+    /// the patient then.
+    pub enum Onset {
+        "onsetDateTime" => DateTime(FHIRDateTime),
+        "onsetAge" => Age(Quantity),
+        "onsetPeriod" => Period(Period),
+        "onsetRange" => Range(Range),
+        "onsetString" => String(String),
+    }
+}
+
+choice_type! {
+    /// When a `Condition` resolved or went into remission.
+    pub enum Abatement {
+        "abatementDateTime" => DateTime(FHIRDateTime),
+        "abatementAge" => Age(Quantity),
+        "abatementPeriod" => Period(Period),
+        "abatementRange" => Range(Range),
+        "abatementString" => String(String),
+    }
+}
+
+impl Condition {
+    /// Whether the condition is `active`, or has come back.
+    pub fn is_active(&self) -> bool {
+        self.clinical_status
+            .as_ref()
+            .and_then(|status| status.coding(CLINICAL_STATUS_SYSTEM))
+            .and_then(|coding| coding.code.as_deref())
+            .is_some_and(|code| {
+                matches!(code, "active" | "recurrence" | "relapse")
+            })
+    }
+}
+
+impl Schema for Condition {
+    fn shape() -> Shape {
+        let mut fields = vec![
+            ("id", Option::<String>::shape()),
+            ("identifier", Vec::<Identifier>::shape()),
+            ("clinicalStatus", Option::<CodeableConcept>::shape()),
+            ("verificationStatus", Option::<CodeableConcept>::shape()),
+            ("category", Vec::<CodeableConcept>::shape()),
+            ("severity", Option::<CodeableConcept>::shape()),
+            ("code", Option::<CodeableConcept>::shape()),
+            ("bodySite", Vec::<CodeableConcept>::shape()),
+            ("subject", Reference::shape()),
+            ("encounter", Option::<Reference>::shape()),
+            ("recordedDate", Option::<FHIRDateTime>::shape()),
+            ("recorder", Option::<Reference>::shape()),
+            ("asserter", Option::<Reference>::shape()),
+        ];
+        fields.extend(Onset::fields());
+        fields.extend(Abatement::fields());
+        Shape::Record(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::schema::assert_shape_matches;
+    use pretty_assertions::assert_eq;
+
+    const RAW: &str = r#"{
+        "resourceType": "Condition",
+        "id": "asthma",
+        "clinicalStatus": {
+            "coding": [{
+                "system": "http://terminology.hl7.org/CodeSystem/condition-clinical",
+                "code": "active"
+            }]
+        },
+        "category": [{"coding": [{"code": "problem-list-item"}]}],
+        "code": {"text": "Asthma"},
+        "subject": {"reference": "Patient/example"},
+        "onsetAge": {"value": 4, "unit": "years"},
+        "recordedDate": "2016-08-10"
+    }"#;
+
+    #[test]
+    fn test_deserialize() {
+        let condition = serde_json::from_str::<Condition>(RAW).unwrap();
+
+        assert!(condition.is_active());
+        assert_eq!(
+            Some(Onset::Age(Quantity {
+                value: Some(4.0),
+                unit: Some("years".to_string()),
+                ..Quantity::default()
+            })),
+            condition.onset
+        );
+        assert_eq!(None, condition.abatement);
+
+        let resolved = serde_json::from_str::<Condition>(
+            r#"{
+                "clinicalStatus": {"coding": [{"code": "resolved"}]},
+                "subject": {"display": "Jane Smith"},
+                "abatementString": "around April 2019"
+            }"#,
+        )
+        .unwrap();
+        assert!(!resolved.is_active());
+        assert_eq!(
+            Some(Abatement::String("around April 2019".to_string())),
+            resolved.abatement
+        );
+        assert!(serde_json::from_str::<Condition>(r#"{"id": "1"}"#).is_err());
+    }
+
+    #[test]
+    fn test_shape_matches_serialized_form() {
+        let concept = CodeableConcept {
+            text: Some("Asthma".to_string()),
+            ..CodeableConcept::default()
+        };
+        let reference = Reference {
+            reference: Some("Patient/example".to_string()),
+            ..Reference::default()
+        };
+        let condition = Condition {
+            id: Some("asthma".to_string()),
+            identifiers: vec![Identifier::default()],
+            clinical_status: Some(concept.clone()),
+            verification_status: Some(concept.clone()),
+            categories: vec![concept.clone()],
+            severity: Some(concept.clone()),
+            code: Some(concept.clone()),
+            body_sites: vec![concept],
+            subject: reference.clone(),
+            encounter: Some(reference.clone()),
+            onset: Some(Onset::DateTime("2016-08-10".parse().unwrap())),
+            abatement: Some(Abatement::Period(Period::default())),
+            recorded_date: Some("2016-08-10".parse().unwrap()),
+            recorder: Some(reference.clone()),
+            asserter: Some(reference),
+        };
+
+        assert_shape_matches(
+            &Condition::shape(),
+            &serde_json::to_value(&condition).unwrap(),
+        );
+    }
+}
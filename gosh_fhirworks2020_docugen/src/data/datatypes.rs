@@ -0,0 +1,860 @@
+use super::fhir_date::{FHIRDate, FHIRDateTime};
+use super::schema::{scalar_schema, Schema, Shape};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Declare the enum of the types a choice element `name[x]` of a resource may
+/// have, such as `deceased[x]`, which is serialized as the one field named
+/// after the chosen type, e.g. `deceasedBoolean`. Resources
+/// `#[serde(flatten)]` their choice elements, deserializing the optional ones
+/// with `deserialize_optional`.
+///
+/// All the fields of the element are serialized, the ones not chosen as
+/// `null`, so that templates can test which one is set. Deserializing more or
+/// less than one of them fails.
+///
+/// # Reference
+///
+/// - [Choice of Data Types](https://www.hl7.org/fhir/formats.html#choice)
+macro_rules! choice_type {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($field:literal => $variant:ident($ty:ty),)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, PartialEq, Clone)]
+        pub enum $name {
+            $($variant($ty),)+
+        }
+
+        impl $name {
+            /// The fields of the element, as they are serialized.
+            pub fn fields() -> Vec<(&'static str, $crate::data::schema::Shape)> {
+                vec![$((
+                    $field,
+                    <$ty as $crate::data::schema::Schema>::shape(),
+                ),)+]
+            }
+
+            /// Deserialize the element, or `None` if none of its fields is
+            /// set. Flattening an `Option` of the element would deserialize
+            /// `None` from invalid fields as well, so optional elements are
+            /// `#[serde(flatten, deserialize_with = "..")]` with this instead.
+            pub fn deserialize_optional<'de, D>(
+                deserializer: D,
+            ) -> Result<Option<Self>, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                #[derive(serde::Deserialize)]
+                #[allow(non_snake_case)]
+                struct Fields {
+                    $(
+                        #[serde(rename = $field, default)]
+                        $variant: Option<$ty>,
+                    )+
+                }
+
+                let fields = Fields::deserialize(deserializer)?;
+                let mut chosen = Vec::new();
+                $(
+                    if let Some(value) = fields.$variant {
+                        chosen.push($name::$variant(value));
+                    }
+                )+
+                match chosen.len() {
+                    0 => Ok(None),
+                    1 => Ok(chosen.pop()),
+                    _ => Err(serde::de::Error::custom(format!(
+                        "expected at most one of {}",
+                        Self::field_list()
+                    ))),
+                }
+            }
+
+            fn field_list() -> String {
+                [$(concat!("`", $field, "`")),+].join(", ")
+            }
+        }
+
+        impl $crate::data::schema::Schema for $name {
+            fn shape() -> $crate::data::schema::Shape {
+                $crate::data::schema::Shape::Record(Self::fields())
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                use serde::ser::SerializeMap;
+
+                let mut map = serializer.serialize_map(None)?;
+                $(
+                    match self {
+                        $name::$variant(value) => {
+                            map.serialize_entry($field, value)?
+                        }
+                        #[allow(unreachable_patterns)]
+                        _ => map.serialize_entry($field, &None::<$ty>)?,
+                    }
+                )+
+                map.end()
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                Self::deserialize_optional(deserializer)?.ok_or_else(|| {
+                    serde::de::Error::custom(format!(
+                        "expected one of {}",
+                        Self::field_list()
+                    ))
+                })
+            }
+        }
+    };
+}
+
+pub(crate) use choice_type;
+
+/// A `Coding` is a code defined by a terminology system, e.g. the SNOMED CT
+/// code `38341003` of hypertension.
+///
+/// # Reference
+///
+/// - [Coding](https://www.hl7.org/fhir/datatypes.html#Coding)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Coding {
+    pub system: Option<String>,
+    pub version: Option<String>,
+    pub code: Option<String>,
+    pub display: Option<String>,
+    #[serde(rename = "userSelected")]
+    pub user_selected: Option<bool>,
+}
+
+/// A `CodeableConcept` is a concept given by any number of `Coding`s and/or
+/// by text, e.g. a marital status.
+///
+/// # Reference
+///
+/// - [CodeableConcept](https://www.hl7.org/fhir/datatypes.html#CodeableConcept)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct CodeableConcept {
+    #[serde(rename = "coding", default)]
+    pub codings: Vec<Coding>,
+    pub text: Option<String>,
+}
+
+impl CodeableConcept {
+    /// The `Coding` of the concept in the terminology `system`, if any.
+    pub fn coding(&self, system: &str) -> Option<&Coding> {
+        self.codings
+            .iter()
+            .find(|coding| coding.system.as_deref() == Some(system))
+    }
+}
+
+/// An `Identifier` identifies a resource within a system of identifiers, e.g.
+/// the NHS number of a patient.
+///
+/// # Reference
+///
+/// - [Identifier](https://www.hl7.org/fhir/datatypes.html#Identifier)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Identifier {
+    #[serde(rename = "use")]
+    pub use_: Option<IdentifierUse>,
+    #[serde(rename = "type")]
+    pub type_: Option<CodeableConcept>,
+    pub system: Option<String>,
+    pub value: Option<String>,
+    pub period: Option<Period>,
+    /// The organization which issued the identifier.
+    pub assigner: Option<Box<Reference>>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum IdentifierUse {
+    Usual,
+    Official,
+    Temp,
+    Secondary,
+    Old,
+}
+
+/// A `Reference` refers to another resource, by its URL and/or by its
+/// identifier, e.g. the general practitioner of a patient.
+///
+/// # Reference
+///
+/// - [Reference](https://www.hl7.org/fhir/references.html#Reference)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Reference {
+    /// A relative or absolute URL, e.g. `Practitioner/123`.
+    pub reference: Option<String>,
+    /// The type of the resource referred to, e.g. `Practitioner`.
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub identifier: Option<Identifier>,
+    pub display: Option<String>,
+}
+
+impl Reference {
+    /// The id of the resource of type `resource_type` referred to by the
+    /// relative or absolute URL of the reference, e.g. `123` for
+    /// `Patient/123` or `https://example.org/fhir/Patient/123/_history/2`.
+    pub fn id_of(&self, resource_type: &str) -> Option<&str> {
+        let url = self.reference.as_deref()?;
+        let url = match url.find("/_history/") {
+            Some(history) => &url[..history],
+            None => url,
+        };
+        let (path, id) = url.rsplit_once('/')?;
+        let type_ = path.rsplit('/').next()?;
+        if type_ == resource_type && !id.is_empty() {
+            Some(id)
+        } else {
+            None
+        }
+    }
+}
+
+/// A `Period` is a time range between two optional `FHIRDateTime`s. A period
+/// without an end is ongoing.
+///
+/// # Reference
+///
+/// - [Period](https://www.hl7.org/fhir/datatypes.html#Period)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Period {
+    pub start: Option<FHIRDateTime>,
+    pub end: Option<FHIRDateTime>,
+}
+
+impl Period {
+    /// Whether `date` is within the period. Partial dates cover all of their
+    /// days, e.g. a period ending in `2020` contains `2020-12-31`, and a
+    /// partial `date` stands for its first day.
+    pub fn contains(&self, date: &FHIRDate) -> bool {
+        let day = |date: &FHIRDate, last: bool| {
+            let (month, day) = if last { (12, 31) } else { (1, 1) };
+            (
+                date.year(),
+                date.month().unwrap_or(month),
+                date.day().unwrap_or(day),
+            )
+        };
+        let on = day(date, false);
+
+        self.start
+            .as_ref()
+            .is_none_or(|start| day(start.date(), false) <= on)
+            && self
+                .end
+                .as_ref()
+                .is_none_or(|end| on <= day(end.date(), true))
+    }
+}
+
+/// A `ContactPoint` is a way of contacting someone, e.g. a phone number or an
+/// email address.
+///
+/// # Reference
+///
+/// - [ContactPoint](https://www.hl7.org/fhir/datatypes.html#ContactPoint)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct ContactPoint {
+    pub system: Option<ContactPointSystem>,
+    pub value: Option<String>,
+    #[serde(rename = "use")]
+    pub use_: Option<ContactPointUse>,
+    /// The order of preference of the contact point, `1` being the most
+    /// preferred.
+    pub rank: Option<u32>,
+    pub period: Option<Period>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ContactPointSystem {
+    Phone,
+    Fax,
+    Email,
+    Pager,
+    Url,
+    Sms,
+    Other,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ContactPointUse {
+    Home,
+    Work,
+    Temp,
+    Old,
+    Mobile,
+}
+
+/// An `Address` is a postal or physical address.
+///
+/// # Reference
+///
+/// - [Address](https://www.hl7.org/fhir/datatypes.html#Address)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Address {
+    #[serde(rename = "use")]
+    pub use_: Option<AddressUse>,
+    #[serde(rename = "type")]
+    pub type_: Option<AddressType>,
+    /// The whole address, as it should be displayed.
+    pub text: Option<String>,
+    /// The street name, number, flat, etc. of the address, one per line.
+    #[serde(rename = "line", default)]
+    pub lines: Vec<String>,
+    pub city: Option<String>,
+    pub district: Option<String>,
+    pub state: Option<String>,
+    #[serde(rename = "postalCode")]
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    pub period: Option<Period>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressUse {
+    Home,
+    Work,
+    Temp,
+    Old,
+    Billing,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressType {
+    Postal,
+    Physical,
+    Both,
+}
+
+/// An `Attachment` is content in some other format, e.g. a photo, either
+/// inline in base64 or at a URL.
+///
+/// # Reference
+///
+/// - [Attachment](https://www.hl7.org/fhir/datatypes.html#Attachment)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Attachment {
+    /// The MIME type of the content, e.g. `image/png`.
+    #[serde(rename = "contentType")]
+    pub content_type: Option<String>,
+    pub language: Option<String>,
+    /// The content, in base64.
+    pub data: Option<String>,
+    pub url: Option<String>,
+    pub size: Option<u32>,
+    /// The SHA-1 hash of the content, in base64.
+    pub hash: Option<String>,
+    pub title: Option<String>,
+    pub creation: Option<FHIRDateTime>,
+}
+
+/// A `Quantity` is a measured amount, e.g. `5.4 mmol/L`.
+///
+/// # Reference
+///
+/// - [Quantity](https://www.hl7.org/fhir/datatypes.html#Quantity)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Quantity {
+    pub value: Option<f64>,
+    /// How the actual amount relates to `value`, e.g. `<` if the amount was
+    /// below what could be measured.
+    pub comparator: Option<QuantityComparator>,
+    /// The unit, as it should be displayed.
+    pub unit: Option<String>,
+    /// The system coding the unit, usually UCUM.
+    pub system: Option<String>,
+    pub code: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+pub enum QuantityComparator {
+    #[serde(rename = "<")]
+    LessThan,
+    #[serde(rename = "<=")]
+    LessOrEqual,
+    #[serde(rename = ">=")]
+    GreaterOrEqual,
+    #[serde(rename = ">")]
+    GreaterThan,
+}
+
+impl fmt::Display for QuantityComparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let comparator = match self {
+            QuantityComparator::LessThan => "<",
+            QuantityComparator::LessOrEqual => "<=",
+            QuantityComparator::GreaterOrEqual => ">=",
+            QuantityComparator::GreaterThan => ">",
+        };
+        write!(f, "{}", comparator)
+    }
+}
+
+/// Writes the comparator, value and unit of the quantity, e.g. `<5.4
+/// mmol/L`, falling back to the code of the unit if it has no display form.
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(comparator) = &self.comparator {
+            write!(f, "{}", comparator)?;
+        }
+        if let Some(value) = self.value {
+            write!(f, "{}", value)?;
+        }
+        match self.unit.as_ref().or(self.code.as_ref()) {
+            Some(unit) if self.value.is_some() => write!(f, " {}", unit),
+            Some(unit) => write!(f, "{}", unit),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A `Range` is a set of quantities between two optional bounds, e.g. a
+/// reference range of `3.1` to `6.2 mmol/L`.
+///
+/// # Reference
+///
+/// - [Range](https://www.hl7.org/fhir/datatypes.html#Range)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Range {
+    pub low: Option<Quantity>,
+    pub high: Option<Quantity>,
+}
+
+/// A `Ratio` is the ratio of two quantities, e.g. a titre of `1:128`.
+///
+/// # Reference
+///
+/// - [Ratio](https://www.hl7.org/fhir/datatypes.html#Ratio)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Ratio {
+    pub numerator: Option<Quantity>,
+    pub denominator: Option<Quantity>,
+}
+
+/// The gender of a person for administrative purposes.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum AdministrativeGender {
+    Male,
+    Female,
+    Other,
+    Unknown,
+}
+
+scalar_schema!(
+    QuantityComparator,
+    AdministrativeGender,
+    IdentifierUse,
+    ContactPointSystem,
+    ContactPointUse,
+    AddressUse,
+    AddressType
+);
+
+impl Schema for Coding {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("system", Option::<String>::shape()),
+            ("version", Option::<String>::shape()),
+            ("code", Option::<String>::shape()),
+            ("display", Option::<String>::shape()),
+            ("userSelected", Option::<bool>::shape()),
+        ])
+    }
+}
+
+impl Schema for CodeableConcept {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("coding", Vec::<Coding>::shape()),
+            ("text", Option::<String>::shape()),
+        ])
+    }
+}
+
+impl Schema for Identifier {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("use", Option::<IdentifierUse>::shape()),
+            ("type", Option::<CodeableConcept>::shape()),
+            ("system", Option::<String>::shape()),
+            ("value", Option::<String>::shape()),
+            ("period", Option::<Period>::shape()),
+            ("assigner", assigner_shape()),
+        ])
+    }
+}
+
+/// The shape of the `assigner` of an `Identifier`. The identifier of the
+/// assigner is not checked, which ends the recursion between the shapes of
+/// `Identifier` and `Reference`.
+fn assigner_shape() -> Shape {
+    Shape::Record(vec![
+        ("reference", Option::<String>::shape()),
+        ("type", Option::<String>::shape()),
+        ("identifier", Shape::Any),
+        ("display", Option::<String>::shape()),
+    ])
+}
+
+impl Schema for Reference {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("reference", Option::<String>::shape()),
+            ("type", Option::<String>::shape()),
+            ("identifier", Option::<Identifier>::shape()),
+            ("display", Option::<String>::shape()),
+        ])
+    }
+}
+
+impl Schema for Period {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("start", Option::<FHIRDateTime>::shape()),
+            ("end", Option::<FHIRDateTime>::shape()),
+        ])
+    }
+}
+
+impl Schema for ContactPoint {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("system", Option::<ContactPointSystem>::shape()),
+            ("value", Option::<String>::shape()),
+            ("use", Option::<ContactPointUse>::shape()),
+            ("rank", Option::<u32>::shape()),
+            ("period", Option::<Period>::shape()),
+        ])
+    }
+}
+
+impl Schema for Address {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("use", Option::<AddressUse>::shape()),
+            ("type", Option::<AddressType>::shape()),
+            ("text", Option::<String>::shape()),
+            ("line", Vec::<String>::shape()),
+            ("city", Option::<String>::shape()),
+            ("district", Option::<String>::shape()),
+            ("state", Option::<String>::shape()),
+            ("postalCode", Option::<String>::shape()),
+            ("country", Option::<String>::shape()),
+            ("period", Option::<Period>::shape()),
+        ])
+    }
+}
+
+impl Schema for Attachment {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("contentType", Option::<String>::shape()),
+            ("language", Option::<String>::shape()),
+            ("data", Option::<String>::shape()),
+            ("url", Option::<String>::shape()),
+            ("size", Option::<u32>::shape()),
+            ("hash", Option::<String>::shape()),
+            ("title", Option::<String>::shape()),
+            ("creation", Option::<FHIRDateTime>::shape()),
+        ])
+    }
+}
+
+impl Schema for Quantity {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("value", Option::<f64>::shape()),
+            ("comparator", Option::<QuantityComparator>::shape()),
+            ("unit", Option::<String>::shape()),
+            ("system", Option::<String>::shape()),
+            ("code", Option::<String>::shape()),
+        ])
+    }
+}
+
+impl Schema for Range {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("low", Option::<Quantity>::shape()),
+            ("high", Option::<Quantity>::shape()),
+        ])
+    }
+}
+
+impl Schema for Ratio {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("numerator", Option::<Quantity>::shape()),
+            ("denominator", Option::<Quantity>::shape()),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::schema::assert_shape_matches;
+    use pretty_assertions::assert_eq;
+
+    choice_type! {
+        pub enum Multiple {
+            "multipleBoolean" => Boolean(bool),
+            "multipleInteger" => Integer(u32),
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Resource {
+        id: String,
+        #[serde(flatten, deserialize_with = "Multiple::deserialize_optional")]
+        multiple: Option<Multiple>,
+    }
+
+    #[test]
+    fn test_choice_type() {
+        let resource = serde_json::from_str::<Resource>(
+            r#"{"id": "1", "multipleInteger": 2}"#,
+        )
+        .unwrap();
+        assert_eq!(Some(Multiple::Integer(2)), resource.multiple);
+        assert_eq!(
+            serde_json::json!({
+                "id": "1",
+                "multipleBoolean": null,
+                "multipleInteger": 2
+            }),
+            serde_json::to_value(&resource).unwrap()
+        );
+        assert_shape_matches(
+            &Shape::Record(
+                vec![("id", Shape::Scalar)]
+                    .into_iter()
+                    .chain(Multiple::fields())
+                    .collect(),
+            ),
+            &serde_json::to_value(&resource).unwrap(),
+        );
+
+        let without = serde_json::from_str::<Resource>(r#"{"id": "1"}"#);
+        assert_eq!(None, without.unwrap().multiple);
+        assert!(serde_json::from_str::<Resource>(
+            r#"{"id": "1", "multipleBoolean": true, "multipleInteger": 2}"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<Resource>(
+            r#"{"id": "1", "multipleInteger": "two"}"#
+        )
+        .is_err());
+        assert!(
+            serde_json::from_value::<Multiple>(serde_json::json!({})).is_err()
+        );
+        assert!(serde_json::from_value::<Multiple>(serde_json::json!({
+            "multipleBoolean": true,
+            "multipleInteger": 2
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_reference_id_of() {
+        let reference = |url: &str| Reference {
+            reference: Some(url.to_string()),
+            ..Reference::default()
+        };
+
+        assert_eq!(Some("123"), reference("Patient/123").id_of("Patient"));
+        assert_eq!(
+            Some("123"),
+            reference("https://example.org/fhir/Patient/123/_history/2")
+                .id_of("Patient")
+        );
+        assert_eq!(None, reference("Location/1").id_of("Patient"));
+        assert_eq!(None, reference("Patient/").id_of("Patient"));
+        assert_eq!(None, Reference::default().id_of("Patient"));
+    }
+
+    #[test]
+    fn test_period_contains() {
+        let period = |start: Option<&str>, end: Option<&str>| Period {
+            start: start.map(|s| s.parse().unwrap()),
+            end: end.map(|s| s.parse().unwrap()),
+        };
+        let date = |s: &str| s.parse::<FHIRDate>().unwrap();
+
+        let year = period(Some("2012"), Some("2012"));
+        assert!(year.contains(&date("2012-01-01")));
+        assert!(year.contains(&date("2012-12-31")));
+        assert!(!year.contains(&date("2013-01-01")));
+
+        let ongoing = period(Some("2012-03-04T09:00:00Z"), None);
+        assert!(ongoing.contains(&date("2012-03-04")));
+        assert!(ongoing.contains(&date("2100")));
+        assert!(!ongoing.contains(&date("2012-03-03")));
+
+        let ended = period(None, Some("2012-03"));
+        assert!(ended.contains(&date("1900")));
+        assert!(!ended.contains(&date("2012-04-01")));
+        assert!(Period::default().contains(&date("2012")));
+    }
+
+    #[test]
+    fn test_display_quantity() {
+        let quantity = Quantity {
+            value: Some(5.4),
+            comparator: Some(QuantityComparator::LessThan),
+            unit: Some("mmol/L".to_string()),
+            system: Some("http://unitsofmeasure.org".to_string()),
+            code: Some("mmol/L".to_string()),
+        };
+        assert_eq!("<5.4 mmol/L", quantity.to_string());
+
+        let count = Quantity {
+            value: Some(3.0),
+            code: Some("{beats}".to_string()),
+            ..Quantity::default()
+        };
+        assert_eq!("3 {beats}", count.to_string());
+        assert_eq!(
+            "%",
+            Quantity {
+                unit: Some("%".to_string()),
+                ..Quantity::default()
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_codeable_concept() {
+        let concept = serde_json::from_str::<CodeableConcept>(
+            r#"{
+                "coding": [
+                    {
+                        "system": "http://snomed.info/sct",
+                        "code": "38341003",
+                        "display": "Hypertension"
+                    }
+                ],
+                "text": "High blood pressure"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some("38341003"),
+            concept
+                .coding("http://snomed.info/sct")
+                .and_then(|coding| coding.code.as_deref())
+        );
+        assert_eq!(None, concept.coding("http://loinc.org"));
+    }
+
+    #[test]
+    fn test_shape_matches_serialized_form() {
+        let period = Period {
+            start: Some("2012-03-04".parse().unwrap()),
+            end: Some("2020-03-04T09:00:00Z".parse().unwrap()),
+        };
+        let identifier = Identifier {
+            use_: Some(IdentifierUse::Official),
+            type_: Some(CodeableConcept {
+                codings: vec![Coding {
+                    system: Some("http://terminology.hl7.org".to_string()),
+                    version: Some("1".to_string()),
+                    code: Some("MR".to_string()),
+                    display: Some("Medical record number".to_string()),
+                    user_selected: Some(false),
+                }],
+                text: Some("MRN".to_string()),
+            }),
+            system: Some("https://fhir.nhs.uk/Id/nhs-number".to_string()),
+            value: Some("9434765919".to_string()),
+            period: Some(period.clone()),
+            assigner: Some(Box::new(Reference {
+                reference: Some("Organization/1".to_string()),
+                type_: Some("Organization".to_string()),
+                identifier: None,
+                display: Some("NHS".to_string()),
+            })),
+        };
+        let reference = Reference {
+            reference: Some("Practitioner/1".to_string()),
+            type_: Some("Practitioner".to_string()),
+            identifier: Some(identifier.clone()),
+            display: Some("Dr Who".to_string()),
+        };
+        let contact_point = ContactPoint {
+            system: Some(ContactPointSystem::Phone),
+            value: Some("020 7405 9200".to_string()),
+            use_: Some(ContactPointUse::Work),
+            rank: Some(1),
+            period: Some(period.clone()),
+        };
+        let address = Address {
+            use_: Some(AddressUse::Home),
+            type_: Some(AddressType::Both),
+            text: Some("Great Ormond Street, London WC1N 3JH".to_string()),
+            lines: vec!["Great Ormond Street".to_string()],
+            city: Some("London".to_string()),
+            district: Some("Camden".to_string()),
+            state: Some("England".to_string()),
+            postal_code: Some("WC1N 3JH".to_string()),
+            country: Some("UK".to_string()),
+            period: Some(period),
+        };
+        let attachment = Attachment {
+            content_type: Some("image/png".to_string()),
+            language: Some("en".to_string()),
+            data: Some("iVBORw0KGgo=".to_string()),
+            url: Some("https://example.org/photo.png".to_string()),
+            size: Some(8),
+            hash: Some("2jmj7l5rSw0yVb/vlWAYkK/YBwk=".to_string()),
+            title: Some("Photo".to_string()),
+            creation: Some("2020-03-04".parse().unwrap()),
+        };
+
+        let check = |shape: Shape, data: serde_json::Value| {
+            assert_shape_matches(&shape, &data)
+        };
+        check(
+            Identifier::shape(),
+            serde_json::to_value(&identifier).unwrap(),
+        );
+        check(
+            Reference::shape(),
+            serde_json::to_value(&reference).unwrap(),
+        );
+        check(
+            ContactPoint::shape(),
+            serde_json::to_value(&contact_point).unwrap(),
+        );
+        check(Address::shape(), serde_json::to_value(&address).unwrap());
+        check(
+            Attachment::shape(),
+            serde_json::to_value(&attachment).unwrap(),
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use super::datatypes::{
+    choice_type, CodeableConcept, Period, Quantity, Range, Ratio,
+};
+use super::fhir_date::FHIRDateTime;
+use super::schema::{Schema, Shape};
+use serde::{Deserialize, Serialize};
+
+/// A `Dosage` is how a medication is, or should be, taken, e.g. "one tablet
+/// twice a day with food".
+///
+/// # Reference
+///
+/// - [Dosage](https://www.hl7.org/fhir/dosage.html#Dosage)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Dosage {
+    /// The order in which the dosages of a medication are followed.
+    pub sequence: Option<i64>,
+    /// The whole dosage, as it should be displayed.
+    pub text: Option<String>,
+    /// Instructions for the patient, e.g. "with food".
+    #[serde(rename = "additionalInstruction", default)]
+    pub additional_instructions: Vec<CodeableConcept>,
+    #[serde(rename = "patientInstruction")]
+    pub patient_instruction: Option<String>,
+    pub timing: Option<Timing>,
+    #[serde(flatten, deserialize_with = "AsNeeded::deserialize_optional")]
+    pub as_needed: Option<AsNeeded>,
+    /// Where the medication enters the body, e.g. the left arm.
+    pub site: Option<CodeableConcept>,
+    /// How the medication enters the body, e.g. orally.
+    pub route: Option<CodeableConcept>,
+    pub method: Option<CodeableConcept>,
+    #[serde(rename = "doseAndRate", default)]
+    pub dose_and_rates: Vec<DoseAndRate>,
+    #[serde(rename = "maxDosePerPeriod")]
+    pub max_dose_per_period: Option<Ratio>,
+    #[serde(rename = "maxDosePerAdministration")]
+    pub max_dose_per_administration: Option<Quantity>,
+    #[serde(rename = "maxDosePerLifetime")]
+    pub max_dose_per_lifetime: Option<Quantity>,
+}
+
+choice_type! {
+    /// Whether a medication is only taken when needed, or for what.
+    pub enum AsNeeded {
+        "asNeededBoolean" => Boolean(bool),
+        "asNeededCodeableConcept" => CodeableConcept(CodeableConcept),
+    }
+}
+
+/// A `DoseAndRate` is the amount of a medication taken at once, and how
+/// fast it is taken in.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct DoseAndRate {
+    /// Whether the dose is `calculated` or `ordered`.
+    #[serde(rename = "type")]
+    pub type_: Option<CodeableConcept>,
+    #[serde(flatten, deserialize_with = "Dose::deserialize_optional")]
+    pub dose: Option<Dose>,
+    #[serde(flatten, deserialize_with = "Rate::deserialize_optional")]
+    pub rate: Option<Rate>,
+}
+
+choice_type! {
+    /// The amount of a medication taken at once.
+    pub enum Dose {
+        "doseRange" => Range(Range),
+        "doseQuantity" => Quantity(Quantity),
+    }
+}
+
+choice_type! {
+    /// How fast a medication is taken in, e.g. for an infusion.
+    pub enum Rate {
+        "rateRatio" => Ratio(Ratio),
+        "rateRange" => Range(Range),
+        "rateQuantity" => Quantity(Quantity),
+    }
+}
+
+/// A `Timing` is when something happens, at given times and/or repeatedly,
+/// e.g. "three times a day for a week".
+///
+/// # Reference
+///
+/// - [Timing](https://www.hl7.org/fhir/datatypes.html#Timing)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Timing {
+    #[serde(rename = "event", default)]
+    pub events: Vec<FHIRDateTime>,
+    pub repeat: Option<TimingRepeat>,
+    /// A code for the whole timing, e.g. `BID` for twice a day.
+    pub code: Option<CodeableConcept>,
+}
+
+/// A `TimingRepeat` is when something happens repeatedly: `frequency` times
+/// every `period` `periodUnit`s, e.g. twice every `1` `d`, for `duration`
+/// `durationUnit`s each time. Units are UCUM codes: `s`, `min`, `h`, `d`,
+/// `wk`, `mo` or `a`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct TimingRepeat {
+    #[serde(flatten, deserialize_with = "Bounds::deserialize_optional")]
+    pub bounds: Option<Bounds>,
+    /// The number of times it happens in all.
+    pub count: Option<u32>,
+    #[serde(rename = "countMax")]
+    pub count_max: Option<u32>,
+    pub duration: Option<f64>,
+    #[serde(rename = "durationMax")]
+    pub duration_max: Option<f64>,
+    #[serde(rename = "durationUnit")]
+    pub duration_unit: Option<String>,
+    pub frequency: Option<u32>,
+    #[serde(rename = "frequencyMax")]
+    pub frequency_max: Option<u32>,
+    pub period: Option<f64>,
+    #[serde(rename = "periodMax")]
+    pub period_max: Option<f64>,
+    #[serde(rename = "periodUnit")]
+    pub period_unit: Option<String>,
+    /// The days of the week it happens on, e.g. `mon`.
+    #[serde(rename = "dayOfWeek", default)]
+    pub days_of_week: Vec<String>,
+    /// The times of day it happens at, e.g. `08:00:00`.
+    #[serde(rename = "timeOfDay", default)]
+    pub times_of_day: Vec<String>,
+    /// The events of the day it happens relative to, e.g. `ACM` for before
+    /// breakfast.
+    #[serde(default)]
+    pub when: Vec<String>,
+    /// The minutes from the events of `when` it happens at.
+    pub offset: Option<u32>,
+}
+
+choice_type! {
+    /// The length of time over which something happens repeatedly.
+    pub enum Bounds {
+        "boundsDuration" => Duration(Quantity),
+        "boundsRange" => Range(Range),
+        "boundsPeriod" => Period(Period),
+    }
+}
+
+impl Schema for Dosage {
+    fn shape() -> Shape {
+        let mut fields = vec![
+            ("sequence", Option::<i64>::shape()),
+            ("text", Option::<String>::shape()),
+            ("additionalInstruction", Vec::<CodeableConcept>::shape()),
+            ("patientInstruction", Option::<String>::shape()),
+            ("timing", Option::<Timing>::shape()),
+            ("site", Option::<CodeableConcept>::shape()),
+            ("route", Option::<CodeableConcept>::shape()),
+            ("method", Option::<CodeableConcept>::shape()),
+            ("doseAndRate", Vec::<DoseAndRate>::shape()),
+            ("maxDosePerPeriod", Option::<Ratio>::shape()),
+            ("maxDosePerAdministration", Option::<Quantity>::shape()),
+            ("maxDosePerLifetime", Option::<Quantity>::shape()),
+        ];
+        fields.extend(AsNeeded::fields());
+        Shape::Record(fields)
+    }
+}
+
+impl Schema for DoseAndRate {
+    fn shape() -> Shape {
+        let mut fields = vec![("type", Option::<CodeableConcept>::shape())];
+        fields.extend(Dose::fields());
+        fields.extend(Rate::fields());
+        Shape::Record(fields)
+    }
+}
+
+impl Schema for Timing {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("event", Vec::<FHIRDateTime>::shape()),
+            ("repeat", Option::<TimingRepeat>::shape()),
+            ("code", Option::<CodeableConcept>::shape()),
+        ])
+    }
+}
+
+impl Schema for TimingRepeat {
+    fn shape() -> Shape {
+        let mut fields = vec![
+            ("count", Option::<u32>::shape()),
+            ("countMax", Option::<u32>::shape()),
+            ("duration", Option::<f64>::shape()),
+            ("durationMax", Option::<f64>::shape()),
+            ("durationUnit", Option::<String>::shape()),
+            ("frequency", Option::<u32>::shape()),
+            ("frequencyMax", Option::<u32>::shape()),
+            ("period", Option::<f64>::shape()),
+            ("periodMax", Option::<f64>::shape()),
+            ("periodUnit", Option::<String>::shape()),
+            ("dayOfWeek", Vec::<String>::shape()),
+            ("timeOfDay", Vec::<String>::shape()),
+            ("when", Vec::<String>::shape()),
+            ("offset", Option::<u32>::shape()),
+        ];
+        fields.extend(Bounds::fields());
+        Shape::Record(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::schema::assert_shape_matches;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_deserialize() {
+        let dosage = serde_json::from_str::<Dosage>(
+            r#"{
+                "text": "One tablet twice a day for a week",
+                "timing": {
+                    "repeat": {
+                        "boundsDuration": {"value": 7, "unit": "days"},
+                        "frequency": 2,
+                        "period": 1,
+                        "periodUnit": "d"
+                    }
+                },
+                "asNeededBoolean": false,
+                "doseAndRate": [
+                    {"doseQuantity": {"value": 1, "unit": "tablet"}}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let repeat = dosage.timing.unwrap().repeat.unwrap();
+        assert_eq!(Some(2), repeat.frequency);
+        assert_eq!(Some("d"), repeat.period_unit.as_deref());
+        assert!(matches!(repeat.bounds, Some(Bounds::Duration(_))));
+        assert_eq!(Some(AsNeeded::Boolean(false)), dosage.as_needed);
+        assert_eq!(
+            Some(Dose::Quantity(Quantity {
+                value: Some(1.0),
+                unit: Some("tablet".to_string()),
+                ..Quantity::default()
+            })),
+            dosage.dose_and_rates[0].dose
+        );
+        assert_eq!(None, dosage.dose_and_rates[0].rate);
+    }
+
+    #[test]
+    fn test_shape_matches_serialized_form() {
+        let concept = CodeableConcept {
+            text: Some("Oral".to_string()),
+            ..CodeableConcept::default()
+        };
+        let quantity = Quantity {
+            value: Some(1.0),
+            unit: Some("tablet".to_string()),
+            ..Quantity::default()
+        };
+        let ratio = Ratio {
+            numerator: Some(quantity.clone()),
+            denominator: Some(quantity.clone()),
+        };
+        let dosage = Dosage {
+            sequence: Some(1),
+            text: Some("One tablet twice a day".to_string()),
+            additional_instructions: vec![concept.clone()],
+            patient_instruction: Some("With food".to_string()),
+            timing: Some(Timing {
+                events: vec!["2020-03-04T08:00:00Z".parse().unwrap()],
+                repeat: Some(TimingRepeat {
+                    bounds: Some(Bounds::Period(Period::default())),
+                    count: Some(14),
+                    count_max: Some(14),
+                    duration: Some(1.0),
+                    duration_max: Some(2.0),
+                    duration_unit: Some("h".to_string()),
+                    frequency: Some(2),
+                    frequency_max: Some(3),
+                    period: Some(1.0),
+                    period_max: Some(1.0),
+                    period_unit: Some("d".to_string()),
+                    days_of_week: vec!["mon".to_string()],
+                    times_of_day: vec!["08:00:00".to_string()],
+                    when: vec!["ACM".to_string()],
+                    offset: Some(30),
+                }),
+                code: Some(concept.clone()),
+            }),
+            as_needed: Some(AsNeeded::CodeableConcept(concept.clone())),
+            site: Some(concept.clone()),
+            route: Some(concept.clone()),
+            method: Some(concept.clone()),
+            dose_and_rates: vec![DoseAndRate {
+                type_: Some(concept),
+                dose: Some(Dose::Range(Range::default())),
+                rate: Some(Rate::Ratio(ratio.clone())),
+            }],
+            max_dose_per_period: Some(ratio),
+            max_dose_per_administration: Some(quantity.clone()),
+            max_dose_per_lifetime: Some(quantity),
+        };
+
+        assert_shape_matches(
+            &Dosage::shape(),
+            &serde_json::to_value(&dosage).unwrap(),
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use super::datatypes::{
+    CodeableConcept, Coding, Identifier, Period, Quantity, Reference,
+};
+use super::schema::{scalar_schema, Schema, Shape};
+use serde::{Deserialize, Serialize};
+
+/// Each `Encounter` is an interaction between a `Patient` and healthcare
+/// providers, e.g. an outpatient visit or an inpatient stay, as described in
+/// FHIR v4.0.1's `Encounter` JSON template. The `statusHistory`,
+/// `classHistory`, `episodeOfCare`, `basedOn`, `diagnosis`, `account` and
+/// `hospitalization` elements are not modelled.
+///
+/// # Reference
+///
+/// - [FHIR | Encounter](https://www.hl7.org/fhir/encounter.html#resource)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Encounter {
+    pub id: Option<String>,
+    #[serde(rename = "identifier", default)]
+    pub identifiers: Vec<Identifier>,
+    pub status: EncounterStatus,
+    /// The kind of encounter, e.g. `AMB` for ambulatory or `IMP` for
+    /// inpatient.
+    pub class: Coding,
+    #[serde(rename = "type", default)]
+    pub types: Vec<CodeableConcept>,
+    #[serde(rename = "serviceType")]
+    pub service_type: Option<CodeableConcept>,
+    pub priority: Option<CodeableConcept>,
+    pub subject: Option<Reference>,
+    #[serde(rename = "participant", default)]
+    pub participants: Vec<EncounterParticipant>,
+    /// The `Appointment`s the encounter was scheduled by.
+    #[serde(rename = "appointment", default)]
+    pub appointments: Vec<Reference>,
+    pub period: Option<Period>,
+    /// How long the encounter lasted, less any time away.
+    pub length: Option<Quantity>,
+    #[serde(rename = "reasonCode", default)]
+    pub reason_codes: Vec<CodeableConcept>,
+    #[serde(rename = "reasonReference", default)]
+    pub reason_references: Vec<Reference>,
+    #[serde(rename = "location", default)]
+    pub locations: Vec<EncounterLocation>,
+    #[serde(rename = "serviceProvider")]
+    pub service_provider: Option<Reference>,
+    /// The larger encounter this one is part of, e.g. the admission of a
+    /// ward round.
+    #[serde(rename = "partOf")]
+    pub part_of: Option<Reference>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum EncounterStatus {
+    Planned,
+    Arrived,
+    Triaged,
+    InProgress,
+    Onleave,
+    Finished,
+    Cancelled,
+    EnteredInError,
+    Unknown,
+}
+
+/// An `EncounterParticipant` is someone taking part in an `Encounter` other
+/// than the patient, e.g. their consultant.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct EncounterParticipant {
+    /// The roles of the participant, e.g. `ATND` for the attending doctor.
+    #[serde(rename = "type", default)]
+    pub types: Vec<CodeableConcept>,
+    pub period: Option<Period>,
+    pub individual: Option<Reference>,
+}
+
+/// An `EncounterLocation` is where the patient was during an `Encounter`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct EncounterLocation {
+    pub location: Reference,
+    pub status: Option<EncounterLocationStatus>,
+    /// Whether the location is e.g. a ward, a room or a bed.
+    #[serde(rename = "physicalType")]
+    pub physical_type: Option<CodeableConcept>,
+    pub period: Option<Period>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum EncounterLocationStatus {
+    Planned,
+    Active,
+    Reserved,
+    Completed,
+}
+
+impl Encounter {
+    /// Whether the patient is currently attending the encounter.
+    pub fn is_in_progress(&self) -> bool {
+        matches!(
+            self.status,
+            EncounterStatus::Arrived
+                | EncounterStatus::Triaged
+                | EncounterStatus::InProgress
+                | EncounterStatus::Onleave
+        )
+    }
+}
+
+scalar_schema!(EncounterStatus, EncounterLocationStatus);
+
+impl Schema for Encounter {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("id", Option::<String>::shape()),
+            ("identifier", Vec::<Identifier>::shape()),
+            ("status", EncounterStatus::shape()),
+            ("class", Coding::shape()),
+            ("type", Vec::<CodeableConcept>::shape()),
+            ("serviceType", Option::<CodeableConcept>::shape()),
+            ("priority", Option::<CodeableConcept>::shape()),
+            ("subject", Option::<Reference>::shape()),
+            ("participant", Vec::<EncounterParticipant>::shape()),
+            ("appointment", Vec::<Reference>::shape()),
+            ("period", Option::<Period>::shape()),
+            ("length", Option::<Quantity>::shape()),
+            ("reasonCode", Vec::<CodeableConcept>::shape()),
+            ("reasonReference", Vec::<Reference>::shape()),
+            ("location", Vec::<EncounterLocation>::shape()),
+            ("serviceProvider", Option::<Reference>::shape()),
+            ("partOf", Option::<Reference>::shape()),
+        ])
+    }
+}
+
+impl Schema for EncounterParticipant {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("type", Vec::<CodeableConcept>::shape()),
+            ("period", Option::<Period>::shape()),
+            ("individual", Option::<Reference>::shape()),
+        ])
+    }
+}
+
+impl Schema for EncounterLocation {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("location", Reference::shape()),
+            ("status", Option::<EncounterLocationStatus>::shape()),
+            ("physicalType", Option::<CodeableConcept>::shape()),
+            ("period", Option::<Period>::shape()),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::schema::assert_shape_matches;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_deserialize() {
+        let encounter = serde_json::from_str::<Encounter>(
+            r#"{
+                "resourceType": "Encounter",
+                "id": "home",
+                "status": "in-progress",
+                "class": {
+                    "system": "http://terminology.hl7.org/CodeSystem/v3-ActCode",
+                    "code": "AMB"
+                },
+                "subject": {"reference": "Patient/example"},
+                "participant": [
+                    {"individual": {"display": "Dr Adam Careful"}}
+                ],
+                "period": {"start": "2015-01-17T16:00:00+10:00"},
+                "location": [
+                    {
+                        "location": {"display": "Ward 4"},
+                        "status": "active"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(encounter.is_in_progress());
+        assert_eq!(Some("AMB"), encounter.class.code.as_deref());
+        assert_eq!(
+            Some("Dr Adam Careful"),
+            encounter.participants[0]
+                .individual
+                .as_ref()
+                .and_then(|individual| individual.display.as_deref())
+        );
+        assert_eq!(
+            Some(EncounterLocationStatus::Active),
+            encounter.locations[0].status
+        );
+
+        assert!(
+            serde_json::from_str::<Encounter>(r#"{"status": "finished"}"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_shape_matches_serialized_form() {
+        let concept = CodeableConcept {
+            text: Some("Consultation".to_string()),
+            ..CodeableConcept::default()
+        };
+        let reference = Reference {
+            reference: Some("Patient/example".to_string()),
+            ..Reference::default()
+        };
+        let period = Period {
+            start: Some("2015-01-17T16:00:00+10:00".parse().unwrap()),
+            end: None,
+        };
+        let encounter = Encounter {
+            id: Some("home".to_string()),
+            identifiers: vec![Identifier::default()],
+            status: EncounterStatus::Finished,
+            class: Coding {
+                code: Some("AMB".to_string()),
+                ..Coding::default()
+            },
+            types: vec![concept.clone()],
+            service_type: Some(concept.clone()),
+            priority: Some(concept.clone()),
+            subject: Some(reference.clone()),
+            participants: vec![EncounterParticipant {
+                types: vec![concept.clone()],
+                period: Some(period.clone()),
+                individual: Some(reference.clone()),
+            }],
+            appointments: vec![reference.clone()],
+            period: Some(period.clone()),
+            length: Some(Quantity::default()),
+            reason_codes: vec![concept.clone()],
+            reason_references: vec![reference.clone()],
+            locations: vec![EncounterLocation {
+                location: reference.clone(),
+                status: Some(EncounterLocationStatus::Completed),
+                physical_type: Some(concept),
+                period: Some(period),
+            }],
+            service_provider: Some(reference.clone()),
+            part_of: Some(reference),
+        };
+
+        assert_shape_matches(
+            &Encounter::shape(),
+            &serde_json::to_value(&encounter).unwrap(),
+        );
+    }
+}
@@ -1,6 +1,9 @@
+use super::locale::Locale;
 use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
 use std::fmt;
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Each `FHIRDate` is either:
 ///
@@ -18,6 +21,157 @@ pub struct FHIRDate {
     day: Option<u32>,
 }
 
+impl FHIRDate {
+    pub fn year(&self) -> u32 {
+        self.year
+    }
+
+    pub fn month(&self) -> Option<u32> {
+        self.month
+    }
+
+    pub fn day(&self) -> Option<u32> {
+        self.day
+    }
+
+    /// Today's date in UTC.
+    pub fn today() -> FHIRDate {
+        let days = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() / 86_400);
+        from_days(days as i64)
+    }
+
+    /// The number of whole years from this date to `date`, e.g. the age on
+    /// `date` of a patient born on this date. Months and days are only taken
+    /// into account when both dates have them.
+    pub fn age_on(&self, date: &FHIRDate) -> Result<u32, String> {
+        let before_anniversary = match (self.month, date.month) {
+            (Some(month), Some(on_month)) if month != on_month => {
+                on_month < month
+            }
+            (Some(_), Some(_)) => {
+                matches!((self.day, date.day), (Some(day), Some(on_day)) if on_day < day)
+            }
+            _ => false,
+        };
+
+        date.year
+            .checked_sub(self.year)
+            .and_then(|years| years.checked_sub(before_anniversary as u32))
+            .ok_or_else(|| format!("date `{}` is after `{}`", self, date))
+    }
+
+    /// Format the date following a `strftime`-like `pattern`. The supported
+    /// specifiers are:
+    ///
+    /// - `%Y`: the year, e.g. `2019`;
+    /// - `%y`: the last two digits of the year, e.g. `19`;
+    /// - `%m`: the zero-padded month, e.g. `01`;
+    /// - `%B`: the month name, e.g. `January`;
+    /// - `%b`: the abbreviated month name, e.g. `Jan`;
+    /// - `%d`: the zero-padded day, e.g. `05`;
+    /// - `%e`: the day without padding, e.g. `5`;
+    /// - `%%`: a literal `%`.
+    ///
+    /// Formatting fails if the pattern refers to a part missing from a partial
+    /// date. Months are named in English, see `format_in` for other locales.
+    pub fn format(&self, pattern: &str) -> Result<String, String> {
+        self.format_in(pattern, &Locale::default())
+    }
+
+    /// Format the date as `format` does, with the month names of `locale`.
+    pub fn format_in(
+        &self,
+        pattern: &str,
+        locale: &Locale,
+    ) -> Result<String, String> {
+        let calendar = locale.calendar();
+        let mut formatted = String::new();
+        let mut chars = pattern.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                formatted.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('Y') => formatted.push_str(&format!("{:0>4}", self.year)),
+                Some('y') => {
+                    formatted.push_str(&format!("{:0>2}", self.year % 100))
+                }
+                Some('m') => formatted
+                    .push_str(&format!("{:0>2}", self.required_month()?)),
+                Some('B') => {
+                    formatted.push_str(calendar.months[self.month_index()?])
+                }
+                Some('b') => formatted
+                    .push_str(calendar.abbreviated_months[self.month_index()?]),
+                Some('d') => {
+                    formatted.push_str(&format!("{:0>2}", self.required_day()?))
+                }
+                Some('e') => {
+                    formatted.push_str(&self.required_day()?.to_string())
+                }
+                Some('%') => formatted.push('%'),
+                Some(c) => {
+                    return Err(format!("unknown date specifier `%{}`", c))
+                }
+                None => return Err("incomplete date specifier `%`".to_string()),
+            }
+        }
+
+        Ok(formatted)
+    }
+
+    fn required_month(&self) -> Result<u32, String> {
+        self.month
+            .ok_or_else(|| format!("date `{}` has no month", self))
+    }
+
+    fn required_day(&self) -> Result<u32, String> {
+        self.day
+            .ok_or_else(|| format!("date `{}` has no day", self))
+    }
+
+    /// The index of the month in the month names of a `Calendar`.
+    fn month_index(&self) -> Result<usize, String> {
+        let month = self.required_month()?;
+        match month {
+            1..=12 => Ok(month as usize - 1),
+            _ => Err(format!("invalid month `{}`", month)),
+        }
+    }
+}
+
+/// The date `days` days after 1970-01-01, following
+/// [civil_from_days](http://howardhinnant.github.io/date_algorithms.html).
+fn from_days(days: i64) -> FHIRDate {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    FHIRDate {
+        year: year as u32,
+        month: Some(month as u32),
+        day: Some(day as u32),
+    }
+}
+
 /// We try to parser a `&str` into a `FHIRDate`.
 pub fn deserialize_fhirdate(s: &str) -> Result<FHIRDate, String> {
     let s = s.trim();
@@ -81,6 +235,15 @@ impl<'de> Deserialize<'de> for FHIRDate {
     }
 }
 
+impl Serialize for FHIRDate {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&serialize_fhirdate(self))
+    }
+}
+
 impl fmt::Display for FHIRDate {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -108,6 +271,216 @@ pub fn serialize_fhirdate(date: &FHIRDate) -> String {
     format!("{}", date)
 }
 
+/// Each `FHIRDateTime` is a `FHIRDate`, optionally followed by a time of day
+/// and a time zone when the date is a full date, e.g. `2015-02-07`,
+/// `2015-02-07T13:28:17-05:00` or `2017-01-01T00:00:00.000Z`.
+///
+/// # Reference
+///
+/// - [dateTime](https://www.hl7.org/fhir/datatypes.html#dateTime)
+#[derive(Debug, PartialEq, Clone)]
+pub struct FHIRDateTime {
+    date: FHIRDate,
+    time: Option<Time>,
+}
+
+/// The time of day of a `FHIRDateTime`. The fraction of a second and the time
+/// zone are kept as they are written.
+#[derive(Debug, PartialEq, Clone)]
+struct Time {
+    hour: u32,
+    minute: u32,
+    second: u32,
+    fraction: Option<String>,
+    zone: Option<String>,
+}
+
+impl FHIRDateTime {
+    pub fn date(&self) -> &FHIRDate {
+        &self.date
+    }
+
+    pub fn hour(&self) -> Option<u32> {
+        self.time.as_ref().map(|time| time.hour)
+    }
+
+    pub fn minute(&self) -> Option<u32> {
+        self.time.as_ref().map(|time| time.minute)
+    }
+
+    pub fn second(&self) -> Option<u32> {
+        self.time.as_ref().map(|time| time.second)
+    }
+
+    /// Format the date and time as `FHIRDate::format_in` does, with the
+    /// additional specifiers:
+    ///
+    /// - `%H`: the zero-padded hour, e.g. `09`;
+    /// - `%M`: the zero-padded minute, e.g. `05`;
+    /// - `%S`: the zero-padded second, e.g. `00`.
+    ///
+    /// Formatting fails if the pattern refers to the time of a date without
+    /// one.
+    pub fn format_in(
+        &self,
+        pattern: &str,
+        locale: &Locale,
+    ) -> Result<String, String> {
+        let mut formatted = String::new();
+        let mut chars = pattern.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                formatted.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('H') => formatted
+                    .push_str(&format!("{:0>2}", self.required_time()?.hour)),
+                Some('M') => formatted
+                    .push_str(&format!("{:0>2}", self.required_time()?.minute)),
+                Some('S') => formatted
+                    .push_str(&format!("{:0>2}", self.required_time()?.second)),
+                Some(c) => formatted.push_str(
+                    &self.date.format_in(&format!("%{}", c), locale)?,
+                ),
+                None => return Err("incomplete date specifier `%`".to_string()),
+            }
+        }
+
+        Ok(formatted)
+    }
+
+    fn required_time(&self) -> Result<&Time, String> {
+        self.time
+            .as_ref()
+            .ok_or_else(|| format!("date `{}` has no time", self))
+    }
+}
+
+impl From<FHIRDate> for FHIRDateTime {
+    fn from(date: FHIRDate) -> Self {
+        Self { date, time: None }
+    }
+}
+
+impl FromStr for FHIRDateTime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (date, time) = match s.split_once('T') {
+            Some((date, time)) => (date, Some(time)),
+            None => (s, None),
+        };
+        let date = date.parse::<FHIRDate>()?;
+        let time = match time {
+            Some(_) if date.day.is_none() => {
+                return Err(format!("date `{}` has a time but no day", s))
+            }
+            Some(time) => Some(
+                parse_time(time)
+                    .ok_or_else(|| format!("invalid time `{}`", time))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self { date, time })
+    }
+}
+
+/// Parse `hh:mm:ss`, optionally followed by a fraction of a second and a time
+/// zone, either `Z` or an offset such as `+01:00`.
+fn parse_time(s: &str) -> Option<Time> {
+    let (time, zone) = match s.find(['Z', '+', '-']) {
+        Some(index) => (&s[..index], Some(&s[index..])),
+        None => (s, None),
+    };
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction)) => (time, Some(fraction)),
+        None => (time, None),
+    };
+
+    let digits =
+        |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    let parts = time.split(':').collect::<Vec<_>>();
+    if parts.len() != 3 || !parts.iter().all(|part| digits(part)) {
+        return None;
+    }
+    if !fraction.is_none_or(digits) {
+        return None;
+    }
+    let valid_zone = |zone: &str| {
+        zone == "Z"
+            || zone.len() == 6
+                && zone.is_ascii()
+                && !zone.starts_with('Z')
+                && zone[1..3].chars().all(|c| c.is_ascii_digit())
+                && &zone[3..4] == ":"
+                && zone[4..].chars().all(|c| c.is_ascii_digit())
+    };
+    if !zone.is_none_or(valid_zone) {
+        return None;
+    }
+
+    let (hour, minute, second) = (
+        parts[0].parse().ok()?,
+        parts[1].parse().ok()?,
+        parts[2].parse().ok()?,
+    );
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    Some(Time {
+        hour,
+        minute,
+        second,
+        fraction: fraction.map(str::to_string),
+        zone: zone.map(str::to_string),
+    })
+}
+
+impl<'de> Deserialize<'de> for FHIRDateTime {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for FHIRDateTime {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl fmt::Display for FHIRDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.date)?;
+        if let Some(time) = &self.time {
+            write!(
+                f,
+                "T{:0>2}:{:0>2}:{:0>2}",
+                time.hour, time.minute, time.second
+            )?;
+            if let Some(fraction) = &time.fraction {
+                write!(f, ".{}", fraction)?;
+            }
+            if let Some(zone) = &time.zone {
+                write!(f, "{}", zone)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -185,4 +558,139 @@ mod tests {
 
         assert_eq!("0001-01-01", &s);
     }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let date = FHIRDate {
+            year: 2019,
+            month: Some(1),
+            day: Some(23),
+        };
+
+        let json = serde_json::to_string(&date).unwrap();
+        assert_eq!("\"2019-01-23\"", json);
+        assert_eq!(date, serde_json::from_str::<FHIRDate>(&json).unwrap());
+    }
+
+    #[test]
+    fn test_format_full_date() {
+        let date = deserialize_fhirdate("2019-01-05").unwrap();
+
+        assert_eq!("05 January 2019", date.format("%d %B %Y").unwrap());
+        assert_eq!("5 Jan 19", date.format("%e %b %y").unwrap());
+        assert_eq!("05/01/2019 100%", date.format("%d/%m/%Y 100%%").unwrap());
+    }
+
+    #[test]
+    fn test_format_partial_date() {
+        let date = deserialize_fhirdate("2019-03").unwrap();
+
+        assert_eq!("March 2019", date.format("%B %Y").unwrap());
+        assert!(date.format("%d %B %Y").is_err());
+    }
+
+    #[test]
+    fn test_format_in_locale() {
+        let date = deserialize_fhirdate("2019-02-05").unwrap();
+        let locale = |s: &str| s.parse::<Locale>().unwrap();
+
+        assert_eq!(
+            "5 février 2019",
+            date.format_in("%e %B %Y", &locale("fr")).unwrap()
+        );
+        assert_eq!(
+            "5. Feb. 2019",
+            date.format_in("%e. %b %Y", &locale("de-AT")).unwrap()
+        );
+        assert_eq!(
+            "5 Chwefror 2019",
+            date.format_in("%e %B %Y", &locale("cy")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_unknown_specifier() {
+        let date = deserialize_fhirdate("2019").unwrap();
+
+        assert!(date.format("%Q").is_err());
+        assert!(date.format("%").is_err());
+    }
+
+    #[test]
+    fn test_from_days() {
+        assert_eq!("1970-01-01", from_days(0).to_string());
+        assert_eq!("2000-02-29", from_days(11_016).to_string());
+        assert_eq!("2022-01-08", from_days(19_000).to_string());
+    }
+
+    #[test]
+    fn test_age_on() {
+        let born = deserialize_fhirdate("2012-03-04").unwrap();
+        let on = |s| deserialize_fhirdate(s).unwrap();
+
+        assert_eq!(Ok(7), born.age_on(&on("2020-03-03")));
+        assert_eq!(Ok(8), born.age_on(&on("2020-03-04")));
+        assert_eq!(Ok(8), born.age_on(&on("2020-03")));
+        assert_eq!(Ok(8), born.age_on(&on("2020")));
+        assert_eq!(Ok(0), born.age_on(&born));
+        assert!(born.age_on(&on("2012-03-03")).is_err());
+    }
+
+    #[test]
+    fn test_date_time() {
+        let date_time = "2015-02-07T13:28:17.239-05:00"
+            .parse::<FHIRDateTime>()
+            .unwrap();
+
+        assert_eq!(
+            &deserialize_fhirdate("2015-02-07").unwrap(),
+            date_time.date()
+        );
+        assert_eq!(Some(13), date_time.hour());
+        assert_eq!(Some(28), date_time.minute());
+        assert_eq!(Some(17), date_time.second());
+        assert_eq!("2015-02-07T13:28:17.239-05:00", date_time.to_string());
+
+        let date = "2015-02".parse::<FHIRDateTime>().unwrap();
+        assert_eq!(None, date.hour());
+        assert_eq!("2015-02", date.to_string());
+        assert_eq!(
+            "2017-01-01T00:00:00Z",
+            "2017-01-01T00:00:00Z"
+                .parse::<FHIRDateTime>()
+                .unwrap()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_invalid_date_time() {
+        for s in &[
+            "2015-02T13:28:17Z",
+            "2015-02-07T13:28",
+            "2015-02-07T25:00:00Z",
+            "2015-02-07T13:28:17+5",
+            "2015-02-07T13:28:17.Z",
+            "2015-02-07T",
+        ] {
+            assert!(s.parse::<FHIRDateTime>().is_err(), "`{}` was parsed", s);
+        }
+    }
+
+    #[test]
+    fn test_format_date_time() {
+        let date_time =
+            "2020-03-04T09:05:00+00:00".parse::<FHIRDateTime>().unwrap();
+        let locale = Locale::default();
+
+        assert_eq!(
+            "4 March 2020 at 09:05",
+            date_time.format_in("%e %B %Y at %H:%M", &locale).unwrap()
+        );
+        assert_eq!("100%", date_time.format_in("100%%", &locale).unwrap());
+
+        let date = "2020-03-04".parse::<FHIRDateTime>().unwrap();
+        assert!(date.format_in("%H:%M", &locale).is_err());
+        assert_eq!("04/03/2020", date.format_in("%d/%m/%Y", &locale).unwrap());
+    }
 }
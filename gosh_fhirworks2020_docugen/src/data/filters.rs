@@ -0,0 +1,174 @@
+use super::appointment::{self, AppointmentParticipant};
+use super::datatypes::Quantity;
+use super::fhir_date::FHIRDate;
+use super::patient::{HumanName, NameStyle};
+use crate::core::filter::{text_arg, FilterRegistry};
+use crate::core::value::Value;
+
+/// The built-in filters of `FilterRegistry`, along with the filters of the
+/// FHIR resources and datatypes of `data`:
+///
+/// - `name([style])`: write a `HumanName` in `style`, `"full"` by default,
+///   see `NameStyle`. Applied to a list of names, such as the `name` of a
+///   patient, it writes the name preferred today, see `HumanName::preferred`;
+/// - `quantity`: write a `Quantity` with its unit, e.g. `<5.4 mmol/L`;
+/// - `actor(type)`: write the participant of an `Appointment` of resource
+///   type `type`, e.g. its `"Location"`, from its `participant` list.
+pub fn registry() -> FilterRegistry {
+    let mut registry = FilterRegistry::default();
+    registry.register("name", name);
+    registry.register("quantity", quantity);
+    registry.register("actor", actor);
+    registry
+}
+
+fn name(value: &Value, args: &[Value]) -> Result<Value, String> {
+    let style = match args.first() {
+        Some(_) => text_arg(args, 0)?.parse::<NameStyle>()?,
+        None => NameStyle::Full,
+    };
+    let formatted = match value {
+        Value::Null => return Ok(Value::Null),
+        Value::List(_) => {
+            let names = value.deserialize_into::<Vec<HumanName>>()?;
+            match HumanName::preferred(&names, &FHIRDate::today()) {
+                Some(name) => name.format(style),
+                None => return Ok(Value::Null),
+            }
+        }
+        Value::Map(_) => value.deserialize_into::<HumanName>()?.format(style),
+        _ => return Err("expected a name or a list of names".to_string()),
+    };
+    Ok(Value::String(formatted))
+}
+
+fn quantity(value: &Value, _: &[Value]) -> Result<Value, String> {
+    match value {
+        Value::Null => Ok(Value::Null),
+        Value::Map(_) => {
+            let quantity = value.deserialize_into::<Quantity>()?;
+            Ok(Value::String(quantity.to_string()))
+        }
+        _ => Err("expected a quantity".to_string()),
+    }
+}
+
+fn actor(value: &Value, args: &[Value]) -> Result<Value, String> {
+    let resource_type = text_arg(args, 0)?;
+    match value {
+        Value::Null => Ok(Value::Null),
+        Value::List(_) => {
+            let participants =
+                value.deserialize_into::<Vec<AppointmentParticipant>>()?;
+            Ok(appointment::actor(&participants, &resource_type)
+                .and_then(|actor| actor.display.clone())
+                .map_or(Value::Null, Value::String))
+        }
+        _ => Err("expected the participants of an appointment".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn apply(
+        name: &str,
+        value: Value,
+        args: &[Value],
+    ) -> Result<Value, String> {
+        let registry = registry();
+        let filter = registry.get(name).unwrap();
+        filter(&value, args, &"en-GB".parse().unwrap())
+    }
+
+    #[test]
+    fn test_name_filter() {
+        let name = |use_: &str, family: &str, end: &str| {
+            vec![
+                ("use", Value::from(use_)),
+                ("family", Value::from(family)),
+                ("given", Value::from(vec!["Jane", "Alice"])),
+                ("prefix", Value::from(vec!["Dr"])),
+                ("period", vec![("end", end)].into_iter().collect::<Value>()),
+            ]
+            .into_iter()
+            .collect::<Value>()
+        };
+        let names = Value::List(vec![
+            name("usual", "Smith", "2100"),
+            name("official", "Jones", "2000"),
+        ]);
+
+        assert_eq!(
+            Ok(Value::from("Dr Jane Alice Smith")),
+            apply("name", names.clone(), &[])
+        );
+        assert_eq!(
+            Ok(Value::from("Dr Jane A. Smith")),
+            apply("name", names, &["formal".into()])
+        );
+        assert_eq!(
+            Ok(Value::from("JONES, Jane Alice")),
+            apply(
+                "name",
+                name("official", "Jones", "2000"),
+                &["family_first".into()]
+            )
+        );
+        assert_eq!(Ok(Value::Null), apply("name", Value::List(vec![]), &[]));
+        assert_eq!(Ok(Value::Null), apply("name", Value::Null, &[]));
+        assert!(apply("name", "Jane".into(), &[]).is_err());
+        assert!(apply("name", Value::List(vec![]), &["short".into()]).is_err());
+    }
+
+    #[test]
+    fn test_quantity_filter() {
+        let quantity = vec![
+            ("value", Value::Number(6.3)),
+            ("comparator", Value::from(">")),
+            ("unit", Value::from("mmol/l")),
+        ]
+        .into_iter()
+        .collect::<Value>();
+
+        assert_eq!(
+            Ok(Value::from(">6.3 mmol/l")),
+            apply("quantity", quantity, &[])
+        );
+        assert_eq!(Ok(Value::Null), apply("quantity", Value::Null, &[]));
+        assert!(apply("quantity", Value::Number(6.3), &[]).is_err());
+    }
+
+    #[test]
+    fn test_actor_filter() {
+        let participants = serde_json::from_str::<serde_json::Value>(
+            r#"[
+                {"actor": {"reference": "Patient/1", "display": "Jane Smith"},
+                 "status": "accepted"},
+                {"actor": {"type": "Location", "display": "Outpatients"},
+                 "status": "accepted"}
+            ]"#,
+        )
+        .unwrap();
+        let participants = Value::from_serialize(&participants).unwrap();
+
+        assert_eq!(
+            Ok(Value::from("Outpatients")),
+            apply("actor", participants.clone(), &["Location".into()])
+        );
+        assert_eq!(
+            Ok(Value::from("Jane Smith")),
+            apply("actor", participants.clone(), &["Patient".into()])
+        );
+        assert_eq!(
+            Ok(Value::Null),
+            apply("actor", participants.clone(), &["Device".into()])
+        );
+        assert!(apply("actor", participants, &[]).is_err());
+        assert!(
+            apply("actor", "Outpatients".into(), &["Location".into()]).is_err()
+        );
+    }
+}
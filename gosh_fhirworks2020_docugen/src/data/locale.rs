@@ -0,0 +1,380 @@
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// A `Locale` names the language documents are written in, and optionally the
+/// region whose conventions they follow, as a language tag such as `fr` or
+/// `en-GB`. It picks the localized variants of templates, the names of months
+/// and the order of dates, and the message catalogue translating
+/// `{{ t "key" }}` tags.
+///
+/// The default locale is `en-GB`.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Locale {
+    language: String,
+    region: Option<String>,
+}
+
+impl Locale {
+    /// The language, in lowercase, e.g. `fr`.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The region, in uppercase, e.g. `CA`.
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    /// The tags naming this locale, from the most to the least specific, e.g.
+    /// `fr-CA` then `fr`. Localized resources are looked up in this order.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags = vec![self.language.clone()];
+        if self.region.is_some() {
+            tags.insert(0, self.to_string());
+        }
+        tags
+    }
+
+    /// Names of months and orders of dates in this locale. Locales without
+    /// their own calendar use the English one.
+    pub fn calendar(&self) -> &'static Calendar {
+        match (self.language(), self.region()) {
+            ("en", Some("US")) => &ENGLISH_US,
+            ("cy", _) => &WELSH,
+            ("de", _) => &GERMAN,
+            ("es", _) => &SPANISH,
+            ("fr", _) => &FRENCH,
+            ("it", _) => &ITALIAN,
+            ("nl", _) => &DUTCH,
+            ("pt", _) => &PORTUGUESE,
+            _ => &ENGLISH,
+        }
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self {
+            language: "en".to_string(),
+            region: Some("GB".to_string()),
+        }
+    }
+}
+
+/// Parses a language tag made of a language of two or three letters,
+/// optionally followed by a region of two letters or three digits, separated
+/// by `-` or `_`, e.g. `fr`, `en-GB` or `es_419`.
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid locale `{}`", s);
+        let mut parts = s.split(['-', '_']);
+
+        let language = parts.next().unwrap_or_default();
+        if !(2..=3).contains(&language.len())
+            || !language.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return Err(invalid());
+        }
+
+        let region = match parts.next() {
+            Some(region)
+                if region.len() == 2
+                    && region.chars().all(|c| c.is_ascii_alphabetic())
+                    || region.len() == 3
+                        && region.chars().all(|c| c.is_ascii_digit()) =>
+            {
+                Some(region.to_ascii_uppercase())
+            }
+            Some(_) => return Err(invalid()),
+            None => None,
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            language: language.to_ascii_lowercase(),
+            region,
+        })
+    }
+}
+
+impl TryFrom<String> for Locale {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Locale> for String {
+    fn from(locale: Locale) -> Self {
+        locale.to_string()
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.language)?;
+        if let Some(region) = &self.region {
+            write!(f, "-{}", region)?;
+        }
+        Ok(())
+    }
+}
+
+/// The names of months and the usual orders of dates of a `Locale`.
+#[derive(Debug, PartialEq)]
+pub struct Calendar {
+    /// Month names, indexed by month number minus one.
+    pub months: [&'static str; 12],
+    pub abbreviated_months: [&'static str; 12],
+    /// Pattern of dates written out, see `FHIRDate::format`, e.g. `4 March
+    /// 2012`.
+    pub long_date: &'static str,
+    /// Pattern of dates written in digits, e.g. `04/03/2012`.
+    pub short_date: &'static str,
+}
+
+const ENGLISH: Calendar = Calendar {
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    abbreviated_months: [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct",
+        "Nov", "Dec",
+    ],
+    long_date: "%e %B %Y",
+    short_date: "%d/%m/%Y",
+};
+
+const ENGLISH_US: Calendar = Calendar {
+    long_date: "%B %e, %Y",
+    short_date: "%m/%d/%Y",
+    ..ENGLISH
+};
+
+const WELSH: Calendar = Calendar {
+    months: [
+        "Ionawr",
+        "Chwefror",
+        "Mawrth",
+        "Ebrill",
+        "Mai",
+        "Mehefin",
+        "Gorffennaf",
+        "Awst",
+        "Medi",
+        "Hydref",
+        "Tachwedd",
+        "Rhagfyr",
+    ],
+    abbreviated_months: [
+        "Ion", "Chwef", "Maw", "Ebr", "Mai", "Meh", "Gorff", "Awst", "Medi",
+        "Hyd", "Tach", "Rhag",
+    ],
+    long_date: "%e %B %Y",
+    short_date: "%d/%m/%Y",
+};
+
+const GERMAN: Calendar = Calendar {
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    abbreviated_months: [
+        "Jan.", "Feb.", "März", "Apr.", "Mai", "Juni", "Juli", "Aug.", "Sept.",
+        "Okt.", "Nov.", "Dez.",
+    ],
+    long_date: "%e. %B %Y",
+    short_date: "%d.%m.%Y",
+};
+
+const SPANISH: Calendar = Calendar {
+    months: [
+        "enero",
+        "febrero",
+        "marzo",
+        "abril",
+        "mayo",
+        "junio",
+        "julio",
+        "agosto",
+        "septiembre",
+        "octubre",
+        "noviembre",
+        "diciembre",
+    ],
+    abbreviated_months: [
+        "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct",
+        "nov", "dic",
+    ],
+    long_date: "%e de %B de %Y",
+    short_date: "%d/%m/%Y",
+};
+
+const FRENCH: Calendar = Calendar {
+    months: [
+        "janvier",
+        "février",
+        "mars",
+        "avril",
+        "mai",
+        "juin",
+        "juillet",
+        "août",
+        "septembre",
+        "octobre",
+        "novembre",
+        "décembre",
+    ],
+    abbreviated_months: [
+        "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août",
+        "sept.", "oct.", "nov.", "déc.",
+    ],
+    long_date: "%e %B %Y",
+    short_date: "%d/%m/%Y",
+};
+
+const ITALIAN: Calendar = Calendar {
+    months: [
+        "gennaio",
+        "febbraio",
+        "marzo",
+        "aprile",
+        "maggio",
+        "giugno",
+        "luglio",
+        "agosto",
+        "settembre",
+        "ottobre",
+        "novembre",
+        "dicembre",
+    ],
+    abbreviated_months: [
+        "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott",
+        "nov", "dic",
+    ],
+    long_date: "%e %B %Y",
+    short_date: "%d/%m/%Y",
+};
+
+const DUTCH: Calendar = Calendar {
+    months: [
+        "januari",
+        "februari",
+        "maart",
+        "april",
+        "mei",
+        "juni",
+        "juli",
+        "augustus",
+        "september",
+        "oktober",
+        "november",
+        "december",
+    ],
+    abbreviated_months: [
+        "jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt",
+        "nov", "dec",
+    ],
+    long_date: "%e %B %Y",
+    short_date: "%d-%m-%Y",
+};
+
+const PORTUGUESE: Calendar = Calendar {
+    months: [
+        "janeiro",
+        "fevereiro",
+        "março",
+        "abril",
+        "maio",
+        "junho",
+        "julho",
+        "agosto",
+        "setembro",
+        "outubro",
+        "novembro",
+        "dezembro",
+    ],
+    abbreviated_months: [
+        "jan", "fev", "mar", "abr", "mai", "jun", "jul", "ago", "set", "out",
+        "nov", "dez",
+    ],
+    long_date: "%e de %B de %Y",
+    short_date: "%d/%m/%Y",
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_locale() {
+        let locale = "fr_ca".parse::<Locale>().unwrap();
+        assert_eq!("fr", locale.language());
+        assert_eq!(Some("CA"), locale.region());
+        assert_eq!("fr-CA", locale.to_string());
+        assert_eq!(vec!["fr-CA", "fr"], locale.tags());
+
+        assert_eq!(vec!["de"], "DE".parse::<Locale>().unwrap().tags());
+        assert_eq!("es-419", "es-419".parse::<Locale>().unwrap().to_string());
+        assert_eq!("en-GB", Locale::default().to_string());
+    }
+
+    #[test]
+    fn test_invalid_locales() {
+        for s in &["", "f", "french", "fr-", "fr-Canada", "fr-CA-x", "1a"] {
+            assert!(s.parse::<Locale>().is_err(), "`{}` was parsed", s);
+        }
+    }
+
+    #[test]
+    fn test_locale_serialization() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Config {
+            locale: Locale,
+        }
+
+        let config = toml::from_str::<Config>("locale = \"en-US\"").unwrap();
+        assert_eq!("en-US".parse::<Locale>().unwrap(), config.locale);
+        assert!(toml::from_str::<Config>("locale = \"english\"").is_err());
+    }
+
+    #[test]
+    fn test_calendar() {
+        let calendar = |s: &str| s.parse::<Locale>().unwrap().calendar();
+
+        assert_eq!("%B %e, %Y", calendar("en-US").long_date);
+        assert_eq!("%e %B %Y", calendar("en").long_date);
+        assert_eq!("mars", calendar("fr-CA").months[2]);
+        assert_eq!(&ENGLISH, calendar("ja"));
+    }
+}
@@ -0,0 +1,311 @@
+use super::datatypes::{
+    choice_type, CodeableConcept, Identifier, Period, Quantity, Reference,
+};
+use super::dosage::Dosage;
+use super::fhir_date::FHIRDateTime;
+use super::schema::{scalar_schema, Schema, Shape};
+use serde::{Deserialize, Serialize};
+
+/// Each `MedicationRequest` is an order or a prescription of a medication for
+/// a `Patient`, as described in FHIR v4.0.1's `MedicationRequest` JSON
+/// template, with the `Dosage` it should be taken at. The elements about
+/// the workflow of the request, such as `basedOn`, `groupIdentifier`,
+/// `insurance` and `substitution`, and the `note` element are not modelled.
+///
+/// # Reference
+///
+/// - [FHIR | MedicationRequest](https://www.hl7.org/fhir/medicationrequest.html#resource)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct MedicationRequest {
+    pub id: Option<String>,
+    #[serde(rename = "identifier", default)]
+    pub identifiers: Vec<Identifier>,
+    pub status: MedicationRequestStatus,
+    #[serde(rename = "statusReason")]
+    pub status_reason: Option<CodeableConcept>,
+    pub intent: MedicationRequestIntent,
+    /// Where the medication is taken, e.g. `inpatient` or `discharge`.
+    #[serde(rename = "category", default)]
+    pub categories: Vec<CodeableConcept>,
+    pub priority: Option<MedicationRequestPriority>,
+    /// Whether the request is that the medication must not be taken.
+    #[serde(rename = "doNotPerform")]
+    pub do_not_perform: Option<bool>,
+    #[serde(flatten, deserialize_with = "Reported::deserialize_optional")]
+    pub reported: Option<Reported>,
+    #[serde(flatten)]
+    pub medication: Medication,
+    pub subject: Reference,
+    pub encounter: Option<Reference>,
+    #[serde(rename = "authoredOn")]
+    pub authored_on: Option<FHIRDateTime>,
+    pub requester: Option<Reference>,
+    pub performer: Option<Reference>,
+    pub recorder: Option<Reference>,
+    #[serde(rename = "reasonCode", default)]
+    pub reason_codes: Vec<CodeableConcept>,
+    #[serde(rename = "reasonReference", default)]
+    pub reason_references: Vec<Reference>,
+    /// Whether the medication is `acute`, `continuous` or `seasonal`.
+    #[serde(rename = "courseOfTherapyType")]
+    pub course_of_therapy_type: Option<CodeableConcept>,
+    #[serde(rename = "dosageInstruction", default)]
+    pub dosage_instructions: Vec<Dosage>,
+    #[serde(rename = "dispenseRequest")]
+    pub dispense_request: Option<DispenseRequest>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum MedicationRequestStatus {
+    Active,
+    OnHold,
+    Cancelled,
+    Completed,
+    EnteredInError,
+    Stopped,
+    Draft,
+    Unknown,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum MedicationRequestIntent {
+    Proposal,
+    Plan,
+    Order,
+    OriginalOrder,
+    ReflexOrder,
+    FillerOrder,
+    InstanceOrder,
+    Option,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum MedicationRequestPriority {
+    Routine,
+    Urgent,
+    Asap,
+    Stat,
+}
+
+choice_type! {
+    /// Whether a `MedicationRequest` was reported rather than recorded from
+    /// its source, or who reported it.
+    pub enum Reported {
+        "reportedBoolean" => Boolean(bool),
+        "reportedReference" => Reference(Box<Reference>),
+    }
+}
+
+choice_type! {
+    /// The medication of a `MedicationRequest`, either coded, e.g. as a dm+d
+    /// code, or a reference to a `Medication` resource.
+    pub enum Medication {
+        "medicationCodeableConcept" => CodeableConcept(CodeableConcept),
+        "medicationReference" => Reference(Box<Reference>),
+    }
+}
+
+/// A `DispenseRequest` is how much of a medication should be supplied, and
+/// for how long.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct DispenseRequest {
+    /// When the prescription may be dispensed.
+    #[serde(rename = "validityPeriod")]
+    pub validity_period: Option<Period>,
+    #[serde(rename = "numberOfRepeatsAllowed")]
+    pub number_of_repeats_allowed: Option<u32>,
+    pub quantity: Option<Quantity>,
+    /// How long each supply should last.
+    #[serde(rename = "expectedSupplyDuration")]
+    pub expected_supply_duration: Option<Quantity>,
+    pub performer: Option<Reference>,
+}
+
+impl MedicationRequest {
+    /// The name of the medication, from its code or the reference to it.
+    pub fn medication_name(&self) -> Option<&str> {
+        match &self.medication {
+            Medication::CodeableConcept(concept) => {
+                concept.text.as_deref().or_else(|| {
+                    concept
+                        .codings
+                        .iter()
+                        .find_map(|coding| coding.display.as_deref())
+                })
+            }
+            Medication::Reference(reference) => reference.display.as_deref(),
+        }
+    }
+
+    /// Whether the medication is currently prescribed.
+    pub fn is_active(&self) -> bool {
+        self.status == MedicationRequestStatus::Active
+            && self.do_not_perform != Some(true)
+    }
+}
+
+scalar_schema!(
+    MedicationRequestStatus,
+    MedicationRequestIntent,
+    MedicationRequestPriority
+);
+
+impl Schema for MedicationRequest {
+    fn shape() -> Shape {
+        let mut fields = vec![
+            ("id", Option::<String>::shape()),
+            ("identifier", Vec::<Identifier>::shape()),
+            ("status", MedicationRequestStatus::shape()),
+            ("statusReason", Option::<CodeableConcept>::shape()),
+            ("intent", MedicationRequestIntent::shape()),
+            ("category", Vec::<CodeableConcept>::shape()),
+            ("priority", Option::<MedicationRequestPriority>::shape()),
+            ("doNotPerform", Option::<bool>::shape()),
+            ("subject", Reference::shape()),
+            ("encounter", Option::<Reference>::shape()),
+            ("authoredOn", Option::<FHIRDateTime>::shape()),
+            ("requester", Option::<Reference>::shape()),
+            ("performer", Option::<Reference>::shape()),
+            ("recorder", Option::<Reference>::shape()),
+            ("reasonCode", Vec::<CodeableConcept>::shape()),
+            ("reasonReference", Vec::<Reference>::shape()),
+            ("courseOfTherapyType", Option::<CodeableConcept>::shape()),
+            ("dosageInstruction", Vec::<Dosage>::shape()),
+            ("dispenseRequest", Option::<DispenseRequest>::shape()),
+        ];
+        fields.extend(Reported::fields());
+        fields.extend(Medication::fields());
+        Shape::Record(fields)
+    }
+}
+
+impl Schema for DispenseRequest {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("validityPeriod", Option::<Period>::shape()),
+            ("numberOfRepeatsAllowed", Option::<u32>::shape()),
+            ("quantity", Option::<Quantity>::shape()),
+            ("expectedSupplyDuration", Option::<Quantity>::shape()),
+            ("performer", Option::<Reference>::shape()),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::dosage::AsNeeded;
+    use crate::data::schema::assert_shape_matches;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_deserialize() {
+        let request = serde_json::from_str::<MedicationRequest>(
+            r#"{
+                "resourceType": "MedicationRequest",
+                "status": "active",
+                "intent": "order",
+                "medicationCodeableConcept": {
+                    "coding": [{"code": "39720311000001101",
+                                "display": "Paracetamol 500mg tablets"}]
+                },
+                "subject": {"reference": "Patient/example"},
+                "authoredOn": "2020-03-04",
+                "dosageInstruction": [{"text": "One tablet four times a day"}],
+                "dispenseRequest": {
+                    "quantity": {"value": 32, "unit": "tablet"},
+                    "numberOfRepeatsAllowed": 1
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(request.is_active());
+        assert_eq!(
+            Some("Paracetamol 500mg tablets"),
+            request.medication_name()
+        );
+        assert_eq!(
+            Some("One tablet four times a day"),
+            request.dosage_instructions[0].text.as_deref()
+        );
+        assert_eq!(
+            Some(1),
+            request
+                .dispense_request
+                .and_then(|dispense| dispense.number_of_repeats_allowed)
+        );
+    }
+
+    #[test]
+    fn test_medication_is_required() {
+        let request = serde_json::from_str::<MedicationRequest>(
+            r#"{
+                "status": "on-hold",
+                "intent": "original-order",
+                "medicationReference": {"display": "Salbutamol inhaler"},
+                "subject": {}
+            }"#,
+        )
+        .unwrap();
+        assert!(!request.is_active());
+        assert_eq!(Some("Salbutamol inhaler"), request.medication_name());
+
+        assert!(serde_json::from_str::<MedicationRequest>(
+            r#"{"status": "active", "intent": "order", "subject": {}}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_shape_matches_serialized_form() {
+        let concept = CodeableConcept {
+            text: Some("Paracetamol".to_string()),
+            ..CodeableConcept::default()
+        };
+        let reference = Reference {
+            reference: Some("Practitioner/1".to_string()),
+            ..Reference::default()
+        };
+        let request = MedicationRequest {
+            id: Some("paracetamol".to_string()),
+            identifiers: vec![Identifier::default()],
+            status: MedicationRequestStatus::Active,
+            status_reason: Some(concept.clone()),
+            intent: MedicationRequestIntent::Order,
+            categories: vec![concept.clone()],
+            priority: Some(MedicationRequestPriority::Routine),
+            do_not_perform: Some(false),
+            reported: Some(Reported::Boolean(false)),
+            medication: Medication::CodeableConcept(concept.clone()),
+            subject: reference.clone(),
+            encounter: Some(reference.clone()),
+            authored_on: Some("2020-03-04".parse().unwrap()),
+            requester: Some(reference.clone()),
+            performer: Some(reference.clone()),
+            recorder: Some(reference.clone()),
+            reason_codes: vec![concept.clone()],
+            reason_references: vec![reference.clone()],
+            course_of_therapy_type: Some(concept),
+            dosage_instructions: vec![Dosage {
+                as_needed: Some(AsNeeded::Boolean(true)),
+                ..Dosage::default()
+            }],
+            dispense_request: Some(DispenseRequest {
+                validity_period: Some(Period::default()),
+                number_of_repeats_allowed: Some(1),
+                quantity: Some(Quantity::default()),
+                expected_supply_duration: Some(Quantity::default()),
+                performer: Some(reference),
+            }),
+        };
+
+        assert_shape_matches(
+            &MedicationRequest::shape(),
+            &serde_json::to_value(&request).unwrap(),
+        );
+    }
+}
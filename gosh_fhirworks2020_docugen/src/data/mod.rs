@@ -1,2 +1,13 @@
+pub mod allergy_intolerance;
+pub mod appointment;
+pub mod condition;
+pub mod datatypes;
+pub mod dosage;
+pub mod encounter;
 pub mod fhir_date;
+pub mod filters;
+pub mod locale;
+pub mod medication_request;
+pub mod observation;
 pub mod patient;
+pub mod schema;
@@ -0,0 +1,363 @@
+use super::datatypes::{
+    choice_type, CodeableConcept, Identifier, Period, Quantity, Range, Ratio,
+    Reference,
+};
+use super::fhir_date::FHIRDateTime;
+use super::schema::{scalar_schema, Schema, Shape};
+use serde::{Deserialize, Serialize};
+
+/// Each `Observation` is a measurement or an assertion about a `Patient`, as
+/// described in FHIR v4.0.1's `Observation` JSON template, e.g. a laboratory
+/// result or a vital sign. The `note`, `specimen` and `device` elements, the
+/// `Timing` type of `effective[x]` and the `SampledData` type of `value[x]`
+/// are not modelled.
+///
+/// # Reference
+///
+/// - [FHIR | Observation](https://www.hl7.org/fhir/observation.html#resource)
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Observation {
+    pub id: Option<String>,
+    #[serde(rename = "identifier", default)]
+    pub identifiers: Vec<Identifier>,
+    pub status: ObservationStatus,
+    /// The kinds of observation, e.g. `laboratory` or `vital-signs`.
+    #[serde(rename = "category", default)]
+    pub categories: Vec<CodeableConcept>,
+    /// What was observed, e.g. the LOINC code of a test.
+    pub code: CodeableConcept,
+    /// Who or what was observed, usually a `Patient`.
+    pub subject: Option<Reference>,
+    pub encounter: Option<Reference>,
+    #[serde(flatten, deserialize_with = "Effective::deserialize_optional")]
+    pub effective: Option<Effective>,
+    /// When the result was made available.
+    pub issued: Option<FHIRDateTime>,
+    #[serde(rename = "performer", default)]
+    pub performers: Vec<Reference>,
+    #[serde(
+        flatten,
+        deserialize_with = "ObservationValue::deserialize_optional"
+    )]
+    pub value: Option<ObservationValue>,
+    /// Why there is no value, e.g. because the test was not performed.
+    #[serde(rename = "dataAbsentReason")]
+    pub data_absent_reason: Option<CodeableConcept>,
+    /// How the value compares to the reference ranges, e.g. `H` for high.
+    #[serde(rename = "interpretation", default)]
+    pub interpretations: Vec<CodeableConcept>,
+    #[serde(rename = "bodySite")]
+    pub body_site: Option<CodeableConcept>,
+    pub method: Option<CodeableConcept>,
+    #[serde(rename = "referenceRange", default)]
+    pub reference_ranges: Vec<ReferenceRange>,
+    /// Observations grouped by this one, e.g. the results of a panel.
+    #[serde(rename = "hasMember", default)]
+    pub has_members: Vec<Reference>,
+    #[serde(rename = "derivedFrom", default)]
+    pub derived_from: Vec<Reference>,
+    /// Observations made together, e.g. the systolic and diastolic blood
+    /// pressures.
+    #[serde(rename = "component", default)]
+    pub components: Vec<ObservationComponent>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum ObservationStatus {
+    Registered,
+    Preliminary,
+    Final,
+    Amended,
+    Corrected,
+    Cancelled,
+    EnteredInError,
+    Unknown,
+}
+
+choice_type! {
+    /// When an `Observation` was made.
+    pub enum Effective {
+        "effectiveDateTime" => DateTime(FHIRDateTime),
+        "effectivePeriod" => Period(Period),
+        "effectiveInstant" => Instant(FHIRDateTime),
+    }
+}
+
+choice_type! {
+    /// The result of an `Observation` or of one of its components.
+    pub enum ObservationValue {
+        "valueQuantity" => Quantity(Quantity),
+        "valueCodeableConcept" => CodeableConcept(CodeableConcept),
+        "valueString" => String(String),
+        "valueBoolean" => Boolean(bool),
+        "valueInteger" => Integer(i64),
+        "valueRange" => Range(Range),
+        "valueRatio" => Ratio(Ratio),
+        "valueTime" => Time(String),
+        "valueDateTime" => DateTime(FHIRDateTime),
+        "valuePeriod" => Period(Period),
+    }
+}
+
+/// A `ReferenceRange` is the range of values of an `Observation` expected
+/// in some population, e.g. the normal range of a test for children.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct ReferenceRange {
+    pub low: Option<Quantity>,
+    pub high: Option<Quantity>,
+    /// What the range means, e.g. `normal` or `therapeutic`.
+    #[serde(rename = "type")]
+    pub type_: Option<CodeableConcept>,
+    /// The populations the range applies to.
+    #[serde(rename = "appliesTo", default)]
+    pub applies_to: Vec<CodeableConcept>,
+    /// The ages the range applies to.
+    pub age: Option<Range>,
+    /// The range as text, e.g. `> 60` or `Negative`.
+    pub text: Option<String>,
+}
+
+/// An `ObservationComponent` is one of several results of an `Observation`
+/// made together.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ObservationComponent {
+    pub code: CodeableConcept,
+    #[serde(
+        flatten,
+        deserialize_with = "ObservationValue::deserialize_optional"
+    )]
+    pub value: Option<ObservationValue>,
+    #[serde(rename = "dataAbsentReason")]
+    pub data_absent_reason: Option<CodeableConcept>,
+    #[serde(rename = "interpretation", default)]
+    pub interpretations: Vec<CodeableConcept>,
+    #[serde(rename = "referenceRange", default)]
+    pub reference_ranges: Vec<ReferenceRange>,
+}
+
+impl Observation {
+    /// Whether the observation is `final`, or was changed after being final.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self.status,
+            ObservationStatus::Final
+                | ObservationStatus::Amended
+                | ObservationStatus::Corrected
+        )
+    }
+}
+
+scalar_schema!(ObservationStatus);
+
+impl Schema for Observation {
+    fn shape() -> Shape {
+        let mut fields = vec![
+            ("id", Option::<String>::shape()),
+            ("identifier", Vec::<Identifier>::shape()),
+            ("status", ObservationStatus::shape()),
+            ("category", Vec::<CodeableConcept>::shape()),
+            ("code", CodeableConcept::shape()),
+            ("subject", Option::<Reference>::shape()),
+            ("encounter", Option::<Reference>::shape()),
+            ("issued", Option::<FHIRDateTime>::shape()),
+            ("performer", Vec::<Reference>::shape()),
+            ("dataAbsentReason", Option::<CodeableConcept>::shape()),
+            ("interpretation", Vec::<CodeableConcept>::shape()),
+            ("bodySite", Option::<CodeableConcept>::shape()),
+            ("method", Option::<CodeableConcept>::shape()),
+            ("referenceRange", Vec::<ReferenceRange>::shape()),
+            ("hasMember", Vec::<Reference>::shape()),
+            ("derivedFrom", Vec::<Reference>::shape()),
+            ("component", Vec::<ObservationComponent>::shape()),
+        ];
+        fields.extend(Effective::fields());
+        fields.extend(ObservationValue::fields());
+        Shape::Record(fields)
+    }
+}
+
+impl Schema for ReferenceRange {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("low", Option::<Quantity>::shape()),
+            ("high", Option::<Quantity>::shape()),
+            ("type", Option::<CodeableConcept>::shape()),
+            ("appliesTo", Vec::<CodeableConcept>::shape()),
+            ("age", Option::<Range>::shape()),
+            ("text", Option::<String>::shape()),
+        ])
+    }
+}
+
+impl Schema for ObservationComponent {
+    fn shape() -> Shape {
+        let mut fields = vec![
+            ("code", CodeableConcept::shape()),
+            ("dataAbsentReason", Option::<CodeableConcept>::shape()),
+            ("interpretation", Vec::<CodeableConcept>::shape()),
+            ("referenceRange", Vec::<ReferenceRange>::shape()),
+        ];
+        fields.extend(ObservationValue::fields());
+        Shape::Record(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::schema::assert_shape_matches;
+    use pretty_assertions::assert_eq;
+
+    const RAW: &str = r#"{
+        "resourceType": "Observation",
+        "id": "glucose",
+        "status": "final",
+        "category": [{"coding": [{"code": "laboratory"}]}],
+        "code": {
+            "coding": [{"system": "http://loinc.org", "code": "15074-8"}],
+            "text": "Glucose"
+        },
+        "subject": {"reference": "Patient/example"},
+        "effectivePeriod": {"start": "2013-04-02T09:30:10+01:00"},
+        "issued": "2013-04-03T15:30:10.000+01:00",
+        "valueQuantity": {
+            "value": 6.3,
+            "unit": "mmol/l",
+            "system": "http://unitsofmeasure.org",
+            "code": "mmol/L"
+        },
+        "interpretation": [{"coding": [{"code": "H"}]}],
+        "referenceRange": [
+            {"low": {"value": 3.1, "unit": "mmol/l"},
+             "high": {"value": 6.2, "unit": "mmol/l"}}
+        ]
+    }"#;
+
+    #[test]
+    fn test_deserialize() {
+        let observation = serde_json::from_str::<Observation>(RAW).unwrap();
+
+        assert!(observation.is_final());
+        assert_eq!(Some("Glucose"), observation.code.text.as_deref());
+        assert_eq!(
+            Some(Effective::Period(Period {
+                start: Some("2013-04-02T09:30:10+01:00".parse().unwrap()),
+                end: None,
+            })),
+            observation.effective
+        );
+        match &observation.value {
+            Some(ObservationValue::Quantity(quantity)) => {
+                assert_eq!("6.3 mmol/l", quantity.to_string())
+            }
+            value => panic!("unexpected value {:?}", value),
+        }
+        assert_eq!(
+            Some(6.2),
+            observation.reference_ranges[0]
+                .high
+                .as_ref()
+                .and_then(|high| high.value)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_components() {
+        let observation = serde_json::from_str::<Observation>(
+            r#"{
+                "status": "entered-in-error",
+                "code": {"text": "Blood pressure"},
+                "effectiveDateTime": "2012-09-17",
+                "component": [
+                    {"code": {"text": "Systolic"},
+                     "valueQuantity": {"value": 107, "unit": "mmHg"}},
+                    {"code": {"text": "Diastolic"},
+                     "dataAbsentReason": {"text": "Not performed"}}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(!observation.is_final());
+        assert_eq!(None, observation.value);
+        assert_eq!(
+            Some(ObservationValue::Quantity(Quantity {
+                value: Some(107.0),
+                unit: Some("mmHg".to_string()),
+                ..Quantity::default()
+            })),
+            observation.components[0].value
+        );
+        assert_eq!(None, observation.components[1].value);
+        assert!(serde_json::from_str::<Observation>(
+            r#"{"status": "done", "code": {}}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_shape_matches_serialized_form() {
+        let quantity = Quantity {
+            value: Some(6.3),
+            unit: Some("mmol/l".to_string()),
+            ..Quantity::default()
+        };
+        let concept = CodeableConcept {
+            text: Some("Glucose".to_string()),
+            ..CodeableConcept::default()
+        };
+        let reference = Reference {
+            reference: Some("Patient/example".to_string()),
+            ..Reference::default()
+        };
+        let reference_range = ReferenceRange {
+            low: Some(quantity.clone()),
+            high: Some(quantity.clone()),
+            type_: Some(concept.clone()),
+            applies_to: vec![concept.clone()],
+            age: Some(Range {
+                low: Some(quantity.clone()),
+                high: Some(quantity.clone()),
+            }),
+            text: Some("3.1 to 6.2".to_string()),
+        };
+        let observation = Observation {
+            id: Some("glucose".to_string()),
+            identifiers: vec![Identifier::default()],
+            status: ObservationStatus::Final,
+            categories: vec![concept.clone()],
+            code: concept.clone(),
+            subject: Some(reference.clone()),
+            encounter: Some(reference.clone()),
+            effective: Some(Effective::Instant(
+                "2013-04-02T09:30:10Z".parse().unwrap(),
+            )),
+            issued: Some("2013-04-03".parse().unwrap()),
+            performers: vec![reference.clone()],
+            value: Some(ObservationValue::Ratio(Ratio {
+                numerator: Some(quantity.clone()),
+                denominator: Some(quantity),
+            })),
+            data_absent_reason: Some(concept.clone()),
+            interpretations: vec![concept.clone()],
+            body_site: Some(concept.clone()),
+            method: Some(concept.clone()),
+            reference_ranges: vec![reference_range.clone()],
+            has_members: vec![reference.clone()],
+            derived_from: vec![reference],
+            components: vec![ObservationComponent {
+                code: concept.clone(),
+                value: Some(ObservationValue::Integer(107)),
+                data_absent_reason: Some(concept.clone()),
+                interpretations: vec![concept],
+                reference_ranges: vec![reference_range],
+            }],
+        };
+
+        assert_shape_matches(
+            &Observation::shape(),
+            &serde_json::to_value(&observation).unwrap(),
+        );
+    }
+}
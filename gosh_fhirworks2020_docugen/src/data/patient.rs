@@ -1,30 +1,652 @@
-use super::fhir_date::FHIRDate;
-use serde::Deserialize;
+use super::datatypes::{
+    choice_type, Address, AdministrativeGender, Attachment, CodeableConcept,
+    ContactPoint, Identifier, Period, Reference,
+};
+use super::fhir_date::{FHIRDate, FHIRDateTime};
+use super::schema::{scalar_schema, Schema, Shape};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// System of the `Identifier`s holding NHS numbers.
+pub const NHS_NUMBER_SYSTEM: &str = "https://fhir.nhs.uk/Id/nhs-number";
 
 /// Each `Patient` is a resource as described in FHIR v4.0.1's `Patient` JSON
-/// template. This `Patient` definition is only a subset of that in the official
-/// FHIR.
+/// template. Every element of the resource is modelled, except for the
+/// `meta`, `text`, `extension` and `contained` elements common to all
+/// resources.
 ///
 /// # Reference
 ///
 /// - [FHIR | Patient](https://www.hl7.org/fhir/patient.html#resource)
-#[derive(Debug, PartialEq, Deserialize, Clone)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
 pub struct Patient {
-    #[serde(rename = "name")]
+    pub id: Option<String>,
+    #[serde(rename = "identifier", default)]
+    pub identifiers: Vec<Identifier>,
+    /// Whether the record of the patient is in active use.
+    pub active: Option<bool>,
+    #[serde(rename = "name", default)]
     pub names: Vec<HumanName>,
+    #[serde(default)]
+    pub telecom: Vec<ContactPoint>,
+    pub gender: Option<AdministrativeGender>,
     #[serde(rename = "birthDate")]
-    pub birth_date: FHIRDate,
+    pub birth_date: Option<FHIRDate>,
+    #[serde(flatten, deserialize_with = "Deceased::deserialize_optional")]
+    pub deceased: Option<Deceased>,
+    #[serde(rename = "address", default)]
+    pub addresses: Vec<Address>,
+    #[serde(rename = "maritalStatus")]
+    pub marital_status: Option<CodeableConcept>,
+    #[serde(flatten, deserialize_with = "MultipleBirth::deserialize_optional")]
+    pub multiple_birth: Option<MultipleBirth>,
+    #[serde(rename = "photo", default)]
+    pub photos: Vec<Attachment>,
+    #[serde(rename = "contact", default)]
+    pub contacts: Vec<PatientContact>,
+    #[serde(rename = "communication", default)]
+    pub communications: Vec<PatientCommunication>,
+    #[serde(rename = "generalPractitioner", default)]
+    pub general_practitioners: Vec<Reference>,
+    #[serde(rename = "managingOrganization")]
+    pub managing_organization: Option<Reference>,
+    #[serde(rename = "link", default)]
+    pub links: Vec<PatientLink>,
+}
+
+impl Patient {
+    /// The value of the first `Identifier` of the patient in `system`.
+    pub fn identifier(&self, system: &str) -> Option<&str> {
+        self.identifiers
+            .iter()
+            .find(|identifier| identifier.system.as_deref() == Some(system))
+            .and_then(|identifier| identifier.value.as_deref())
+    }
+
+    pub fn nhs_number(&self) -> Option<&str> {
+        self.identifier(NHS_NUMBER_SYSTEM)
+    }
+
+    /// The name to address the patient by on `date`, see
+    /// `HumanName::preferred`.
+    pub fn name_on(&self, date: &FHIRDate) -> Option<&HumanName> {
+        HumanName::preferred(&self.names, date)
+    }
+
+    /// The name to address the patient by today.
+    pub fn name(&self) -> Option<&HumanName> {
+        self.name_on(&FHIRDate::today())
+    }
+
+    /// Whether the patient is known to have died, either because it is
+    /// recorded as such or because a date of death is recorded.
+    pub fn is_deceased(&self) -> bool {
+        match &self.deceased {
+            Some(Deceased::Boolean(deceased)) => *deceased,
+            Some(Deceased::DateTime(_)) => true,
+            None => false,
+        }
+    }
+}
+
+choice_type! {
+    /// Whether a `Patient` has died, or when.
+    pub enum Deceased {
+        "deceasedBoolean" => Boolean(bool),
+        "deceasedDateTime" => DateTime(FHIRDateTime),
+    }
+}
+
+choice_type! {
+    /// Whether a `Patient` is part of a multiple birth, or their birth order.
+    pub enum MultipleBirth {
+        "multipleBirthBoolean" => Boolean(bool),
+        "multipleBirthInteger" => Integer(u32),
+    }
 }
 
-/// Each `Patient` has one or more `HumanName`s. A `HumanName` contains more
-/// attributes in the official specification so this definition is a subset of
-/// the official definition.
+/// Each `Patient` has one or more `HumanName`s, e.g. their current official
+/// name and their maiden name.
 ///
 /// # Reference
 ///
 /// - [Human Name](https://www.hl7.org/fhir/datatypes.html#HumanName).
-#[derive(Debug, PartialEq, Deserialize, Clone)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
 pub struct HumanName {
+    #[serde(rename = "use")]
+    pub use_: Option<NameUse>,
+    /// The whole name, as it should be displayed.
+    pub text: Option<String>,
     pub family: Option<String>,
+    #[serde(default)]
     pub given: Vec<String>,
+    /// Parts coming before the name, e.g. `Dr`.
+    #[serde(default)]
+    pub prefix: Vec<String>,
+    /// Parts coming after the name, e.g. `PhD`.
+    #[serde(default)]
+    pub suffix: Vec<String>,
+    /// When the name was in use. A name without a period is always valid.
+    pub period: Option<Period>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum NameUse {
+    Usual,
+    Official,
+    Temp,
+    Nickname,
+    Anonymous,
+    Old,
+    Maiden,
+}
+
+/// The ways of writing a `HumanName`, named in templates as the argument of
+/// the `name` filter, e.g. `{{ patient.name | name("formal") }}`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NameStyle {
+    /// `full`: every part of the name, e.g. `Dr Jane Alice Smith`.
+    Full,
+    /// `formal`: the first given name and the initials of the others, e.g.
+    /// `Dr Jane A. Smith`.
+    Formal,
+    /// `family_first`: the family name in uppercase first, as in lists sorted
+    /// by family name, e.g. `SMITH, Jane Alice`.
+    FamilyFirst,
+    /// `given`: the first given name, e.g. `Jane`.
+    Given,
+    /// `family`: the family name, e.g. `Smith`.
+    Family,
+    /// `text`: the `text` of the name, or the `full` name without one.
+    Text,
+}
+
+impl FromStr for NameStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(NameStyle::Full),
+            "formal" => Ok(NameStyle::Formal),
+            "family_first" => Ok(NameStyle::FamilyFirst),
+            "given" => Ok(NameStyle::Given),
+            "family" => Ok(NameStyle::Family),
+            "text" => Ok(NameStyle::Text),
+            _ => Err(format!("unknown name style `{}`", s)),
+        }
+    }
+}
+
+impl HumanName {
+    /// Whether the name was in use on `date`.
+    pub fn is_valid_on(&self, date: &FHIRDate) -> bool {
+        self.period
+            .as_ref()
+            .is_none_or(|period| period.contains(date))
+    }
+
+    /// The name to address someone by on `date`, among their `names`: the
+    /// first `official` name valid on that date, else the first `usual` one,
+    /// else the first valid name without a use. Names of other uses, such as
+    /// nicknames or maiden names, are only picked when there is no other.
+    pub fn preferred<'n>(
+        names: &'n [HumanName],
+        date: &FHIRDate,
+    ) -> Option<&'n HumanName> {
+        names
+            .iter()
+            .filter(|name| name.is_valid_on(date))
+            .min_by_key(|name| match name.use_ {
+                Some(NameUse::Official) => 0,
+                Some(NameUse::Usual) => 1,
+                None => 2,
+                Some(NameUse::Old) | Some(NameUse::Maiden) => 4,
+                Some(_) => 3,
+            })
+    }
+
+    /// Write the name in `style`. Missing parts are left out.
+    pub fn format(&self, style: NameStyle) -> String {
+        let first = self.given.first().map(String::as_str);
+        let parts: Vec<&str> = match style {
+            NameStyle::Full => self
+                .prefix
+                .iter()
+                .chain(&self.given)
+                .chain(&self.family)
+                .chain(&self.suffix)
+                .map(String::as_str)
+                .collect(),
+            NameStyle::Formal => {
+                let initials = self.given.iter().skip(1).map(|given| {
+                    given
+                        .chars()
+                        .next()
+                        .map(|c| format!("{}.", c.to_uppercase()))
+                        .unwrap_or_default()
+                });
+                let parts = self
+                    .prefix
+                    .iter()
+                    .cloned()
+                    .chain(first.map(str::to_string))
+                    .chain(initials)
+                    .chain(self.family.clone())
+                    .chain(self.suffix.iter().cloned())
+                    .collect::<Vec<_>>();
+                return join_parts(&parts);
+            }
+            NameStyle::FamilyFirst => {
+                let given = self.given.join(" ");
+                return match &self.family {
+                    Some(family) if !given.is_empty() => {
+                        format!("{}, {}", family.to_uppercase(), given)
+                    }
+                    Some(family) => family.to_uppercase(),
+                    None => given,
+                };
+            }
+            NameStyle::Given => first.into_iter().collect(),
+            NameStyle::Family => self.family.as_deref().into_iter().collect(),
+            NameStyle::Text => match &self.text {
+                Some(text) => vec![text.as_str()],
+                None => return self.format(NameStyle::Full),
+            },
+        };
+        join_parts(&parts)
+    }
+}
+
+/// Join the non-empty `parts` of a name with spaces.
+fn join_parts<S: AsRef<str>>(parts: &[S]) -> String {
+    parts
+        .iter()
+        .map(AsRef::as_ref)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A `PatientContact` is someone to contact about a `Patient`, e.g. a parent
+/// or a guardian.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct PatientContact {
+    /// The relationships of the contact to the patient, e.g. `mother`.
+    #[serde(rename = "relationship", default)]
+    pub relationships: Vec<CodeableConcept>,
+    pub name: Option<HumanName>,
+    #[serde(default)]
+    pub telecom: Vec<ContactPoint>,
+    pub address: Option<Address>,
+    pub gender: Option<AdministrativeGender>,
+    pub organization: Option<Reference>,
+    pub period: Option<Period>,
+}
+
+/// A `PatientCommunication` is a language a `Patient` may be communicated
+/// with in.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct PatientCommunication {
+    /// The language, coded as a language tag such as `en-GB`.
+    pub language: CodeableConcept,
+    pub preferred: Option<bool>,
+}
+
+/// A `PatientLink` links a `Patient` to another resource about the same
+/// person, e.g. a duplicate record it was merged with.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct PatientLink {
+    pub other: Reference,
+    #[serde(rename = "type")]
+    pub type_: LinkType,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+pub enum LinkType {
+    #[serde(rename = "replaced-by")]
+    ReplacedBy,
+    #[serde(rename = "replaces")]
+    Replaces,
+    #[serde(rename = "refer")]
+    Refer,
+    #[serde(rename = "seealso")]
+    SeeAlso,
+}
+
+scalar_schema!(NameUse, LinkType);
+
+impl Schema for Patient {
+    fn shape() -> Shape {
+        let mut fields = vec![
+            ("id", Option::<String>::shape()),
+            ("identifier", Vec::<Identifier>::shape()),
+            ("active", Option::<bool>::shape()),
+            ("name", Vec::<HumanName>::shape()),
+            ("telecom", Vec::<ContactPoint>::shape()),
+            ("gender", Option::<AdministrativeGender>::shape()),
+            ("birthDate", Option::<FHIRDate>::shape()),
+            ("address", Vec::<Address>::shape()),
+            ("maritalStatus", Option::<CodeableConcept>::shape()),
+            ("photo", Vec::<Attachment>::shape()),
+            ("contact", Vec::<PatientContact>::shape()),
+            ("communication", Vec::<PatientCommunication>::shape()),
+            ("generalPractitioner", Vec::<Reference>::shape()),
+            ("managingOrganization", Option::<Reference>::shape()),
+            ("link", Vec::<PatientLink>::shape()),
+        ];
+        fields.extend(Deceased::fields());
+        fields.extend(MultipleBirth::fields());
+        Shape::Record(fields)
+    }
+}
+
+impl Schema for HumanName {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("use", Option::<NameUse>::shape()),
+            ("text", Option::<String>::shape()),
+            ("family", Option::<String>::shape()),
+            ("given", Vec::<String>::shape()),
+            ("prefix", Vec::<String>::shape()),
+            ("suffix", Vec::<String>::shape()),
+            ("period", Option::<Period>::shape()),
+        ])
+    }
+}
+
+impl Schema for PatientContact {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("relationship", Vec::<CodeableConcept>::shape()),
+            ("name", Option::<HumanName>::shape()),
+            ("telecom", Vec::<ContactPoint>::shape()),
+            ("address", Option::<Address>::shape()),
+            ("gender", Option::<AdministrativeGender>::shape()),
+            ("organization", Option::<Reference>::shape()),
+            ("period", Option::<Period>::shape()),
+        ])
+    }
+}
+
+impl Schema for PatientCommunication {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("language", CodeableConcept::shape()),
+            ("preferred", Option::<bool>::shape()),
+        ])
+    }
+}
+
+impl Schema for PatientLink {
+    fn shape() -> Shape {
+        Shape::Record(vec![
+            ("other", Reference::shape()),
+            ("type", LinkType::shape()),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::datatypes::{
+        AddressUse, Coding, ContactPointSystem, ContactPointUse,
+    };
+    use crate::data::schema::assert_shape_matches;
+    use pretty_assertions::assert_eq;
+
+    const RAW: &str = r#"{
+        "resourceType": "Patient",
+        "id": "example",
+        "identifier": [
+            {
+                "use": "official",
+                "system": "https://fhir.nhs.uk/Id/nhs-number",
+                "value": "9434765919"
+            }
+        ],
+        "active": true,
+        "name": [{"family": "Smith", "given": ["Jane", "Alice"]}],
+        "telecom": [
+            {"system": "phone", "value": "020 7405 9200", "use": "mobile"}
+        ],
+        "gender": "female",
+        "birthDate": "2012-03-04",
+        "deceasedBoolean": false,
+        "address": [
+            {
+                "use": "home",
+                "line": ["Great Ormond Street"],
+                "city": "London",
+                "postalCode": "WC1N 3JH"
+            }
+        ],
+        "maritalStatus": {"text": "Never Married"},
+        "multipleBirthInteger": 2,
+        "contact": [
+            {
+                "relationship": [{"text": "mother"}],
+                "name": {"family": "Smith", "given": ["Mary"]}
+            }
+        ],
+        "communication": [
+            {"language": {"coding": [{"code": "en-GB"}]}, "preferred": true}
+        ],
+        "generalPractitioner": [{"reference": "Practitioner/1"}],
+        "link": [{"other": {"reference": "Patient/2"}, "type": "seealso"}]
+    }"#;
+
+    #[test]
+    fn test_deserialize() {
+        let patient = serde_json::from_str::<Patient>(RAW).unwrap();
+
+        assert_eq!(Some("9434765919"), patient.nhs_number());
+        assert_eq!(Some(AdministrativeGender::Female), patient.gender);
+        assert_eq!(Some(Deceased::Boolean(false)), patient.deceased);
+        assert!(!patient.is_deceased());
+        assert_eq!(Some(MultipleBirth::Integer(2)), patient.multiple_birth);
+        assert_eq!(Some(ContactPointUse::Mobile), patient.telecom[0].use_);
+        assert_eq!(Some(AddressUse::Home), patient.addresses[0].use_);
+        assert_eq!(
+            Some("Mary"),
+            patient.contacts[0]
+                .name
+                .as_ref()
+                .map(|name| name.given[0].as_str())
+        );
+        assert_eq!(LinkType::SeeAlso, patient.links[0].type_);
+        assert_eq!(
+            Some("Practitioner/1"),
+            patient.general_practitioners[0].reference.as_deref()
+        );
+    }
+
+    #[test]
+    fn test_deserialize_minimal() {
+        let patient = serde_json::from_str::<Patient>(
+            r#"{"deceasedDateTime": "2020-03-04T09:00:00Z"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(Vec::<HumanName>::new(), patient.names);
+        assert_eq!(None, patient.birth_date);
+        assert_eq!(None, patient.nhs_number());
+        assert!(patient.is_deceased());
+        assert!(serde_json::from_str::<Patient>(r#"{"gender": "f"}"#).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_invalid_choice() {
+        assert!(serde_json::from_str::<Patient>(
+            r#"{"deceasedBoolean": true, "deceasedDateTime": "2020-01-01"}"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<Patient>(
+            r#"{"deceasedDateTime": "not a date"}"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<Patient>(
+            r#"{"multipleBirthInteger": -1}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_shape_matches_serialized_form() {
+        let concept = CodeableConcept {
+            codings: vec![Coding {
+                system: Some("urn:ietf:bcp:47".to_string()),
+                code: Some("en-GB".to_string()),
+                ..Coding::default()
+            }],
+            text: Some("English".to_string()),
+        };
+        let reference = Reference {
+            reference: Some("Practitioner/1".to_string()),
+            ..Reference::default()
+        };
+        let telecom = vec![ContactPoint {
+            system: Some(ContactPointSystem::Email),
+            value: Some("jane@example.org".to_string()),
+            ..ContactPoint::default()
+        }];
+        let period = Period {
+            start: Some("2012-03-04".parse().unwrap()),
+            end: None,
+        };
+        let patient = Patient {
+            id: Some("example".to_string()),
+            identifiers: vec![Identifier {
+                system: Some(NHS_NUMBER_SYSTEM.to_string()),
+                value: Some("9434765919".to_string()),
+                ..Identifier::default()
+            }],
+            active: Some(true),
+            names: vec![HumanName {
+                use_: Some(NameUse::Official),
+                text: Some("Jane Smith".to_string()),
+                family: Some("Smith".to_string()),
+                given: vec!["Jane".to_string()],
+                prefix: vec!["Miss".to_string()],
+                suffix: vec!["MBE".to_string()],
+                period: Some(period.clone()),
+            }],
+            telecom: telecom.clone(),
+            gender: Some(AdministrativeGender::Female),
+            birth_date: Some("2012-03-04".parse().unwrap()),
+            deceased: Some(Deceased::DateTime(
+                "2020-03-04T09:00:00Z".parse().unwrap(),
+            )),
+            addresses: vec![Address {
+                lines: vec!["Great Ormond Street".to_string()],
+                ..Address::default()
+            }],
+            marital_status: Some(concept.clone()),
+            multiple_birth: Some(MultipleBirth::Boolean(false)),
+            photos: vec![Attachment {
+                url: Some("https://example.org/photo.png".to_string()),
+                ..Attachment::default()
+            }],
+            contacts: vec![PatientContact {
+                relationships: vec![concept.clone()],
+                name: Some(HumanName::default()),
+                telecom,
+                address: Some(Address::default()),
+                gender: Some(AdministrativeGender::Male),
+                organization: Some(reference.clone()),
+                period: Some(period),
+            }],
+            communications: vec![PatientCommunication {
+                language: concept,
+                preferred: Some(true),
+            }],
+            general_practitioners: vec![reference.clone()],
+            managing_organization: Some(reference.clone()),
+            links: vec![PatientLink {
+                other: reference,
+                type_: LinkType::ReplacedBy,
+            }],
+        };
+
+        assert_shape_matches(
+            &Patient::shape(),
+            &serde_json::to_value(&patient).unwrap(),
+        );
+    }
+
+    fn human_name(
+        use_: Option<NameUse>,
+        family: &str,
+        period: Option<&str>,
+    ) -> HumanName {
+        HumanName {
+            use_,
+            family: Some(family.to_string()),
+            period: period.map(|end| Period {
+                start: None,
+                end: Some(end.parse().unwrap()),
+            }),
+            ..HumanName::default()
+        }
+    }
+
+    #[test]
+    fn test_preferred_name() {
+        let names = vec![
+            human_name(Some(NameUse::Nickname), "Smithy", None),
+            human_name(Some(NameUse::Official), "Jones", Some("2015-06-01")),
+            human_name(None, "Smith", None),
+            human_name(Some(NameUse::Usual), "Smith-Jones", None),
+            human_name(Some(NameUse::Maiden), "Brown", None),
+        ];
+
+        assert_eq!(Some("Jones"), family_of(&names, "2015-06-01"));
+        assert_eq!(Some("Smith-Jones"), family_of(&names, "2015-06-02"));
+        assert_eq!(Some("Smith"), family_of(&names[1..3], "2020"));
+        assert_eq!(Some("Smithy"), family_of(&names[..2], "2020"));
+        assert_eq!(None, family_of(&names[1..2], "2020"));
+
+        let patient = Patient {
+            names: names.clone(),
+            ..Patient::default()
+        };
+        assert_eq!(Some(&names[1]), patient.name_on(&"2010".parse().unwrap()));
+    }
+
+    fn family_of<'n>(names: &'n [HumanName], date: &str) -> Option<&'n str> {
+        HumanName::preferred(names, &date.parse().unwrap())
+            .and_then(|name| name.family.as_deref())
+    }
+
+    #[test]
+    fn test_format_name() {
+        let name = HumanName {
+            family: Some("Smith".to_string()),
+            given: vec!["Jane".to_string(), "alice".to_string()],
+            prefix: vec!["Dr".to_string()],
+            suffix: vec!["PhD".to_string()],
+            ..HumanName::default()
+        };
+        let format = |style: &str| name.format(style.parse().unwrap());
+
+        assert_eq!("Dr Jane alice Smith PhD", format("full"));
+        assert_eq!("Dr Jane A. Smith PhD", format("formal"));
+        assert_eq!("SMITH, Jane alice", format("family_first"));
+        assert_eq!("Jane", format("given"));
+        assert_eq!("Smith", format("family"));
+        assert_eq!("Dr Jane alice Smith PhD", format("text"));
+        assert!("short".parse::<NameStyle>().is_err());
+
+        let text = HumanName {
+            text: Some("Jane Smith".to_string()),
+            ..HumanName::default()
+        };
+        assert_eq!("Jane Smith", text.format(NameStyle::Text));
+        assert_eq!("", text.format(NameStyle::Formal));
+        assert_eq!(
+            "SMITH",
+            human_name(None, "Smith", None).format(NameStyle::FamilyFirst)
+        );
+    }
 }
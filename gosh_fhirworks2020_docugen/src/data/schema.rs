@@ -0,0 +1,148 @@
+use super::fhir_date::{FHIRDate, FHIRDateTime};
+
+/// The shape of the data a type is serialized to, which is the shape of the
+/// `Value` filling a template. It lets templates be checked against the data
+/// they will be filled with before any data is fetched.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Shape {
+    /// Text, a number, a boolean or a date.
+    Scalar,
+    List(Box<Shape>),
+    /// Named fields, as they are serialized.
+    Record(Vec<(&'static str, Shape)>),
+    /// Data of any shape, in which every path is accepted.
+    Any,
+}
+
+impl Shape {
+    /// The shape of the field or list item `segment` of a tag identifier. A
+    /// `None` means that the data never has such a field.
+    pub fn get(&self, segment: &str) -> Option<&Shape> {
+        match self {
+            Shape::Record(fields) => fields
+                .iter()
+                .find(|(name, _)| *name == segment)
+                .map(|(_, shape)| shape),
+            Shape::List(item) => segment.parse::<usize>().ok().map(|_| &**item),
+            Shape::Any => Some(&Shape::Any),
+            Shape::Scalar => None,
+        }
+    }
+
+    /// The names of the fields of a `Record`.
+    pub fn field_names(&self) -> Vec<&'static str> {
+        match self {
+            Shape::Record(fields) => {
+                fields.iter().map(|(name, _)| *name).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A type whose serialized form has a known `Shape`. Resources used to fill
+/// templates implement it so that templates can be checked against them.
+pub trait Schema {
+    fn shape() -> Shape;
+}
+
+/// Implement `Schema` for types serialized to a scalar, such as codes.
+macro_rules! scalar_schema {
+    ($($t:ty),*) => {
+        $(
+            impl Schema for $t {
+                fn shape() -> Shape {
+                    Shape::Scalar
+                }
+            }
+        )*
+    };
+}
+
+pub(crate) use scalar_schema;
+
+scalar_schema!(String, bool, u32, i64, f64, FHIRDate, FHIRDateTime);
+
+/// A missing value is serialized as `null`, which has no fields, so an
+/// optional value has the shape of the value.
+impl<T: Schema> Schema for Option<T> {
+    fn shape() -> Shape {
+        T::shape()
+    }
+}
+
+impl<T: Schema> Schema for Box<T> {
+    fn shape() -> Shape {
+        T::shape()
+    }
+}
+
+impl<T: Schema> Schema for Vec<T> {
+    fn shape() -> Shape {
+        Shape::List(Box::new(T::shape()))
+    }
+}
+
+/// Check that `shape` describes `data` exactly: every field of `data` is in
+/// `shape`, and every field of `shape` is in `data` unless it is `null`. Used
+/// to test `Schema` implementations against fully populated resources.
+#[cfg(test)]
+pub(crate) fn assert_shape_matches(shape: &Shape, data: &serde_json::Value) {
+    use serde_json::Value as Json;
+
+    match (shape, data) {
+        (Shape::Any, _) | (_, Json::Null) => {}
+        (Shape::Scalar, Json::Bool(_) | Json::Number(_) | Json::String(_)) => {}
+        (Shape::List(item), Json::Array(items)) => {
+            for data in items {
+                assert_shape_matches(item, data);
+            }
+        }
+        (Shape::Record(fields), Json::Object(map)) => {
+            for key in map.keys() {
+                assert!(
+                    shape.get(key).is_some(),
+                    "field `{}` is missing from the shape",
+                    key
+                );
+            }
+            for (name, field) in fields {
+                let data = map.get(*name).unwrap_or_else(|| {
+                    panic!("field `{}` is missing from the data", name)
+                });
+                assert_shape_matches(field, data);
+            }
+        }
+        (shape, data) => panic!("{:?} does not match {}", shape, data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_get() {
+        let shape = Shape::Record(vec![
+            ("given", Vec::<String>::shape()),
+            ("extension", Shape::Any),
+        ]);
+
+        assert_eq!(Some(&Shape::Scalar), shape.get("given").unwrap().get("0"));
+        assert_eq!(None, shape.get("given").unwrap().get("first"));
+        assert_eq!(None, shape.get("family"));
+        assert_eq!(None, Shape::Scalar.get("0"));
+        assert_eq!(Some(&Shape::Any), shape.get("extension").unwrap().get("x"));
+        assert_eq!(vec!["given", "extension"], shape.field_names());
+    }
+
+    #[test]
+    fn test_optional_values() {
+        assert_eq!(Shape::Scalar, Option::<FHIRDate>::shape());
+        assert_eq!(
+            Shape::List(Box::new(Shape::Scalar)),
+            Option::<Vec<String>>::shape()
+        );
+    }
+}
@@ -4,20 +4,47 @@ pub mod core;
 pub mod data;
 pub mod web;
 
-use crate::core::document::{DocumentTemplate, TagPair};
-use crate::core::parser;
-use config::DocugenConfig;
-use log::{error, info};
-use pretty_env_logger;
+use crate::core::cache::TemplateCache;
+use crate::core::compiled::CompiledTemplate;
+use crate::core::document::{DocumentTemplate, FilledDocument, RenderOptions};
+use crate::core::escape::Escape;
+use crate::core::filter::FilterRegistry;
+use crate::core::lint::lint;
+use crate::core::loader::{
+    localized_path, resolve_includes_listed, DirectorySource, TemplateSource,
+};
+use crate::core::messages::MessageCatalogue;
+use crate::core::metadata::{TemplateFile, TemplateMetadata};
+use crate::core::parser::{self, Delimiters};
+use crate::core::value::Value;
+use crate::data::allergy_intolerance::AllergyIntolerance;
+use crate::data::appointment::Appointment;
+use crate::data::condition::Condition;
+use crate::data::encounter::Encounter;
+use crate::data::fhir_date::FHIRDate;
+use crate::data::filters;
+use crate::data::locale::Locale;
+use crate::data::medication_request::MedicationRequest;
+use crate::data::observation::Observation;
+use crate::data::patient::Patient;
+use crate::data::schema::{Schema, Shape};
+use config::{DocugenConfig, TemplateConfig};
+use log::{error, info, warn};
+use std::collections::HashMap;
 use std::fs;
 use std::io::{self, Write};
 use std::path;
-use tokio;
 
 /// Default path to search for the configuration file. Defaults to `config.toml`
 /// under the project root or the binary root.
 const DEFAULT_CONFIG_PATH: &str = "config.toml";
 const DEFAULT_TEMPLATE_PATH: &str = "document.template";
+/// Path of the API serving the resources named by the `resource_type` of
+/// templates, e.g. `/api/Patient`.
+const API_PATH: &str = "/api";
+/// Directory within the templates directory that message catalogues are read
+/// from by default.
+const DEFAULT_MESSAGES_DIRECTORY: &str = "messages";
 
 #[tokio::main]
 async fn main() {
@@ -26,8 +53,13 @@ async fn main() {
     let matches = cli::cli().get_matches();
 
     let config_path = matches.value_of("config").unwrap_or(DEFAULT_CONFIG_PATH);
+
+    if let Some(lint_matches) = matches.subcommand_matches("lint") {
+        std::process::exit(lint_template(lint_matches, config_path));
+    }
+
     info!("Trying to read config from {}", &config_path);
-    let config = match read_config_from_path(&config_path) {
+    let config = match read_config_from_path(config_path) {
         Ok(cfg) => {
             info!("config given: {:?}", cfg);
             cfg
@@ -54,9 +86,71 @@ async fn main() {
         }
     }
 
-    let endpoint = matches
-        .value_of("ENDPOINT")
-        .expect("<ENDPOINT> is required");
+    let locale = match matches.value_of("locale").map(str::parse).transpose() {
+        Ok(locale) => locale
+            .or_else(|| config.templates.locale.clone())
+            .unwrap_or_default(),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1)
+        }
+    };
+    info!("Filling documents in locale {}", locale);
+
+    let template_path = matches
+        .value_of("TEMPLATE")
+        .unwrap_or(DEFAULT_TEMPLATE_PATH);
+    let (template, metadata) = match read_template_from_path(
+        template_path,
+        config.templates.directory.as_deref(),
+        config.templates.cache_directory.as_deref(),
+        config.templates.delimiters.clone(),
+        &locale,
+    ) {
+        Ok(template) => template,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+    };
+    info!("Template metadata: {:?}", metadata);
+
+    // Documents are filled for each `Patient`, or for each upcoming
+    // `Appointment` along with its `Patient`.
+    if let Some(resource_type) = &metadata.resource_type {
+        if resource_type != "Patient" && resource_type != "Appointment" {
+            eprintln!(
+                "error: templates filled with `{}` resources are not supported",
+                resource_type
+            );
+            std::process::exit(1)
+        }
+    }
+
+    let endpoint = match (matches.value_of("ENDPOINT"), &metadata.resource_type)
+    {
+        (Some(endpoint), _) => endpoint.to_string(),
+        (None, Some(resource_type)) => {
+            format!("{}/{}", API_PATH, resource_type)
+        }
+        (None, None) => {
+            eprintln!(
+                "error: no <ENDPOINT> given, and the template does not set \
+                 the `resource_type` it is filled with in its front matter"
+            );
+            std::process::exit(1)
+        }
+    };
+
+    for resource_type in &metadata.related {
+        if related_key(resource_type).is_none() {
+            eprintln!(
+                "error: templates cannot list related `{}` resources",
+                resource_type
+            );
+            std::process::exit(1)
+        }
+    }
 
     let protocol = if config.web_api.use_https {
         "https"
@@ -64,56 +158,601 @@ async fn main() {
         "http"
     };
 
-    let endpoint = format!(
-        "{}://{}:{}{}",
-        protocol, &config.web_api.ip_address, &config.web_api.port, &endpoint
+    let server = format!(
+        "{}://{}:{}",
+        protocol, &config.web_api.ip_address, &config.web_api.port
     );
+    let endpoint = format!("{}{}", server, &endpoint);
+    let api = format!("{}{}", server, API_PATH);
 
-    let patients = web::get_patients(&endpoint)
-        .await
-        .expect("failed to get patients from supplied endpoint");
+    let by_appointment =
+        metadata.resource_type.as_deref() == Some("Appointment");
+    let subjects = if by_appointment {
+        get_appointment_subjects(&endpoint, &api).await
+    } else {
+        get_patient_subjects(&endpoint).await
+    };
+    let subjects = match subjects {
+        Ok(subjects) => subjects,
+        Err(e) => {
+            eprintln!(
+                "error: failed to get resources from {}: {}",
+                endpoint, e
+            );
+            std::process::exit(1)
+        }
+    };
 
-    let template_path = matches
-        .value_of("TEMPLATE")
-        .unwrap_or(DEFAULT_TEMPLATE_PATH);
-    let template = read_template_from_path(&template_path)
-        .expect("failed to read template");
+    // The data of each document, with the related resources of its patient
+    // listed under their key, unless they could not be fetched.
+    let mut documents = Vec::with_capacity(subjects.len());
+    for subject in subjects {
+        let patient_id = subject.patient_id.as_deref();
+        let related = get_all_related(&api, &metadata.related, patient_id)
+            .await
+            .map(|related| {
+                let related = related
+                    .into_iter()
+                    .map(|(key, resources)| (key, Value::from(resources)));
+                subject.data.into_iter().chain(related).collect()
+            });
+        documents.push(related);
+    }
+    let subject_type = if by_appointment {
+        "appointment"
+    } else {
+        "patient"
+    };
 
-    for patient in &patients[..] {
-        // We require that each `Patient` has at least one full name.
-        assert!(!patient.names.is_empty());
+    let escape = matches
+        .value_of("format")
+        .and_then(Escape::from_format)
+        .or(metadata.format)
+        .or(config.templates.format)
+        .unwrap_or_else(|| {
+            Escape::for_template(path::Path::new(template_path))
+        });
+    info!("Escaping data with {:?}", escape);
+
+    let messages = match read_messages(
+        &config.templates,
+        path::Path::new(template_path),
+        &locale,
+    ) {
+        Ok(messages) => messages,
+        Err(e) => {
+            eprintln!("error: failed to read messages: {}", e);
+            std::process::exit(1)
+        }
+    };
 
-        let full_name = patient.names[0].clone();
+    let options = RenderOptions {
+        missing_tags: config.templates.missing_tags,
+        escape,
+        locale,
+        messages,
+        filters: filters::registry(),
+        ..RenderOptions::default()
+    };
 
-        let given = full_name.given.join(" ");
-        let family = match full_name.family {
-            Some(f) => f,
-            None => "".to_string(),
+    let output = matches.value_of("output").map(path::Path::new);
+    let filename =
+        match output.map(|_| filename_template(&metadata)).transpose() {
+            Ok(filename) => filename,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1)
+            }
         };
 
-        let full_name = format!("{} {}", given, family);
+    if matches.is_present("combined") {
+        // The combined report can be very large, so it is streamed to stdout,
+        // or to its file, rather than built in memory.
+        let documents =
+            match documents.into_iter().collect::<Result<Vec<_>, _>>() {
+                Ok(documents) => documents,
+                Err(e) => {
+                    error!("failed to fill the combined document");
+                    error!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+        let data = combined_data(by_appointment, documents);
+        // Data which does not fit the template is reported rather than
+        // panicking, and no partial document file is left behind.
+        let path = match (output, &filename) {
+            (Some(directory), Some(filename)) => {
+                match document_path(
+                    directory,
+                    filename,
+                    &data,
+                    &options.filters,
+                ) {
+                    Ok(path) => Some(path),
+                    Err(e) => {
+                        error!("failed to name the combined document");
+                        error!("{}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            _ => None,
+        };
+        let writer: Box<dyn Write> = match &path {
+            Some(path) => {
+                info!("Writing document to {}", path.display());
+                match fs::File::create(path) {
+                    Ok(file) => Box::new(file),
+                    Err(e) => {
+                        error!("failed to create the combined document");
+                        error!("{}: {}", path.display(), e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            None => Box::new(io::stdout()),
+        };
+        let mut writer = io::BufWriter::new(writer);
+        let written = template
+            .render_to(&data, &options, &mut writer)
+            .map_err(|e| e.to_string())
+            .and_then(|_| writer.flush().map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            error!("failed to fill the combined document");
+            error!("{}", e);
+            if let Some(path) = &path {
+                drop(writer);
+                let _ = fs::remove_file(path);
+            }
+            std::process::exit(1);
+        }
+    } else {
+        // A patient whose data does not fit the template should not stop the
+        // documents of the other patients from being generated. Each document
+        // is filled in memory first, so that no partial document is written
+        // for such a patient.
+        let mut failures = 0;
+        for (index, document) in documents.into_iter().enumerate() {
+            let written = document.and_then(|data: Vec<(&str, Value)>| {
+                let data = data.into_iter().collect();
+                let document = template
+                    .saturate_with(&data, &options)
+                    .map_err(|e| e.to_string())?;
+                match (output, &filename) {
+                    (Some(directory), Some(filename)) => {
+                        let path = document_path(
+                            directory,
+                            filename,
+                            &data,
+                            &options.filters,
+                        )?;
+                        info!("Writing document to {}", path.display());
+                        fs::write(&path, document.document())
+                            .map_err(|e| e.to_string())
+                    }
+                    _ => {
+                        write_document(&document);
+                        Ok(())
+                    }
+                }
+            });
+            if let Err(e) = written {
+                error!(
+                    "failed to fill template for {} #{}",
+                    subject_type, index
+                );
+                error!("{}", e);
+                failures += 1;
+            }
+        }
+
+        if failures > 0 {
+            error!("{} document(s) could not be generated", failures);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The data filling the document about a resource, before the related
+/// resources of its patient are added, and the id of that patient.
+struct Subject {
+    data: Vec<(&'static str, Value)>,
+    patient_id: Option<String>,
+}
 
-        let birth_date = patient.birth_date.to_string();
-        let name_tag = TagPair {
-            key: "name".to_string(),
-            value: full_name,
+/// The data filling the combined document, from the data of the `documents`
+/// of each patient or appointment. Patients are listed along with their
+/// related resources, e.g. as `patients.0.observations`, and appointments
+/// along with their patient and its related resources, e.g. as
+/// `appointments.0.patient` and `appointments.0.observations`.
+fn combined_data(
+    by_appointment: bool,
+    documents: Vec<Vec<(&'static str, Value)>>,
+) -> Value {
+    if by_appointment {
+        let appointments = documents
+            .into_iter()
+            .map(|data| data.into_iter().collect())
+            .collect::<Vec<Value>>();
+        return vec![("appointments", Value::from(appointments))]
+            .into_iter()
+            .collect();
+    }
+
+    let patients =
+        documents
+            .into_iter()
+            .map(|data| {
+                let (patient, related): (Vec<_>, Vec<_>) =
+                    data.into_iter().partition(|(key, _)| *key == "patient");
+                match patient.into_iter().next() {
+                    Some((_, Value::Map(mut fields))) => {
+                        fields.extend(related.into_iter().map(
+                            |(key, resources)| (key.to_string(), resources),
+                        ));
+                        Value::Map(fields)
+                    }
+                    Some((_, patient)) => patient,
+                    None => Value::Null,
+                }
+            })
+            .collect::<Vec<Value>>();
+    vec![("patients", Value::from(patients))]
+        .into_iter()
+        .collect()
+}
+
+/// Get the `Patient`s returned by `endpoint`, each filling a document.
+async fn get_patient_subjects(endpoint: &str) -> Result<Vec<Subject>, String> {
+    let patients = web::get_patients(endpoint)
+        .await
+        .map_err(|e| e.to_string())?;
+    patients
+        .iter()
+        .map(|patient| {
+            Ok(Subject {
+                data: vec![("patient", Value::from_serialize(patient)?)],
+                patient_id: patient.id.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Get the `Appointment`s returned by `endpoint` which are still to take
+/// place, each filling a document along with its `Patient` from the API at
+/// `api`. Appointments whose patient cannot be found or fetched are skipped.
+async fn get_appointment_subjects(
+    endpoint: &str,
+    api: &str,
+) -> Result<Vec<Subject>, String> {
+    let today = FHIRDate::today();
+    let appointments = web::get_appointments(endpoint)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut patients = HashMap::new();
+    let mut subjects = Vec::new();
+    for appointment in appointments {
+        if !appointment.is_upcoming_on(&today) {
+            continue;
+        }
+        let patient_id = match appointment.patient_id() {
+            Some(id) => id.to_string(),
+            None => {
+                warn!(
+                    "skipping appointment {:?} without a patient",
+                    appointment.id
+                );
+                continue;
+            }
         };
-        let birth_date_tag = TagPair {
-            key: "birth_date".to_string(),
-            value: birth_date,
+        if !patients.contains_key(&patient_id) {
+            let patient = match web::get_patient(api, &patient_id).await {
+                Ok(patient) => patient,
+                Err(e) => {
+                    warn!(
+                        "skipping appointment {:?}: failed to get patient {}: {}",
+                        appointment.id, patient_id, e
+                    );
+                    continue;
+                }
+            };
+            let patient = patient
+                .map(|patient| Value::from_serialize(&patient))
+                .transpose()?;
+            patients.insert(patient_id.clone(), patient);
+        }
+        let patient = match &patients[&patient_id] {
+            Some(patient) => patient.clone(),
+            None => {
+                warn!(
+                    "skipping appointment {:?} of unknown patient {}",
+                    appointment.id, patient_id
+                );
+                continue;
+            }
         };
+        subjects.push(Subject {
+            data: vec![
+                ("appointment", Value::from_serialize(&appointment)?),
+                ("patient", patient),
+            ],
+            patient_id: Some(patient_id),
+        });
+    }
+    info!("{} upcoming appointment(s) found", subjects.len());
 
-        let tag_pairs = vec![name_tag, birth_date_tag];
+    Ok(subjects)
+}
 
-        let output = template
-            .saturate(&tag_pairs)
-            .expect("failed to fill template with data fetched from API");
+fn write_document(document: &FilledDocument) {
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    handle
+        .write_all(document.document().as_bytes())
+        .expect("failed to write out");
+}
 
-        let stdout = io::stdout();
-        let mut handle = stdout.lock();
-        handle
-            .write_all(&output.document().as_bytes())
-            .expect("failed to write out");
+/// Read the message catalogue of `locale` from the configured messages
+/// directory, or from the `messages` directory within the templates directory.
+fn read_messages(
+    config: &TemplateConfig,
+    template_path: &path::Path,
+    locale: &Locale,
+) -> Result<MessageCatalogue, String> {
+    let directory = match &config.messages_directory {
+        Some(directory) => directory.clone(),
+        None => config
+            .directory
+            .clone()
+            .or_else(|| template_path.parent().map(path::Path::to_path_buf))
+            .unwrap_or_else(|| path::PathBuf::from("."))
+            .join(DEFAULT_MESSAGES_DIRECTORY),
+    };
+    info!(
+        "Reading messages from directory: \"{}\"",
+        directory.display()
+    );
+
+    MessageCatalogue::read_from_directory(&directory, locale)
+}
+
+/// The key of the data listing the related resources of type `resource_type`
+/// of a patient, if templates can list them, see `TemplateMetadata::related`.
+fn related_key(resource_type: &str) -> Option<&'static str> {
+    match resource_type {
+        "Observation" => Some("observations"),
+        "Condition" => Some("conditions"),
+        "AllergyIntolerance" => Some("allergies"),
+        "MedicationRequest" => Some("medications"),
+        "Encounter" => Some("encounters"),
+        _ => None,
+    }
+}
+
+/// The shape of the list of related resources of type `resource_type`.
+fn related_shape(resource_type: &str) -> Shape {
+    match resource_type {
+        "Observation" => Vec::<Observation>::shape(),
+        "Condition" => Vec::<Condition>::shape(),
+        "AllergyIntolerance" => Vec::<AllergyIntolerance>::shape(),
+        "MedicationRequest" => Vec::<MedicationRequest>::shape(),
+        "Encounter" => Vec::<Encounter>::shape(),
+        _ => Shape::Any,
+    }
+}
+
+/// Get the related resources of types `related` of the patient `patient_id`
+/// from the API at `api`, listed under their key. A patient without an id
+/// has none.
+async fn get_all_related(
+    api: &str,
+    related: &[String],
+    patient_id: Option<&str>,
+) -> Result<Vec<(&'static str, Vec<Value>)>, String> {
+    let mut resources = Vec::with_capacity(related.len());
+    for resource_type in related {
+        let listed = match patient_id {
+            Some(id) => {
+                get_related(api, resource_type, id).await.map_err(|e| {
+                    format!(
+                        "failed to get the related `{}` resources of patient \
+                         {}: {}",
+                        resource_type, id, e
+                    )
+                })?
+            }
+            None => {
+                warn!(
+                    "a patient without an id has no related `{}` resources",
+                    resource_type
+                );
+                Vec::new()
+            }
+        };
+        resources.push((related_key(resource_type).unwrap(), listed));
+    }
+    Ok(resources)
+}
+
+/// Get the related resources of type `resource_type` of the patient
+/// `patient_id` from the API at `api`, as template data.
+async fn get_related(
+    api: &str,
+    resource_type: &str,
+    patient_id: &str,
+) -> Result<Vec<Value>, String> {
+    match resource_type {
+        "Observation" => {
+            to_values(web::get_observations(api, patient_id).await)
+        }
+        "Condition" => to_values(web::get_conditions(api, patient_id).await),
+        "AllergyIntolerance" => {
+            to_values(web::get_allergy_intolerances(api, patient_id).await)
+        }
+        "MedicationRequest" => {
+            to_values(web::get_medication_requests(api, patient_id).await)
+        }
+        "Encounter" => to_values(web::get_encounters(api, patient_id).await),
+        _ => Err(format!("unknown related resource `{}`", resource_type)),
+    }
+}
+
+/// Convert the `resources` fetched from the API into template data.
+fn to_values<R: serde::Serialize>(
+    resources: Result<Vec<R>, Box<dyn std::error::Error>>,
+) -> Result<Vec<Value>, String> {
+    resources
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(Value::from_serialize)
+        .collect()
+}
+
+/// The shape of the data filling a template of resources of type
+/// `resource_type`, as it is filled by `main`, with the related resources of
+/// type `related` of the patients, see `combined_data`.
+fn data_shape(
+    combined: bool,
+    resource_type: Option<&str>,
+    related: &[String],
+) -> Shape {
+    let related = related.iter().filter_map(|resource_type| {
+        related_key(resource_type)
+            .map(|key| (key, related_shape(resource_type)))
+    });
+    let document = if resource_type == Some("Appointment") {
+        vec![
+            ("appointment", Appointment::shape()),
+            ("patient", Patient::shape()),
+        ]
+    } else {
+        vec![("patient", Patient::shape())]
+    };
+    let document = document.into_iter().chain(related.clone()).collect();
+
+    match (combined, resource_type) {
+        (false, _) => Shape::Record(document),
+        (true, Some("Appointment")) => Shape::Record(vec![(
+            "appointments",
+            Shape::List(Box::new(Shape::Record(document))),
+        )]),
+        (true, _) => {
+            let patient = match Patient::shape() {
+                Shape::Record(fields) => {
+                    Shape::Record(fields.into_iter().chain(related).collect())
+                }
+                shape => shape,
+            };
+            Shape::Record(vec![("patients", Shape::List(Box::new(patient)))])
+        }
+    }
+}
+
+/// The template naming the file each document is written to, from the
+/// `filename` of the front matter of the template filling the documents.
+fn filename_template(
+    metadata: &TemplateMetadata,
+) -> Result<DocumentTemplate, String> {
+    let filename = metadata.filename.as_deref().ok_or(
+        "the template does not set the `filename` of the documents in its \
+         front matter",
+    )?;
+    parser::parse_template(filename)
+        .map_err(|e| e.with_origin("filename").to_string())
+}
+
+/// The path of the file in `directory` which the document filled with `data`
+/// is written to, named by filling `filename` with `data` using `filters`.
+fn document_path(
+    directory: &path::Path,
+    filename: &DocumentTemplate,
+    data: &Value,
+    filters: &FilterRegistry,
+) -> Result<path::PathBuf, String> {
+    let options = RenderOptions {
+        filters: filters.clone(),
+        ..RenderOptions::default()
+    };
+    let filename = filename
+        .saturate_with(data, &options)
+        .map_err(|e| e.to_string())?;
+    // Data must not name a file outside of `directory`.
+    let filename = filename.document().replace(['/', '\\'], "_");
+    Ok(directory.join(filename))
+}
+
+/// Check the template given to the `lint` subcommand, printing the tags it
+/// needs to stdout and the problems found to stderr. Returns the exit status.
+fn lint_template(matches: &clap::ArgMatches, config_path: &str) -> i32 {
+    let path = matches
+        .value_of("TEMPLATE")
+        .expect("<TEMPLATE> is required");
+    let raw_template = match read_from_file(path::Path::new(path)) {
+        Ok(raw) => raw,
+        Err(e) => {
+            eprintln!("error: failed to read {}: {}", path, e);
+            return 1;
+        }
+    };
+
+    // Linting does not need a configuration file, but uses the templates
+    // directory and delimiters it sets if there is one.
+    let configured = if path::Path::new(config_path).exists() {
+        match read_config_from_path(config_path) {
+            Ok(config) => config.templates,
+            Err(e) => {
+                eprintln!("error: failed to read {}: {}", config_path, e);
+                return 1;
+            }
+        }
+    } else {
+        TemplateConfig::default()
+    };
+    let templates_directory = matches
+        .value_of("templates")
+        .map(path::PathBuf::from)
+        .or(configured.directory)
+        .or_else(|| path::Path::new(path).parent().map(path::Path::to_path_buf))
+        .unwrap_or_else(|| path::PathBuf::from("."));
+    let source = DirectorySource::new(templates_directory)
+        .with_delimiters(configured.delimiters);
+
+    // The data filling the template, as it is filled by `main`. Errors in
+    // the front matter are reported by `lint` itself.
+    let metadata = TemplateFile::parse(&raw_template)
+        .map(|file| file.metadata)
+        .unwrap_or_default();
+    let shape = data_shape(
+        matches.is_present("combined"),
+        metadata.resource_type.as_deref(),
+        &metadata.related,
+    );
+    let report = lint(
+        &raw_template,
+        path,
+        &source,
+        &filters::registry(),
+        Some(&shape),
+    );
+    for tag in &report.tags {
+        println!("{}", tag);
+    }
+    for lint in &report.lints {
+        eprintln!("{}\n", lint);
+    }
+    eprintln!(
+        "{}: {} error(s), {} warning(s)",
+        path,
+        report.errors(),
+        report.warnings()
+    );
+
+    let denied = matches.is_present("deny-warnings") && report.warnings() > 0;
+    if report.errors() > 0 || denied {
+        1
+    } else {
+        0
     }
 }
 
@@ -122,16 +761,14 @@ pub fn read_config_from_path(path: &str) -> Result<DocugenConfig, String> {
     info!("Trying to read configuration from path: \"{}\"", path);
     let path = path::Path::new(path);
 
-    // We require that the configuration file exists at the provided `path`.
-    // This will trigger a panic if the configuration file does not exist as it
-    // is likely a programmer mistake.
-    assert!(
-        path.exists(),
-        "Configuration file at path \"{:?}\" does not exist!",
-        &path
-    );
+    if !path.exists() {
+        return Err(format!(
+            "configuration file {} does not exist",
+            path.display()
+        ));
+    }
 
-    let raw_config = read_from_file(&path).map_err(|e| e.to_string())?;
+    let raw_config = read_from_file(path).map_err(|e| e.to_string())?;
     let config = parse_as_toml(&raw_config)?;
 
     info!("Config successfully parsed as TOML");
@@ -159,7 +796,22 @@ fn parse_as_toml(raw: &str) -> Result<DocugenConfig, String> {
     })
 }
 
-pub fn read_template_from_path(path: &str) -> Result<DocumentTemplate, String> {
+/// Attempt to read a template from a file of the given `path` and compile it,
+/// along with the metadata of its front matter, see `TemplateFile`.
+/// `Include`s are resolved against `templates_directory`, or the directory
+/// containing the template if no directory is given. If a `cache_directory`
+/// is given, the compiled template is loaded from it when neither the
+/// template nor its includes have changed, and stored in it otherwise. Tags
+/// of the template and its includes start delimited by `delimiters`. The
+/// variants of the template and its includes localized for `locale` are read
+/// instead of them if there are any, see `localized_path`.
+pub fn read_template_from_path(
+    path: &str,
+    templates_directory: Option<&path::Path>,
+    cache_directory: Option<&path::Path>,
+    delimiters: Delimiters,
+    locale: &Locale,
+) -> Result<(CompiledTemplate, TemplateMetadata), String> {
     info!("Trying to read template from path: \"{}\"", path);
 
     let path = path::Path::new(path);
@@ -169,13 +821,162 @@ pub fn read_template_from_path(path: &str) -> Result<DocumentTemplate, String> {
         "A template file does not exist or is unreadable at the provided path"
     );
 
-    let raw_template = read_from_file(&path).map_err(|e| e.to_string())?;
-    let template = parser::document_template()
-        .parse(raw_template.as_bytes())
+    let localized = localized_path(path, locale);
+    let path = localized.as_deref().unwrap_or(path);
+    info!(
+        "Reading template for locale {}: \"{}\"",
+        locale,
+        path.display()
+    );
+
+    let raw_file = read_from_file(path).map_err(|e| e.to_string())?;
+    let file = TemplateFile::parse(&raw_file)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let raw_template = file.template;
+
+    let templates_directory = templates_directory
+        .or_else(|| path.parent())
+        .unwrap_or_else(|| path::Path::new("."));
+    info!(
+        "Resolving includes against directory: \"{}\"",
+        templates_directory.display()
+    );
+    let source = DirectorySource::new(templates_directory)
+        .with_delimiters(delimiters)
+        .with_locale(locale.clone());
+
+    let cache = cache_directory.map(TemplateCache::new);
+    if let Some(template) =
+        cache.as_ref().and_then(|c| c.load(raw_template, &source))
+    {
+        info!("Compiled template loaded from cache");
+        return Ok((template, file.metadata));
+    }
+
+    let template =
+        parser::parse_template_with(raw_template, &source.delimiters())
+            .map_err(|e| {
+                e.with_origin(&path.display().to_string())
+                    .offset_lines(file.front_matter_lines)
+                    .to_string()
+            })?;
+    let (template, included) = resolve_includes_listed(&template, &source)
         .map_err(|e| e.to_string())?;
 
     info!("DocumentTemplate successfully parsed");
     info!("{:#?}", &template);
 
-    Ok(template)
+    let template = template.compile();
+    if let Some(cache) = &cache {
+        // A template which cannot be cached can still be used.
+        if let Err(e) = cache.store(raw_template, &source, &included, &template)
+        {
+            warn!("failed to cache compiled template: {}", e);
+        }
+    }
+
+    Ok((template, file.metadata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    /// The data of the document of a patient with observations of `tests`.
+    fn patient_document(
+        id: &str,
+        family: &str,
+        tests: &[&str],
+    ) -> Vec<(&'static str, Value)> {
+        let observations = tests
+            .iter()
+            .map(|test| json!({ "status": "final", "code": { "text": test } }))
+            .collect::<Vec<_>>();
+        vec![
+            (
+                "patient",
+                json!({ "id": id, "name": [{ "family": family }] }).into(),
+            ),
+            ("observations", json!(observations).into()),
+        ]
+    }
+
+    /// Fill `source` with `data`, after checking it against `shape`.
+    fn fill(source: &str, data: &Value, shape: &Shape) -> String {
+        let templates = HashMap::<String, String>::new();
+        let options = RenderOptions {
+            filters: filters::registry(),
+            ..RenderOptions::default()
+        };
+        let report =
+            lint(source, "test", &templates, &options.filters, Some(shape));
+        assert_eq!(0, report.lints.len(), "{:?}", report.lints);
+        let template = parser::parse_template(source).unwrap();
+        let document = template.saturate_with(data, &options).unwrap();
+        document.document().to_string()
+    }
+
+    #[test]
+    fn test_combined_patients_list_their_related_resources() {
+        let documents = vec![
+            patient_document("1", "Smith", &["Glucose", "Sodium"]),
+            patient_document("2", "Jones", &["Potassium"]),
+        ];
+        let data = combined_data(false, documents);
+        let shape =
+            data_shape(true, Some("Patient"), &["Observation".to_string()]);
+
+        assert_eq!(
+            "Smith: Glucose Sodium\nJones: Potassium\n",
+            fill(
+                "{{#patients}}{{ name | name(\"family\") }}:\
+                 {{#observations}} {{ code.text }}{{/observations}}\n\
+                 {{/patients}}",
+                &data,
+                &shape,
+            )
+        );
+    }
+
+    #[test]
+    fn test_combined_appointments_list_their_patient() {
+        let documents = vec![
+            patient_document("1", "Smith", &["Glucose"]),
+            patient_document("2", "Jones", &[]),
+        ]
+        .into_iter()
+        .zip(&["a1", "a2"])
+        .map(|(mut data, id)| {
+            let appointment = json!({ "id": id, "status": "booked" });
+            data.insert(0, ("appointment", appointment.into()));
+            data
+        })
+        .collect();
+        let data = combined_data(true, documents);
+        let shape =
+            data_shape(true, Some("Appointment"), &["Observation".to_string()]);
+
+        assert_eq!(
+            "a1 Smith: Glucose\na2 Jones:\n",
+            fill(
+                "{{#appointments}}{{ appointment.id }} \
+                 {{ patient.name | name(\"family\") }}:\
+                 {{#observations}} {{ code.text }}{{/observations}}\n\
+                 {{/appointments}}",
+                &data,
+                &shape,
+            )
+        );
+    }
+
+    #[test]
+    fn test_read_missing_config() {
+        assert_eq!(
+            Err("configuration file missing.toml does not exist".to_string()),
+            read_config_from_path("missing.toml")
+        );
+    }
 }
@@ -1,29 +1,123 @@
+use super::data::allergy_intolerance::AllergyIntolerance;
+use super::data::appointment::Appointment;
+use super::data::condition::Condition;
+use super::data::encounter::Encounter;
+use super::data::medication_request::MedicationRequest;
+use super::data::observation::Observation;
 use super::data::patient::Patient;
 use log::{debug, error, info};
 use reqwest;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use std::fmt::Debug;
 
-/// Patient data from the FHIR web API is returned in `Bundle`s of approximately
-/// 10 `Patient`s each. The `Patient`s are themselves encapsulated by an `Entry`
-/// wrapper.
+/// Resources from the FHIR web API are returned in `Bundle`s of approximately
+/// 10 resources each, e.g. `Patient`s. The resources are themselves
+/// encapsulated by an `Entry` wrapper. A `Bundle` of a search without results
+/// has no entries.
 #[derive(Debug, PartialEq, Deserialize)]
-pub struct Bundle {
+pub struct Bundle<R> {
     id: String,
-    #[serde(rename = "entry")]
-    entries: Vec<Entry>,
+    #[serde(rename = "entry", default = "Vec::new")]
+    entries: Vec<Entry<R>>,
 }
 
-/// Each `Entry` encapsulates a `Patient` and provides additional metadata.
+/// Each `Entry` encapsulates a resource and provides additional metadata.
 #[derive(Debug, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct Entry {
-    resource: Patient,
+pub struct Entry<R> {
+    resource: R,
 }
 
 pub async fn get_patients(
     endpoint: &str,
 ) -> Result<Vec<Patient>, Box<dyn std::error::Error>> {
-    info!("Requesting patient data from {}", endpoint);
+    get_resources(endpoint).await
+}
+
+/// Get the `Patient` of id `patient_id` from the API at `api`, if there is
+/// one.
+pub async fn get_patient(
+    api: &str,
+    patient_id: &str,
+) -> Result<Option<Patient>, Box<dyn std::error::Error>> {
+    let endpoint =
+        query_endpoint(&format!("{}/Patient", api), "_id", patient_id)?;
+    Ok(get_resources(&endpoint).await?.into_iter().next())
+}
+
+pub async fn get_appointments(
+    endpoint: &str,
+) -> Result<Vec<Appointment>, Box<dyn std::error::Error>> {
+    get_resources(endpoint).await
+}
+
+/// Get the `Observation`s of the patient `patient_id` from the API at `api`,
+/// e.g. `https://127.0.0.1:8001/api`. The other resources about a patient
+/// are fetched likewise.
+pub async fn get_observations(
+    api: &str,
+    patient_id: &str,
+) -> Result<Vec<Observation>, Box<dyn std::error::Error>> {
+    get_resources(&search_endpoint(api, "Observation", patient_id)?).await
+}
+
+pub async fn get_conditions(
+    api: &str,
+    patient_id: &str,
+) -> Result<Vec<Condition>, Box<dyn std::error::Error>> {
+    get_resources(&search_endpoint(api, "Condition", patient_id)?).await
+}
+
+pub async fn get_allergy_intolerances(
+    api: &str,
+    patient_id: &str,
+) -> Result<Vec<AllergyIntolerance>, Box<dyn std::error::Error>> {
+    get_resources(&search_endpoint(api, "AllergyIntolerance", patient_id)?)
+        .await
+}
+
+pub async fn get_encounters(
+    api: &str,
+    patient_id: &str,
+) -> Result<Vec<Encounter>, Box<dyn std::error::Error>> {
+    get_resources(&search_endpoint(api, "Encounter", patient_id)?).await
+}
+
+pub async fn get_medication_requests(
+    api: &str,
+    patient_id: &str,
+) -> Result<Vec<MedicationRequest>, Box<dyn std::error::Error>> {
+    get_resources(&search_endpoint(api, "MedicationRequest", patient_id)?).await
+}
+
+/// The endpoint of the API at `api` searching the resources of type
+/// `resource_type` about the patient `patient_id`.
+fn search_endpoint(
+    api: &str,
+    resource_type: &str,
+    patient_id: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    query_endpoint(&format!("{}/{}", api, resource_type), "patient", patient_id)
+}
+
+/// `endpoint` queried for `key=value`. The value is percent-encoded, so that
+/// ids cannot add parameters of their own to the query.
+fn query_endpoint(
+    endpoint: &str,
+    key: &str,
+    value: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut url = reqwest::Url::parse(endpoint)?;
+    url.query_pairs_mut().append_pair(key, value);
+    Ok(url.into())
+}
+
+/// Get the resources of the `Bundle`s returned by `endpoint`.
+pub async fn get_resources<R: DeserializeOwned + Debug>(
+    endpoint: &str,
+) -> Result<Vec<R>, Box<dyn std::error::Error>> {
+    info!("Requesting resources from {}", endpoint);
 
     let response = reqwest::Client::builder()
         .danger_accept_invalid_certs(true)
@@ -32,21 +126,20 @@ pub async fn get_patients(
         .send()
         .await?
         .text()
-        .await
-        .expect("failed to get request body");
+        .await?;
 
-    println!("{}", &response[0..50]);
+    debug!("Response body = {}", &response);
 
-    let response: Vec<Bundle> = match serde_json::from_str(&response) {
+    let response: Vec<Bundle<R>> = match serde_json::from_str(&response) {
         Ok(r) => r,
         Err(e) => {
-            error!("Failed to parse response!");
+            error!("Failed to parse response from {}!", endpoint);
             error!("{:#?}", e);
-            std::process::exit(1);
+            return Err(e.into());
         }
     };
 
-    // We need to pull `Patient` out of the various layers.
+    // We need to pull the resources out of the various layers.
     let response = response
         .into_iter()
         .flat_map(|r| r.entries)
@@ -83,6 +176,76 @@ mod tests {
             }
         ]"#;
 
-        assert!(serde_json::from_str::<Vec<Bundle>>(raw).is_ok());
+        assert!(serde_json::from_str::<Vec<Bundle<Patient>>>(raw).is_ok());
+    }
+
+    #[test]
+    fn test_observation_bundles() {
+        let raw = r#"[
+            {
+                "id": "1",
+                "entry": [
+                    {
+                        "resource": {
+                            "status": "final",
+                            "code": {"text": "Heart rate"},
+                            "valueQuantity": {"value": 72, "unit": "/min"}
+                        }
+                    }
+                ]
+            },
+            {"id": "2"}
+        ]"#;
+
+        let bundles = serde_json::from_str::<Vec<Bundle<Observation>>>(raw);
+        assert_eq!(
+            vec![1, 0],
+            bundles
+                .unwrap()
+                .iter()
+                .map(|bundle| bundle.entries.len())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "https://127.0.0.1/api/Observation?patient=12",
+            search_endpoint("https://127.0.0.1/api", "Observation", "12")
+                .unwrap()
+        );
+        assert_eq!(
+            "https://127.0.0.1/api/Observation?patient=12%26_id%3D1",
+            search_endpoint("https://127.0.0.1/api", "Observation", "12&_id=1")
+                .unwrap()
+        );
+        assert!(search_endpoint("127.0.0.1/api", "Observation", "12").is_err());
+    }
+
+    #[test]
+    fn test_appointment_bundles() {
+        let raw = r#"[
+            {
+                "id": "1",
+                "entry": [
+                    {
+                        "resource": {
+                            "resourceType": "Appointment",
+                            "status": "booked",
+                            "start": "2020-03-04T09:30:00Z",
+                            "participant": [
+                                {
+                                    "actor": {"reference": "Patient/12"},
+                                    "status": "accepted"
+                                }
+                            ]
+                        }
+                    }
+                ]
+            }
+        ]"#;
+
+        let bundles = serde_json::from_str::<Vec<Bundle<Appointment>>>(raw);
+        assert_eq!(
+            Some("12"),
+            bundles.unwrap()[0].entries[0].resource.patient_id()
+        );
     }
 }
@@ -1,7 +1,25 @@
-use docugen::core::document::TagPair;
+use docugen::core::document::RenderOptions;
+use docugen::core::loader::{resolve_includes, DirectorySource};
+use docugen::core::metadata::TemplateFile;
 use docugen::core::parser::document_template;
+use docugen::core::value::Value;
+use docugen::data::allergy_intolerance::AllergyIntolerance;
+use docugen::data::appointment::Appointment;
+use docugen::data::condition::Condition;
+use docugen::data::filters;
+use docugen::data::medication_request::MedicationRequest;
+use docugen::data::observation::Observation;
+use docugen::data::patient::Patient;
 use log::debug;
 
+/// Fill templates with the filters of FHIR resources, as `docugen_bin` does.
+fn options() -> RenderOptions {
+    RenderOptions {
+        filters: filters::registry(),
+        ..RenderOptions::default()
+    }
+}
+
 #[test]
 fn test_string_to_template() -> Result<(), String> {
     let raw = r#"
@@ -17,30 +35,17 @@ Sincerely,
 "#;
 
     let template = document_template().parse(raw.as_bytes()).unwrap();
-    let tag_pairs: &[TagPair] = &[
-        TagPair {
-            key: "receiver_name".to_string(),
-            value: "Moon Moon".to_string(),
-        },
-        TagPair {
-            key: "attachment_name".to_string(),
-            value: "Word.exe".to_string(),
-        },
-        TagPair {
-            key: "sender_name".to_string(),
-            value: "Shiba Inu".to_string(),
-        },
-        TagPair {
-            key: "sender_title".to_string(),
-            value: "Professuer of Bepis Studies".to_string(),
-        },
-        TagPair {
-            key: "hospital_name".to_string(),
-            value: "Dogeland Hospital".to_string(),
-        },
-    ];
+    let data: Value = vec![
+        ("receiver_name", "Moon Moon"),
+        ("attachment_name", "Word.exe"),
+        ("sender_name", "Shiba Inu"),
+        ("sender_title", "Professuer of Bepis Studies"),
+        ("hospital_name", "Dogeland Hospital"),
+    ]
+    .into_iter()
+    .collect();
 
-    let filled_document = template.saturate(tag_pairs).unwrap();
+    let filled_document = template.saturate_with(&data, &options()).unwrap();
     let actual_filled_content = filled_document.document();
 
     let expected_filled_content = r#"
@@ -62,3 +67,307 @@ Dogeland Hospital
 
     Ok(())
 }
+
+#[test]
+fn test_list_report() -> Result<(), String> {
+    let raw = "{{#patients}}{{ name }} ({{ birth_date }})\n{{/patients}}";
+
+    let template = document_template().parse(raw.as_bytes()).unwrap();
+    let patients = vec![
+        vec![("name", "Moon Moon"), ("birth_date", "2013-05-01")]
+            .into_iter()
+            .collect::<Value>(),
+        vec![("name", "Shiba Inu"), ("birth_date", "2010-11-02")]
+            .into_iter()
+            .collect::<Value>(),
+    ];
+    let data = vec![("patients", patients)].into_iter().collect();
+
+    let filled_document = template.saturate_with(&data, &options()).unwrap();
+
+    assert_eq!(
+        "Moon Moon (2013-05-01)\nShiba Inu (2010-11-02)\n",
+        filled_document.document()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_patient_template() -> Result<(), String> {
+    let raw_patient = r#"{
+        "name": [{ "given": ["Jane", "Anne"], "family": "Smith" }],
+        "birthDate": "2012-03-04"
+    }"#;
+    let patient = serde_json::from_str::<Patient>(raw_patient)
+        .map_err(|e| e.to_string())?;
+
+    let raw = "{{#patient.name.0}}{{#given}}{{.}} {{/given}}{{family}}\
+               {{/patient.name.0}}: {{ patient.birthDate }}";
+    let template = document_template().parse(raw.as_bytes()).unwrap();
+    let data = vec![("patient", Value::from_serialize(&patient)?)]
+        .into_iter()
+        .collect();
+
+    let filled_document = template.saturate_with(&data, &options()).unwrap();
+
+    assert_eq!("Jane Anne Smith: 2012-03-04", filled_document.document());
+
+    Ok(())
+}
+
+#[test]
+fn test_template_with_includes() -> Result<(), String> {
+    let raw = std::fs::read_to_string("template.template")
+        .map_err(|e| e.to_string())?;
+    let file = TemplateFile::parse(&raw).map_err(|e| e.to_string())?;
+    assert_eq!(Some("Patient"), file.metadata.resource_type.as_deref());
+    let template = document_template().parse(file.template.as_bytes()).unwrap();
+    let template =
+        resolve_includes(&template, &DirectorySource::new("templates"))
+            .map_err(|e| e.to_string())?;
+
+    let patient = r#"{ "name": [{ "given": ["Jane"], "family": "Smith" }],
+                       "birthDate": "2012-03-04" }"#;
+    let patient =
+        serde_json::from_str::<Patient>(patient).map_err(|e| e.to_string())?;
+    let data = vec![("patient", Value::from_serialize(&patient)?)]
+        .into_iter()
+        .collect();
+
+    let filled_document = template.saturate_with(&data, &options()).unwrap();
+
+    assert!(filled_document
+        .document()
+        .starts_with("Great Ormond Street Hospital for Children\n"));
+    assert!(filled_document
+        .document()
+        .contains("Name:\t\tSMITH, Jane\nBirthdate:\t04 March 2012\n"));
+    assert!(filled_document.document().contains("Dear Jane Smith,\n"));
+    assert!(filled_document
+        .document()
+        .trim_end()
+        .ends_with("The Hospital"));
+
+    Ok(())
+}
+
+#[test]
+fn test_list_template_with_standalone_lines() -> Result<(), String> {
+    let raw =
+        std::fs::read_to_string("templates/patient_birthdates_list.template")
+            .map_err(|e| e.to_string())?;
+    let template = document_template().parse(raw.as_bytes()).unwrap();
+
+    let patient = r#"{ "name": [{ "given": ["Jane"], "family": "Smith" }],
+                       "birthDate": "2012-03-04" }"#;
+    let patient =
+        serde_json::from_str::<Patient>(patient).map_err(|e| e.to_string())?;
+    let data = vec![("patients", vec![Value::from_serialize(&patient)?])]
+        .into_iter()
+        .collect();
+
+    assert_eq!(
+        "Patients and their recorded birthdates:\n- Jane Smith: 2012-03-04\n",
+        template
+            .saturate_with(&data, &options())
+            .unwrap()
+            .document()
+    );
+
+    let data = vec![("patients", Vec::<Value>::new())]
+        .into_iter()
+        .collect();
+
+    assert_eq!(
+        "Patients and their recorded birthdates:\nNo patients were found.\n",
+        template
+            .saturate_with(&data, &options())
+            .unwrap()
+            .document()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_conditional_on_patient_age() -> Result<(), String> {
+    let raw = "{{#if patient.birthDate | age(\"2020-03-04\") >= 18}}\n\
+               Dear {{ patient.name.0.given.0 }},\n\
+               {{else if patient.name.0.family}}\n\
+               To the parent or guardian of {{ patient.name.0.given.0 }} \
+               {{ patient.name.0.family }},\n\
+               {{else}}\n\
+               To the parent or guardian,\n\
+               {{/if}}\n";
+    let template = document_template().parse(raw.as_bytes()).unwrap();
+
+    let patient = r#"{ "name": [{ "given": ["Jane"], "family": "Smith" }],
+                       "birthDate": "2012-03-04" }"#;
+    let patient =
+        serde_json::from_str::<Patient>(patient).map_err(|e| e.to_string())?;
+    let data = vec![("patient", Value::from_serialize(&patient)?)]
+        .into_iter()
+        .collect();
+
+    assert_eq!(
+        "To the parent or guardian of Jane Smith,\n",
+        template
+            .saturate_with(&data, &options())
+            .unwrap()
+            .document()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_observation_results_template() -> Result<(), String> {
+    let raw = std::fs::read_to_string("templates/observation_results.template")
+        .map_err(|e| e.to_string())?;
+    let file = TemplateFile::parse(&raw).map_err(|e| e.to_string())?;
+    assert_eq!(vec!["Observation".to_string()], file.metadata.related);
+    let template = document_template().parse(file.template.as_bytes()).unwrap();
+    let template =
+        resolve_includes(&template, &DirectorySource::new("templates"))
+            .map_err(|e| e.to_string())?;
+
+    let patient =
+        r#"{ "id": "1", "name": [{ "given": ["Jane"], "family": "Smith" }] }"#;
+    let patient =
+        serde_json::from_str::<Patient>(patient).map_err(|e| e.to_string())?;
+    let observations = r#"[
+        { "status": "final", "code": { "text": "Glucose" },
+          "effectiveDateTime": "2020-03-04T09:30:00Z",
+          "valueQuantity": { "value": 6.3, "unit": "mmol/l" } },
+        { "status": "final", "code": { "text": "Blood group" },
+          "effectiveDateTime": "2020-03-05",
+          "valueString": "O+" },
+        { "status": "final", "code": { "text": "Smoker" },
+          "effectivePeriod": { "start": "2019", "end": "2020-03" },
+          "valueBoolean": false },
+        { "status": "final", "code": { "text": "Pregnancies" },
+          "effectiveInstant": "2020-03-06T10:00:00Z",
+          "valueInteger": 0 },
+        { "status": "final", "code": { "text": "Titre" },
+          "valueRatio": { "numerator": { "value": 1 },
+                          "denominator": { "value": 128 } } },
+        { "status": "registered", "code": { "text": "Biopsy" } }
+    ]"#;
+    let observations = serde_json::from_str::<Vec<Observation>>(observations)
+        .map_err(|e| e.to_string())?;
+    let data = vec![
+        ("patient", Value::from_serialize(&patient)?),
+        ("observations", Value::from_serialize(&observations)?),
+    ]
+    .into_iter()
+    .collect();
+
+    let filled_document = template.saturate_with(&data, &options()).unwrap();
+
+    assert!(filled_document.document().contains(
+        "tests:\n\n- Glucose: 6.3 mmol/l (4 March 2020)\n\
+         - Blood group: O+ (5 March 2020)\n\
+         - Smoker: no (2019 to 2020-03)\n\
+         - Pregnancies: 0 (6 March 2020)\n\
+         - Titre: 1 / 128\n\
+         - Biopsy: -\n"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_discharge_summary_template() -> Result<(), String> {
+    let raw = std::fs::read_to_string("templates/discharge_summary.template")
+        .map_err(|e| e.to_string())?;
+    let file = TemplateFile::parse(&raw).map_err(|e| e.to_string())?;
+    let template = document_template().parse(file.template.as_bytes()).unwrap();
+    let template =
+        resolve_includes(&template, &DirectorySource::new("templates"))
+            .map_err(|e| e.to_string())?;
+
+    let patient =
+        r#"{ "id": "1", "name": [{ "given": ["Jane"], "family": "Smith" }] }"#;
+    let patient =
+        serde_json::from_str::<Patient>(patient).map_err(|e| e.to_string())?;
+    let conditions = r#"[
+        { "code": { "text": "Asthma" }, "subject": { "reference": "Patient/1" } }
+    ]"#;
+    let conditions = serde_json::from_str::<Vec<Condition>>(conditions)
+        .map_err(|e| e.to_string())?;
+    let allergies = r#"[
+        { "code": { "text": "Penicillin" }, "criticality": "high",
+          "patient": { "reference": "Patient/1" } }
+    ]"#;
+    let allergies = serde_json::from_str::<Vec<AllergyIntolerance>>(allergies)
+        .map_err(|e| e.to_string())?;
+    let medications: Vec<MedicationRequest> = Vec::new();
+    let data = vec![
+        ("patient", Value::from_serialize(&patient)?),
+        ("conditions", Value::from_serialize(&conditions)?),
+        ("allergies", Value::from_serialize(&allergies)?),
+        ("medications", Value::from_serialize(&medications)?),
+    ]
+    .into_iter()
+    .collect();
+
+    let filled_document = template.saturate_with(&data, &options()).unwrap();
+
+    assert!(filled_document.document().contains(
+        "Problems:\n- Asthma\n\n\
+         Allergies:\n- Penicillin (high criticality)\n\n\
+         Medications:\nNone prescribed.\n"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_appointment_reminder_template() -> Result<(), String> {
+    let raw =
+        std::fs::read_to_string("templates/appointment_reminder.template")
+            .map_err(|e| e.to_string())?;
+    let file = TemplateFile::parse(&raw).map_err(|e| e.to_string())?;
+    assert_eq!(Some("Appointment"), file.metadata.resource_type.as_deref());
+    let template = document_template().parse(file.template.as_bytes()).unwrap();
+    let template =
+        resolve_includes(&template, &DirectorySource::new("templates"))
+            .map_err(|e| e.to_string())?;
+
+    let patient =
+        r#"{ "id": "1", "name": [{ "given": ["Jane"], "family": "Smith" }] }"#;
+    let patient =
+        serde_json::from_str::<Patient>(patient).map_err(|e| e.to_string())?;
+    let appointment = r#"{
+        "id": "a1", "status": "booked", "description": "Asthma review",
+        "start": "2020-03-04T09:30:00Z",
+        "participant": [
+            { "actor": { "reference": "Patient/1" }, "status": "accepted" },
+            { "actor": { "reference": "Location/2", "display": "Clinic 4" },
+              "status": "accepted" }
+        ]
+    }"#;
+    let appointment = serde_json::from_str::<Appointment>(appointment)
+        .map_err(|e| e.to_string())?;
+    assert_eq!(Some("1"), appointment.patient_id());
+    let data = vec![
+        ("appointment", Value::from_serialize(&appointment)?),
+        ("patient", Value::from_serialize(&patient)?),
+    ]
+    .into_iter()
+    .collect();
+
+    let filled_document = template.saturate_with(&data, &options()).unwrap();
+
+    assert!(filled_document.document().contains(
+        "Dear Jane Smith,\n\n\
+         This is a reminder of your appointment on 4 March 2020 at 09:30: \
+         Asthma review.\n\n\
+         Where: Clinic 4\n\
+         With: the clinical team\n\n\
+         If you cannot attend"
+    ));
+
+    Ok(())
+}